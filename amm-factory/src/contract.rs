@@ -0,0 +1,319 @@
+use std::str::FromStr;
+
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    attr, to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Order, Reply, Response,
+    StdError, StdResult, SubMsg, WasmMsg,
+};
+use cw0::parse_reply_instantiate_data;
+use cw2::set_contract_version;
+use cw20::Denom;
+use cw_storage_plus::{Bound, PrimaryKey};
+
+use crate::error::ContractError;
+use crate::msg::{
+    ConfigResponse, ConstantSumParams, ExecuteMsg, InstantiateMsg, PairsResponse, QueryMsg,
+};
+use crate::state::{
+    order_asset_infos, pair_key, pairs, Config, PairInfo, PairType, PendingPair, CONFIG,
+    PENDING_PAIR,
+};
+
+// Version info for migration info
+pub const CONTRACT_NAME: &str = "crates.io:amm-factory";
+pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Same maximum as the pairs, so a pair can't fail to instantiate because of the defaults
+const MAX_FEE_PERCENT: &str = "1";
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+const INSTANTIATE_PAIR_REPLY_ID: u64 = 0;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    validate_fee_percent(msg.lp_fee_percent, msg.protocol_fee_percent)?;
+
+    let config = Config {
+        owner: msg.owner.map(|h| deps.api.addr_validate(&h)).transpose()?,
+        constant_product_code_id: msg.constant_product_code_id,
+        constant_sum_code_id: msg.constant_sum_code_id,
+        lp_token_code_id: msg.lp_token_code_id,
+        protocol_fee_recipient: deps.api.addr_validate(&msg.protocol_fee_recipient)?,
+        protocol_fee_percent: msg.protocol_fee_percent,
+        lp_fee_percent: msg.lp_fee_percent,
+    };
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("key", "instantiate"))
+}
+
+fn validate_fee_percent(
+    lp_fee_percent: Decimal,
+    protocol_fee_percent: Decimal,
+) -> Result<(), ContractError> {
+    let total_fee_percent = lp_fee_percent + protocol_fee_percent;
+    let max_fee_percent = Decimal::from_str(MAX_FEE_PERCENT)?;
+    if total_fee_percent > max_fee_percent {
+        return Err(ContractError::FeesTooHigh {
+            max_fee_percent,
+            total_fee_percent,
+        });
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::CreatePair {
+            pair_type,
+            asset_infos,
+            constant_sum_params,
+        } => execute_create_pair(deps, pair_type, asset_infos, constant_sum_params),
+        ExecuteMsg::UpdateConfig {
+            owner,
+            constant_product_code_id,
+            constant_sum_code_id,
+            lp_token_code_id,
+            protocol_fee_recipient,
+            protocol_fee_percent,
+            lp_fee_percent,
+        } => execute_update_config(
+            deps,
+            info,
+            owner,
+            constant_product_code_id,
+            constant_sum_code_id,
+            lp_token_code_id,
+            protocol_fee_recipient,
+            protocol_fee_percent,
+            lp_fee_percent,
+        ),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    owner: Option<String>,
+    constant_product_code_id: Option<u64>,
+    constant_sum_code_id: Option<u64>,
+    lp_token_code_id: Option<u64>,
+    protocol_fee_recipient: Option<String>,
+    protocol_fee_percent: Option<Decimal>,
+    lp_fee_percent: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if config.owner != Some(info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(owner) = owner {
+        config.owner = Some(deps.api.addr_validate(&owner)?);
+    }
+    if let Some(code_id) = constant_product_code_id {
+        config.constant_product_code_id = code_id;
+    }
+    if let Some(code_id) = constant_sum_code_id {
+        config.constant_sum_code_id = code_id;
+    }
+    if let Some(code_id) = lp_token_code_id {
+        config.lp_token_code_id = code_id;
+    }
+    if let Some(recipient) = protocol_fee_recipient {
+        config.protocol_fee_recipient = deps.api.addr_validate(&recipient)?;
+    }
+    config.protocol_fee_percent = protocol_fee_percent.unwrap_or(config.protocol_fee_percent);
+    config.lp_fee_percent = lp_fee_percent.unwrap_or(config.lp_fee_percent);
+    validate_fee_percent(config.lp_fee_percent, config.protocol_fee_percent)?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("action", "update_config"))
+}
+
+fn validate_denom(deps: Deps, denom: Denom) -> StdResult<Denom> {
+    match denom {
+        Denom::Cw20(addr) => Ok(Denom::Cw20(deps.api.addr_validate(addr.as_str())?)),
+        native => Ok(native),
+    }
+}
+
+pub fn execute_create_pair(
+    deps: DepsMut,
+    pair_type: PairType,
+    asset_infos: [Denom; 2],
+    constant_sum_params: Option<ConstantSumParams>,
+) -> Result<Response, ContractError> {
+    let [first, second] = asset_infos;
+    let asset_infos = order_asset_infos([
+        validate_denom(deps.as_ref(), first)?,
+        validate_denom(deps.as_ref(), second)?,
+    ]);
+    let key = pair_key(&asset_infos);
+    if key.0 == key.1 {
+        return Err(ContractError::IdenticalAssets {});
+    }
+    if pairs().may_load(deps.storage, key.clone())?.is_some() {
+        return Err(ContractError::PairExists {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let token1_denom = asset_infos[0].clone();
+    let token2_denom = asset_infos[1].clone();
+    let owner = config.owner.as_ref().map(|owner| owner.to_string());
+    let protocol_fee_recipient = config.protocol_fee_recipient.to_string();
+    // Each pool contract takes its own instantiate message
+    let (code_id, msg) = match (&pair_type, constant_sum_params) {
+        (PairType::ConstantProduct, None) => (
+            config.constant_product_code_id,
+            to_binary(&constant_product_amm::msg::InstantiateMsg {
+                token1_denom,
+                token2_denom,
+                owner: owner.clone(),
+                protocol_fee_recipient,
+                protocol_fee_percent: config.protocol_fee_percent,
+                lp_fee_percent: config.lp_fee_percent,
+                lp_token_code_id: config.lp_token_code_id,
+                amp: None,
+                concentrated: None,
+            })?,
+        ),
+        (PairType::ConstantProduct, Some(_)) => return Err(ContractError::UnexpectedPairParams {}),
+        (PairType::ConstantSum, params) => {
+            let params = params.unwrap_or(ConstantSumParams {
+                rate: None,
+                depeg_protection: None,
+            });
+            (
+                config.constant_sum_code_id,
+                to_binary(&constant_sum_amm::msg::InstantiateMsg {
+                    token1_denom,
+                    token2_denom,
+                    owner: owner.clone(),
+                    protocol_fee_recipient,
+                    protocol_fee_percent: config.protocol_fee_percent,
+                    lp_fee_percent: config.lp_fee_percent,
+                    lp_token_code_id: config.lp_token_code_id,
+                    depeg_protection: params.depeg_protection,
+                    rate: params.rate,
+                })?,
+            )
+        }
+    };
+    let instantiate_pair_msg = WasmMsg::Instantiate {
+        code_id,
+        funds: vec![],
+        // Pairs can be migrated by the factory owner
+        admin: owner,
+        label: format!("{}-{}", key.0, key.1),
+        msg,
+    };
+    // The pair is only registered once the reply has its address
+    PENDING_PAIR.save(
+        deps.storage,
+        &PendingPair {
+            asset_infos,
+            pair_type,
+        },
+    )?;
+    let reply_msg = SubMsg::reply_on_success(instantiate_pair_msg, INSTANTIATE_PAIR_REPLY_ID);
+
+    Ok(Response::new()
+        .add_submessage(reply_msg)
+        .add_attributes(vec![
+            attr("action", "create_pair"),
+            attr("pair", format!("{}-{}", key.0, key.1)),
+        ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    if msg.id != INSTANTIATE_PAIR_REPLY_ID {
+        return Err(ContractError::UnknownReplyId { id: msg.id });
+    };
+    let res =
+        parse_reply_instantiate_data(msg).map_err(|_| ContractError::InstantiatePairError {})?;
+    let contract_addr = deps.api.addr_validate(&res.contract_address)?;
+
+    let pending = PENDING_PAIR.load(deps.storage)?;
+    PENDING_PAIR.remove(deps.storage);
+    let pair = PairInfo {
+        asset_infos: pending.asset_infos,
+        contract_addr: contract_addr.clone(),
+        pair_type: pending.pair_type,
+    };
+    pairs().save(deps.storage, pair_key(&pair.asset_infos), &pair)?;
+
+    Ok(Response::new().add_attribute("pair_contract_addr", contract_addr))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::Pair { asset_infos } => to_binary(&query_pair(deps, asset_infos)?),
+        QueryMsg::PairByAddress { address } => to_binary(&query_pair_by_address(deps, address)?),
+        QueryMsg::Pairs { start_after, limit } => {
+            to_binary(&query_pairs(deps, start_after, limit)?)
+        }
+    }
+}
+
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse {
+        owner: config.owner.map(|o| o.into_string()),
+        constant_product_code_id: config.constant_product_code_id,
+        constant_sum_code_id: config.constant_sum_code_id,
+        lp_token_code_id: config.lp_token_code_id,
+        protocol_fee_recipient: config.protocol_fee_recipient.into_string(),
+        protocol_fee_percent: config.protocol_fee_percent,
+        lp_fee_percent: config.lp_fee_percent,
+    })
+}
+
+pub fn query_pair(deps: Deps, asset_infos: [Denom; 2]) -> StdResult<PairInfo> {
+    pairs().load(deps.storage, pair_key(&asset_infos))
+}
+
+pub fn query_pair_by_address(deps: Deps, address: String) -> StdResult<PairInfo> {
+    let address = deps.api.addr_validate(&address)?;
+    pairs()
+        .idx
+        .contract_addr
+        .item(deps.storage, address)?
+        .map(|(_, pair)| pair)
+        .ok_or_else(|| StdError::not_found("PairInfo"))
+}
+
+pub fn query_pairs(
+    deps: Deps,
+    start_after: Option<[Denom; 2]>,
+    limit: Option<u32>,
+) -> StdResult<PairsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start =
+        start_after.map(|asset_infos| Bound::exclusive(pair_key(&asset_infos).joined_key()));
+    let pairs = pairs()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, pair)| pair))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(PairsResponse { pairs })
+}
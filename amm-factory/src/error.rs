@@ -0,0 +1,32 @@
+use cosmwasm_std::{Decimal, StdError};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Total fee ({total_fee_percent}) percent is higher than max ({max_fee_percent})")]
+    FeesTooHigh {
+        max_fee_percent: Decimal,
+        total_fee_percent: Decimal,
+    },
+
+    #[error("A pair can not trade a token against itself")]
+    IdenticalAssets {},
+
+    #[error("The pair already exists")]
+    PairExists {},
+
+    #[error("Constant sum params can only be set on constant sum pairs")]
+    UnexpectedPairParams {},
+
+    #[error("Failed to instantiate the pair")]
+    InstantiatePairError {},
+
+    #[error("Unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
+}
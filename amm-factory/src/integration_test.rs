@@ -0,0 +1,675 @@
+#![cfg(test)]
+
+use std::str::FromStr;
+
+use crate::error::ContractError;
+use crate::msg::{
+    ConfigResponse, ConstantSumParams, ExecuteMsg, InstantiateMsg, PairsResponse, QueryMsg,
+};
+use crate::state::{PairInfo, PairType};
+use constant_sum_amm::msg::DepegProtectionParams;
+use cosmwasm_std::{coins, Addr, Decimal, Empty, Uint128};
+use cw20::{Cw20Coin, Cw20Contract, Cw20ExecuteMsg, Denom};
+use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
+
+const NATIVE_TOKEN_DENOM: &str = "juno";
+
+fn mock_app() -> App {
+    App::default()
+}
+
+fn contract_factory() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        crate::contract::execute,
+        crate::contract::instantiate,
+        crate::contract::query,
+    )
+    .with_reply(crate::contract::reply);
+    Box::new(contract)
+}
+
+fn contract_constant_product() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        constant_product_amm::contract::execute,
+        constant_product_amm::contract::instantiate,
+        constant_product_amm::contract::query,
+    )
+    .with_reply(constant_product_amm::contract::reply);
+    Box::new(contract)
+}
+
+fn contract_constant_sum() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        constant_sum_amm::contract::execute,
+        constant_sum_amm::contract::instantiate,
+        constant_sum_amm::contract::query,
+    )
+    .with_reply(constant_sum_amm::contract::reply);
+    Box::new(contract)
+}
+
+fn contract_cw20() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    );
+    Box::new(contract)
+}
+
+fn create_cw20(router: &mut App, owner: &Addr, name: String, symbol: String) -> Cw20Contract {
+    let cw20_id = router.store_code(contract_cw20());
+    let msg = cw20_base::msg::InstantiateMsg {
+        name,
+        symbol,
+        decimals: 6,
+        initial_balances: vec![Cw20Coin {
+            address: owner.to_string(),
+            amount: Uint128::new(5000),
+        }],
+        mint: None,
+        marketing: None,
+    };
+    let addr = router
+        .instantiate_contract(cw20_id, owner.clone(), &msg, &[], "CASH", None)
+        .unwrap();
+    Cw20Contract(addr)
+}
+
+fn create_factory(router: &mut App, owner: &Addr) -> Addr {
+    let cw20_id = router.store_code(contract_cw20());
+    let constant_product_id = router.store_code(contract_constant_product());
+    let constant_sum_id = router.store_code(contract_constant_sum());
+    let factory_id = router.store_code(contract_factory());
+    let msg = InstantiateMsg {
+        owner: Some(owner.to_string()),
+        constant_product_code_id: constant_product_id,
+        constant_sum_code_id: constant_sum_id,
+        lp_token_code_id: cw20_id,
+        protocol_fee_recipient: "fee_recipient".to_string(),
+        protocol_fee_percent: Decimal::from_str("0.1").unwrap(),
+        lp_fee_percent: Decimal::from_str("0.2").unwrap(),
+    };
+    router
+        .instantiate_contract(factory_id, owner.clone(), &msg, &[], "factory", None)
+        .unwrap()
+}
+
+fn create_pair(
+    router: &mut App,
+    factory: &Addr,
+    pair_type: PairType,
+    asset_infos: [Denom; 2],
+) -> Result<AppResponse, ContractError> {
+    let msg = ExecuteMsg::CreatePair {
+        pair_type,
+        asset_infos,
+        constant_sum_params: None,
+    };
+    router
+        .execute_contract(Addr::unchecked("anyone"), factory.clone(), &msg, &[])
+        .map_err(|err| err.downcast().unwrap())
+}
+
+fn get_pair(router: &App, factory: &Addr, asset_infos: [Denom; 2]) -> PairInfo {
+    router
+        .wrap()
+        .query_wasm_smart(factory, &QueryMsg::Pair { asset_infos })
+        .unwrap()
+}
+
+#[test]
+fn test_create_pair() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let factory = create_factory(&mut router, &owner);
+    let token1 = create_cw20(&mut router, &owner, "token1".into(), "TOKENONE".into());
+    let token2 = create_cw20(&mut router, &owner, "token2".into(), "TOKENTWO".into());
+    let native = Denom::Native(NATIVE_TOKEN_DENOM.to_string());
+
+    // Anyone can create a pair, the denoms are ordered
+    create_pair(
+        &mut router,
+        &factory,
+        PairType::ConstantProduct,
+        [native.clone(), Denom::Cw20(token1.addr())],
+    )
+    .unwrap();
+    let pair = get_pair(
+        &router,
+        &factory,
+        [native.clone(), Denom::Cw20(token1.addr())],
+    );
+    assert_eq!(
+        pair.asset_infos,
+        [Denom::Cw20(token1.addr()), native.clone()]
+    );
+    assert_eq!(pair.pair_type, PairType::ConstantProduct);
+    assert_eq!(
+        get_pair(
+            &router,
+            &factory,
+            [Denom::Cw20(token1.addr()), native.clone()]
+        ),
+        pair
+    );
+
+    // The pair is deployed with the default fees and the factory owner
+    let info: constant_product_amm::msg::InfoResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &pair.contract_addr,
+            &constant_product_amm::msg::QueryMsg::Info {},
+        )
+        .unwrap();
+    assert_eq!(info.token1_denom, Denom::Cw20(token1.addr()));
+    assert_eq!(info.token2_denom, native);
+    let fee: constant_product_amm::msg::FeeResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &pair.contract_addr,
+            &constant_product_amm::msg::QueryMsg::Fee {},
+        )
+        .unwrap();
+    assert_eq!(
+        fee,
+        constant_product_amm::msg::FeeResponse {
+            owner: Some(owner.to_string()),
+            lp_fee_percent: Decimal::from_str("0.2").unwrap(),
+            protocol_fee_percent: Decimal::from_str("0.1").unwrap(),
+            protocol_fee_recipient: "fee_recipient".to_string(),
+        }
+    );
+    let by_address: PairInfo = router
+        .wrap()
+        .query_wasm_smart(
+            &factory,
+            &QueryMsg::PairByAddress {
+                address: pair.contract_addr.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(by_address, pair);
+
+    let err = create_pair(
+        &mut router,
+        &factory,
+        PairType::ConstantProduct,
+        [Denom::Cw20(token2.addr()), Denom::Cw20(token2.addr())],
+    )
+    .unwrap_err();
+    assert_eq!(ContractError::IdenticalAssets {}, err);
+
+    // Missing pairs are not found
+    let err = router
+        .wrap()
+        .query_wasm_smart::<PairInfo>(
+            &factory,
+            &QueryMsg::Pair {
+                asset_infos: [Denom::Cw20(token2.addr()), native],
+            },
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("not found"));
+}
+
+#[test]
+fn test_duplicate_pair() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let factory = create_factory(&mut router, &owner);
+    let token1 = create_cw20(&mut router, &owner, "token1".into(), "TOKENONE".into());
+    let token2 = create_cw20(&mut router, &owner, "token2".into(), "TOKENTWO".into());
+    let native = Denom::Native(NATIVE_TOKEN_DENOM.to_string());
+
+    create_pair(
+        &mut router,
+        &factory,
+        PairType::ConstantProduct,
+        [native.clone(), Denom::Cw20(token1.addr())],
+    )
+    .unwrap();
+    create_pair(
+        &mut router,
+        &factory,
+        PairType::ConstantSum,
+        [Denom::Cw20(token2.addr()), Denom::Cw20(token1.addr())],
+    )
+    .unwrap();
+
+    // Only one pair per denoms, whatever the order or the pair type
+    for (pair_type, asset_infos) in [
+        (
+            PairType::ConstantProduct,
+            [Denom::Cw20(token1.addr()), native.clone()],
+        ),
+        (
+            PairType::ConstantSum,
+            [Denom::Cw20(token1.addr()), native.clone()],
+        ),
+        (
+            PairType::ConstantProduct,
+            [Denom::Cw20(token1.addr()), Denom::Cw20(token2.addr())],
+        ),
+        (
+            PairType::ConstantSum,
+            [Denom::Cw20(token1.addr()), Denom::Cw20(token2.addr())],
+        ),
+    ] {
+        let err = create_pair(&mut router, &factory, pair_type, asset_infos).unwrap_err();
+        assert_eq!(ContractError::PairExists {}, err);
+    }
+}
+
+#[test]
+fn test_native_denom_named_like_cw20() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let factory = create_factory(&mut router, &owner);
+    let token1 = create_cw20(&mut router, &owner, "token1".into(), "TOKENONE".into());
+    let token2 = create_cw20(&mut router, &owner, "token2".into(), "TOKENTWO".into());
+    // A native denom with the same name as the cw20 address is a different asset
+    let native = Denom::Native(token1.addr().to_string());
+
+    create_pair(
+        &mut router,
+        &factory,
+        PairType::ConstantProduct,
+        [Denom::Cw20(token1.addr()), Denom::Cw20(token2.addr())],
+    )
+    .unwrap();
+    create_pair(
+        &mut router,
+        &factory,
+        PairType::ConstantProduct,
+        [native.clone(), Denom::Cw20(token2.addr())],
+    )
+    .unwrap();
+    create_pair(
+        &mut router,
+        &factory,
+        PairType::ConstantProduct,
+        [native.clone(), Denom::Cw20(token1.addr())],
+    )
+    .unwrap();
+
+    let cw20_pair = get_pair(
+        &router,
+        &factory,
+        [Denom::Cw20(token1.addr()), Denom::Cw20(token2.addr())],
+    );
+    let native_pair = get_pair(&router, &factory, [native, Denom::Cw20(token2.addr())]);
+    assert_ne!(cw20_pair.contract_addr, native_pair.contract_addr);
+}
+
+#[test]
+fn test_pairs_pagination() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let factory = create_factory(&mut router, &owner);
+    let token1 = create_cw20(&mut router, &owner, "token1".into(), "TOKENONE".into());
+    let token2 = create_cw20(&mut router, &owner, "token2".into(), "TOKENTWO".into());
+    let native = Denom::Native(NATIVE_TOKEN_DENOM.to_string());
+
+    for asset_infos in [
+        [native.clone(), Denom::Cw20(token2.addr())],
+        [Denom::Cw20(token2.addr()), Denom::Cw20(token1.addr())],
+        [native.clone(), Denom::Cw20(token1.addr())],
+    ] {
+        create_pair(
+            &mut router,
+            &factory,
+            PairType::ConstantProduct,
+            asset_infos,
+        )
+        .unwrap();
+    }
+    let first = get_pair(
+        &router,
+        &factory,
+        [Denom::Cw20(token1.addr()), Denom::Cw20(token2.addr())],
+    );
+    let second = get_pair(
+        &router,
+        &factory,
+        [native.clone(), Denom::Cw20(token1.addr())],
+    );
+    let third = get_pair(
+        &router,
+        &factory,
+        [native.clone(), Denom::Cw20(token2.addr())],
+    );
+
+    let query_pairs = |start_after: Option<[Denom; 2]>, limit: Option<u32>| {
+        router
+            .wrap()
+            .query_wasm_smart::<PairsResponse>(&factory, &QueryMsg::Pairs { start_after, limit })
+            .unwrap()
+            .pairs
+    };
+
+    // Pairs are in denom order, cw20 tokens sort before native denoms
+    assert_eq!(
+        query_pairs(None, None),
+        vec![first.clone(), second.clone(), third.clone()]
+    );
+    assert_eq!(
+        query_pairs(None, Some(2)),
+        vec![first.clone(), second.clone()]
+    );
+
+    // The next page starts after the last pair of the previous one, in either denom order
+    assert_eq!(
+        query_pairs(Some(second.asset_infos.clone()), Some(2)),
+        vec![third.clone()]
+    );
+    assert_eq!(
+        query_pairs(Some([native, Denom::Cw20(token1.addr())]), Some(2)),
+        vec![third.clone()]
+    );
+    assert_eq!(query_pairs(Some(first.asset_infos), Some(1)), vec![second]);
+    assert_eq!(query_pairs(Some(third.asset_infos), None), vec![]);
+}
+
+#[test]
+fn test_create_constant_sum_pair() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let factory = create_factory(&mut router, &owner);
+    let token1 = create_cw20(&mut router, &owner, "token1".into(), "TOKENONE".into());
+    let token2 = create_cw20(&mut router, &owner, "token2".into(), "TOKENTWO".into());
+    let asset_infos = [Denom::Cw20(token1.addr()), Denom::Cw20(token2.addr())];
+
+    let depeg_protection = DepegProtectionParams {
+        price_source: "price_source".to_string(),
+        price_band: Decimal::from_str("0.02").unwrap(),
+        max_token1_output: None,
+        max_token2_output: Some(Decimal::from_str("0.1").unwrap()),
+    };
+    let params = ConstantSumParams {
+        rate: Some(Decimal::from_str("1.05").unwrap()),
+        depeg_protection: Some(depeg_protection.clone()),
+    };
+
+    // The params are only taken by constant sum pairs
+    let msg = ExecuteMsg::CreatePair {
+        pair_type: PairType::ConstantProduct,
+        asset_infos: asset_infos.clone(),
+        constant_sum_params: Some(params.clone()),
+    };
+    let err = router
+        .execute_contract(Addr::unchecked("anyone"), factory.clone(), &msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::UnexpectedPairParams {},
+        err.downcast().unwrap()
+    );
+
+    let msg = ExecuteMsg::CreatePair {
+        pair_type: PairType::ConstantSum,
+        asset_infos: asset_infos.clone(),
+        constant_sum_params: Some(params),
+    };
+    router
+        .execute_contract(Addr::unchecked("anyone"), factory.clone(), &msg, &[])
+        .unwrap();
+
+    // Constant sum pairs use their own code id and instantiate message
+    let pair = get_pair(&router, &factory, asset_infos);
+    assert_eq!(pair.pair_type, PairType::ConstantSum);
+    let info: constant_sum_amm::msg::InfoResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &pair.contract_addr,
+            &constant_sum_amm::msg::QueryMsg::Info {},
+        )
+        .unwrap();
+    assert_eq!(info.token1_denom, Denom::Cw20(token1.addr()));
+    assert_eq!(info.token2_denom, Denom::Cw20(token2.addr()));
+    assert_eq!(info.rate, Decimal::from_str("1.05").unwrap());
+    let res: constant_sum_amm::msg::DepegProtectionResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &pair.contract_addr,
+            &constant_sum_amm::msg::QueryMsg::DepegProtection {},
+        )
+        .unwrap();
+    assert_eq!(res.depeg_protection, Some(depeg_protection));
+    let fee: constant_sum_amm::msg::FeeResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &pair.contract_addr,
+            &constant_sum_amm::msg::QueryMsg::Fee {},
+        )
+        .unwrap();
+    assert_eq!(fee.owner, Some(owner.to_string()));
+    assert_eq!(fee.lp_fee_percent, Decimal::from_str("0.2").unwrap());
+}
+
+#[test]
+fn test_pass_through_swap_across_pair_types() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    router.init_modules(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &owner, coins(4000, NATIVE_TOKEN_DENOM))
+            .unwrap()
+    });
+    let factory = create_factory(&mut router, &owner);
+    let token1 = create_cw20(&mut router, &owner, "token1".into(), "TOKENONE".into());
+    let token2 = create_cw20(&mut router, &owner, "token2".into(), "TOKENTWO".into());
+    let native = Denom::Native(NATIVE_TOKEN_DENOM.to_string());
+
+    // token1 -> juno in a constant product pair, juno -> token2 in a constant sum pair
+    let product_asset_infos = [Denom::Cw20(token1.addr()), native.clone()];
+    create_pair(
+        &mut router,
+        &factory,
+        PairType::ConstantProduct,
+        product_asset_infos.clone(),
+    )
+    .unwrap();
+    let product_pair = get_pair(&router, &factory, product_asset_infos).contract_addr;
+    let sum_asset_infos = [Denom::Cw20(token2.addr()), native];
+    create_pair(
+        &mut router,
+        &factory,
+        PairType::ConstantSum,
+        sum_asset_infos.clone(),
+    )
+    .unwrap();
+    let sum_pair = get_pair(&router, &factory, sum_asset_infos).contract_addr;
+
+    for (token, pair) in [(&token1, &product_pair), (&token2, &sum_pair)] {
+        let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+            spender: pair.to_string(),
+            amount: Uint128::new(5000),
+            expires: None,
+        };
+        router
+            .execute_contract(owner.clone(), token.addr(), &allowance_msg, &[])
+            .unwrap();
+    }
+    let add_liquidity_msg = constant_product_amm::msg::ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(2000),
+        token2_amount: Uint128::new(2000),
+        min_liquidity: Uint128::zero(),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            product_pair.clone(),
+            &add_liquidity_msg,
+            &coins(2000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+    let add_liquidity_msg = constant_sum_amm::msg::ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(2000),
+        token2_amount: Uint128::new(2000),
+        min_liquidity: Uint128::zero(),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            sum_pair.clone(),
+            &add_liquidity_msg,
+            &coins(2000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+
+    let resp: constant_product_amm::msg::Token1ForToken2PriceResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &product_pair,
+            &constant_product_amm::msg::QueryMsg::Token1ForToken2Price {
+                token1_amount: Uint128::new(100),
+            },
+        )
+        .unwrap();
+    let resp: constant_sum_amm::msg::Token2ForToken1PriceResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &sum_pair,
+            &constant_sum_amm::msg::QueryMsg::Token2ForToken1Price {
+                token2_amount: resp.token2_amount,
+            },
+        )
+        .unwrap();
+    let output = resp.token1_amount;
+
+    let pass_through_msg =
+        |output_min_token: Uint128| constant_product_amm::msg::ExecuteMsg::PassThroughSwap {
+            output_amm_address: sum_pair.to_string(),
+            input_token: constant_product_amm::msg::TokenSelect::Token1,
+            input_token_amount: Uint128::new(100),
+            output_min_token,
+            expiration: None,
+        };
+    // The minimum applies to the output of the constant sum pair
+    router
+        .execute_contract(
+            owner.clone(),
+            product_pair.clone(),
+            &pass_through_msg(output + Uint128::new(1)),
+            &[],
+        )
+        .unwrap_err();
+    router
+        .execute_contract(owner.clone(), product_pair, &pass_through_msg(output), &[])
+        .unwrap();
+    assert_eq!(
+        token1.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(2900)
+    );
+    assert_eq!(
+        token2.balance(&router, owner).unwrap(),
+        Uint128::new(3000) + output
+    );
+}
+
+#[test]
+fn test_update_config() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let factory = create_factory(&mut router, &owner);
+    let token = create_cw20(&mut router, &owner, "token".into(), "CWTOKEN".into());
+
+    let update_msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        constant_product_code_id: None,
+        constant_sum_code_id: None,
+        lp_token_code_id: None,
+        protocol_fee_recipient: Some("new_fee_recipient".to_string()),
+        protocol_fee_percent: Some(Decimal::zero()),
+        lp_fee_percent: Some(Decimal::from_str("0.3").unwrap()),
+    };
+    let err = router
+        .execute_contract(Addr::unchecked("anyone"), factory.clone(), &update_msg, &[])
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+    // The default fees are checked against the same maximum as the pairs
+    let too_high_msg = ExecuteMsg::UpdateConfig {
+        owner: None,
+        constant_product_code_id: None,
+        constant_sum_code_id: None,
+        lp_token_code_id: None,
+        protocol_fee_recipient: None,
+        protocol_fee_percent: None,
+        lp_fee_percent: Some(Decimal::from_str("1").unwrap()),
+    };
+    let err = router
+        .execute_contract(owner.clone(), factory.clone(), &too_high_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::FeesTooHigh {
+            max_fee_percent: Decimal::from_str("1").unwrap(),
+            total_fee_percent: Decimal::from_str("1.1").unwrap(),
+        },
+        err.downcast().unwrap()
+    );
+
+    router
+        .execute_contract(owner.clone(), factory.clone(), &update_msg, &[])
+        .unwrap();
+    let config: ConfigResponse = router
+        .wrap()
+        .query_wasm_smart(&factory, &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(config.owner, Some(owner.to_string()));
+    assert_eq!(config.protocol_fee_recipient, "new_fee_recipient");
+    assert_eq!(config.protocol_fee_percent, Decimal::zero());
+    assert_eq!(config.lp_fee_percent, Decimal::from_str("0.3").unwrap());
+
+    // New pairs use the updated defaults
+    let asset_infos = [
+        Denom::Native(NATIVE_TOKEN_DENOM.to_string()),
+        Denom::Cw20(token.addr()),
+    ];
+    create_pair(
+        &mut router,
+        &factory,
+        PairType::ConstantProduct,
+        asset_infos.clone(),
+    )
+    .unwrap();
+    let pair = get_pair(&router, &factory, asset_infos);
+    let fee: constant_product_amm::msg::FeeResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &pair.contract_addr,
+            &constant_product_amm::msg::QueryMsg::Fee {},
+        )
+        .unwrap();
+    assert_eq!(fee.lp_fee_percent, Decimal::from_str("0.3").unwrap());
+    assert_eq!(fee.protocol_fee_percent, Decimal::zero());
+    assert_eq!(fee.protocol_fee_recipient, "new_fee_recipient");
+
+    // Ownership moves to the new owner
+    let transfer_msg = ExecuteMsg::UpdateConfig {
+        owner: Some("new_owner".to_string()),
+        constant_product_code_id: None,
+        constant_sum_code_id: None,
+        lp_token_code_id: None,
+        protocol_fee_recipient: None,
+        protocol_fee_percent: None,
+        lp_fee_percent: None,
+    };
+    router
+        .execute_contract(owner.clone(), factory.clone(), &transfer_msg, &[])
+        .unwrap();
+    let err = router
+        .execute_contract(owner, factory, &update_msg, &[])
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+}
@@ -0,0 +1,87 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::Decimal;
+
+use constant_sum_amm::msg::DepegProtectionParams;
+use cw20::Denom;
+
+use crate::state::{PairInfo, PairType};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    // Owner of the factory and of the pairs it creates
+    pub owner: Option<String>,
+    pub constant_product_code_id: u64,
+    pub constant_sum_code_id: u64,
+    pub lp_token_code_id: u64,
+    // Default fees of new pairs
+    pub protocol_fee_recipient: String,
+    // NOTE: Fees percents are out of 100 e.g., 1 = 1%
+    pub protocol_fee_percent: Decimal,
+    pub lp_fee_percent: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    // Deploy a pair with the default fees, only one pair can exist for two denoms
+    CreatePair {
+        pair_type: PairType,
+        asset_infos: [Denom; 2],
+        // Only for constant sum pairs, None creates the pair at a 1:1 rate without depeg
+        // protection
+        constant_sum_params: Option<ConstantSumParams>,
+    },
+    // None leaves a value unchanged
+    UpdateConfig {
+        owner: Option<String>,
+        constant_product_code_id: Option<u64>,
+        constant_sum_code_id: Option<u64>,
+        lp_token_code_id: Option<u64>,
+        protocol_fee_recipient: Option<String>,
+        protocol_fee_percent: Option<Decimal>,
+        lp_fee_percent: Option<Decimal>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ConstantSumParams {
+    // Price of token1 in token2 the pair swaps at, None is 1:1
+    pub rate: Option<Decimal>,
+    pub depeg_protection: Option<DepegProtectionParams>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    // The denoms can be in either order
+    Pair {
+        asset_infos: [Denom; 2],
+    },
+    PairByAddress {
+        address: String,
+    },
+    // Pairs ordered by their denoms
+    Pairs {
+        start_after: Option<[Denom; 2]>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: Option<String>,
+    pub constant_product_code_id: u64,
+    pub constant_sum_code_id: u64,
+    pub lp_token_code_id: u64,
+    pub protocol_fee_recipient: String,
+    pub protocol_fee_percent: Decimal,
+    pub lp_fee_percent: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PairsResponse {
+    pub pairs: Vec<PairInfo>,
+}
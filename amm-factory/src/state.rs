@@ -0,0 +1,86 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Addr, Decimal};
+use cw20::Denom;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, UniqueIndex};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PairType {
+    ConstantProduct,
+    ConstantSum,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Config {
+    pub owner: Option<Addr>,
+    pub constant_product_code_id: u64,
+    pub constant_sum_code_id: u64,
+    pub lp_token_code_id: u64,
+    // Fees of new pairs, existing pairs are updated through the pair itself
+    pub protocol_fee_recipient: Addr,
+    pub protocol_fee_percent: Decimal,
+    pub lp_fee_percent: Decimal,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PairInfo {
+    // Ordered by denom, the pair's token1 and token2
+    pub asset_infos: [Denom; 2],
+    pub contract_addr: Addr,
+    pub pair_type: PairType,
+}
+
+// Pair waiting for the instantiate reply with its address
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingPair {
+    pub asset_infos: [Denom; 2],
+    pub pair_type: PairType,
+}
+
+pub const PENDING_PAIR: Item<PendingPair> = Item::new("pending_pair");
+
+pub struct PairIndexes<'a> {
+    pub contract_addr: UniqueIndex<'a, Addr, PairInfo>,
+}
+
+impl<'a> IndexList<PairInfo> for PairIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<PairInfo>> + '_> {
+        let v: Vec<&dyn Index<PairInfo>> = vec![&self.contract_addr];
+        Box::new(v.into_iter())
+    }
+}
+
+// Pairs keyed by their ordered denoms, see pair_key
+pub fn pairs<'a>() -> IndexedMap<'a, (String, String), PairInfo, PairIndexes<'a>> {
+    let indexes = PairIndexes {
+        contract_addr: UniqueIndex::new(|pair| pair.contract_addr.clone(), "pairs__contract_addr"),
+    };
+    IndexedMap::new("pairs", indexes)
+}
+
+// Prefixed with the kind, a native denom can have the same name as a cw20 address
+fn denom_key(denom: &Denom) -> String {
+    match denom {
+        Denom::Native(denom) => format!("native:{}", denom),
+        Denom::Cw20(addr) => format!("cw20:{}", addr),
+    }
+}
+
+// Sorts the denoms so both orders of a pair map to the same key
+pub fn order_asset_infos(asset_infos: [Denom; 2]) -> [Denom; 2] {
+    let [first, second] = asset_infos;
+    if denom_key(&first) <= denom_key(&second) {
+        [first, second]
+    } else {
+        [second, first]
+    }
+}
+
+pub fn pair_key(asset_infos: &[Denom; 2]) -> (String, String) {
+    let [first, second] = order_asset_infos(asset_infos.clone());
+    (denom_key(&first), denom_key(&second))
+}
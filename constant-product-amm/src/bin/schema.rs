@@ -3,11 +3,13 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use constant_sum_amm::msg::{
-    ExecuteMsg, InfoResponse, InstantiateMsg, QueryMsg, Token1ForToken2PriceResponse,
-    Token2ForToken1PriceResponse,
+use constant_product_amm::msg::{
+    AmpResponse, CumulativePricesResponse, ExecuteMsg, InfoResponse, InstantiateMsg,
+    LimitsResponse, OwnershipResponse, PauseResponse, PositionResponse, ProtocolFeesResponse,
+    QueryMsg, ReceiveMsg, ReverseSimulateResponse, SimulateSwapResponse, TicksResponse,
+    Token1ForToken2PriceResponse, Token2ForToken1PriceResponse, TwapResponse,
 };
-use constant_sum_amm::state::Token;
+use constant_product_amm::state::Token;
 use cw20::BalanceResponse;
 
 fn main() {
@@ -19,9 +21,21 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
     export_schema(&schema_for!(Token), &out_dir);
     export_schema(&schema_for!(BalanceResponse), &out_dir);
     export_schema(&schema_for!(InfoResponse), &out_dir);
     export_schema(&schema_for!(Token1ForToken2PriceResponse), &out_dir);
     export_schema(&schema_for!(Token2ForToken1PriceResponse), &out_dir);
+    export_schema(&schema_for!(SimulateSwapResponse), &out_dir);
+    export_schema(&schema_for!(ReverseSimulateResponse), &out_dir);
+    export_schema(&schema_for!(TwapResponse), &out_dir);
+    export_schema(&schema_for!(CumulativePricesResponse), &out_dir);
+    export_schema(&schema_for!(OwnershipResponse), &out_dir);
+    export_schema(&schema_for!(ProtocolFeesResponse), &out_dir);
+    export_schema(&schema_for!(LimitsResponse), &out_dir);
+    export_schema(&schema_for!(PauseResponse), &out_dir);
+    export_schema(&schema_for!(AmpResponse), &out_dir);
+    export_schema(&schema_for!(PositionResponse), &out_dir);
+    export_schema(&schema_for!(TicksResponse), &out_dir);
 }
@@ -0,0 +1,754 @@
+use std::convert::TryInto;
+
+use cosmwasm_std::{Decimal256, Order, StdError, StdResult, Storage, Uint128, Uint256, Uint512};
+use cw_storage_plus::Bound;
+
+use crate::state::{ConcentratedPool, Position, TickInfo, TICKS};
+
+// Ticks of the prices 1.0001^-887272 and 1.0001^887272, the sqrt prices at these ticks are the
+// smallest and largest that fit in Q64.96, see Uniswap v3 TickMath
+pub const MIN_TICK: i32 = -887_272;
+pub const MAX_TICK: i32 = 887_272;
+pub const MAX_TICK_SPACING: u32 = 16_384;
+
+// The same fee scale as the constant product curve
+const FEE_SCALE: u128 = 10_000;
+
+// 1 / sqrt(1.0001^(2^i)) in Q128.128 for i = 1..19, the factor of i = 0 is in
+// get_sqrt_price_at_tick
+const SQRT_RATIO_FACTORS: [u128; 19] = [
+    0xfff97272373d413259a46990580e213a,
+    0xfff2e50f5f656932ef12357cf3c7fdcc,
+    0xffe5caca7e10e4e61c3624eaa0941cd0,
+    0xffcb9843d60f6159c9db58835c926644,
+    0xff973b41fa98c081472e6896dfb254c0,
+    0xff2ea16466c96a3843ec78b326b52861,
+    0xfe5dee046a99a2a811c461f1969c3053,
+    0xfcbe86c7900a88aedcffc83b479aa3a4,
+    0xf987a7253ac413176f2b074cf7815e54,
+    0xf3392b0822b70005940c7a398e4b70f3,
+    0xe7159475a2c29b7443b29c7fa6e889d9,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e5,
+    0x70d869a156d2a1b890bb3df62baf32f7,
+    0x31be135f97d08fd981231505542fcfa6,
+    0x9aa508b5b7a84e1c677de54f3e99bc9,
+    0x5d6af8dedb81196699c329225ee604,
+    0x2216e584f5fa1ea926041bedfe98,
+    0x48a170391f7dc42444e8fa2,
+];
+
+fn q96() -> Uint256 {
+    Uint256::from(1u8) << 96
+}
+
+fn q128() -> Uint256 {
+    Uint256::from(1u8) << 128
+}
+
+// Ticks are stored with the sign bit flipped, so the keys sort in tick order
+pub fn tick_key(tick: i32) -> u32 {
+    (tick as u32) ^ (1 << 31)
+}
+
+pub fn key_tick(key: u32) -> i32 {
+    (key ^ (1 << 31)) as i32
+}
+
+// a * b / denominator without overflowing the intermediate product
+fn mul_div(a: Uint256, b: Uint256, denominator: Uint256) -> StdResult<Uint256> {
+    let result = a
+        .full_mul(b)
+        .checked_div(Uint512::from(denominator))
+        .map_err(StdError::divide_by_zero)?;
+    Ok(result.try_into()?)
+}
+
+fn mul_div_rounding_up(a: Uint256, b: Uint256, denominator: Uint256) -> StdResult<Uint256> {
+    let result = mul_div(a, b, denominator)?;
+    if a.full_mul(b) == result.full_mul(denominator) {
+        Ok(result)
+    } else {
+        Ok(result + Uint256::from(1u8))
+    }
+}
+
+fn div_rounding_up(a: Uint256, b: Uint256) -> StdResult<Uint256> {
+    mul_div_rounding_up(a, Uint256::from(1u8), b)
+}
+
+// Fee growth counters wrap around like in Uniswap v3, only their differences matter
+fn wrapping_add(a: Uint256, b: Uint256) -> Uint256 {
+    let headroom = Uint256::MAX - a;
+    if b <= headroom {
+        a + b
+    } else {
+        b - headroom - Uint256::from(1u8)
+    }
+}
+
+fn wrapping_sub(a: Uint256, b: Uint256) -> Uint256 {
+    if a >= b {
+        a - b
+    } else {
+        Uint256::MAX - (b - a) + Uint256::from(1u8)
+    }
+}
+
+fn to_uint128(value: Uint256) -> StdResult<Uint128> {
+    Ok(value.try_into()?)
+}
+
+fn add_liquidity_delta(liquidity: Uint128, delta: i128) -> StdResult<Uint128> {
+    if delta >= 0 {
+        liquidity
+            .checked_add(Uint128::new(delta as u128))
+            .map_err(StdError::overflow)
+    } else {
+        liquidity
+            .checked_sub(Uint128::new(delta.unsigned_abs()))
+            .map_err(StdError::overflow)
+    }
+}
+
+// sqrt(1.0001^tick) in Q64.96, rounded up
+pub fn get_sqrt_price_at_tick(tick: i32) -> StdResult<Uint256> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(StdError::generic_err(format!(
+            "Tick out of range: {}",
+            tick
+        )));
+    }
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = if abs_tick & 1 != 0 {
+        Uint256::from(0xfffcb933bd6fad37aa2d162d1a594001u128)
+    } else {
+        q128()
+    };
+    for (i, factor) in SQRT_RATIO_FACTORS.iter().enumerate() {
+        if abs_tick & (2 << i) != 0 {
+            ratio = (ratio * Uint256::from(*factor)) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = Uint256::MAX / ratio;
+    }
+    // Q128.128 to Q64.96
+    Ok((ratio + Uint256::from(u32::MAX)) >> 32)
+}
+
+pub fn min_sqrt_price() -> StdResult<Uint256> {
+    get_sqrt_price_at_tick(MIN_TICK)
+}
+
+pub fn max_sqrt_price() -> StdResult<Uint256> {
+    get_sqrt_price_at_tick(MAX_TICK)
+}
+
+// Largest tick whose sqrt price is at most sqrt_price
+pub fn get_tick_at_sqrt_price(sqrt_price: Uint256) -> StdResult<i32> {
+    if sqrt_price < min_sqrt_price()? || sqrt_price >= max_sqrt_price()? {
+        return Err(StdError::generic_err("Price out of range"));
+    }
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        if get_sqrt_price_at_tick(mid)? <= sqrt_price {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+// Output tokens per input token at the sqrt price, token2 per token1 is sqrt_price^2 / 2^192
+pub fn get_spot_price(sqrt_price: Uint256, input_token1: bool) -> StdResult<Decimal256> {
+    let one = Uint256::from(10u128.pow(18));
+    let atomics = if input_token1 {
+        mul_div(mul_div(sqrt_price, sqrt_price, q96())?, one, q96())?
+    } else {
+        mul_div(mul_div(q96(), one, sqrt_price)?, q96(), sqrt_price)?
+    };
+    Ok(Decimal256::from_ratio(atomics, one))
+}
+
+// Token1 between two sqrt prices, L * (b - a) / (a * b)
+pub fn get_amount1_delta(
+    sqrt_price_a: Uint256,
+    sqrt_price_b: Uint256,
+    liquidity: Uint128,
+    round_up: bool,
+) -> StdResult<Uint256> {
+    let (a, b) = (
+        sqrt_price_a.min(sqrt_price_b),
+        sqrt_price_a.max(sqrt_price_b),
+    );
+    let numerator = Uint256::from(liquidity) << 96;
+    if round_up {
+        div_rounding_up(mul_div_rounding_up(numerator, b - a, b)?, a)
+    } else {
+        Ok(mul_div(numerator, b - a, b)? / a)
+    }
+}
+
+// Token2 between two sqrt prices, L * (b - a)
+pub fn get_amount2_delta(
+    sqrt_price_a: Uint256,
+    sqrt_price_b: Uint256,
+    liquidity: Uint128,
+    round_up: bool,
+) -> StdResult<Uint256> {
+    let (a, b) = (
+        sqrt_price_a.min(sqrt_price_b),
+        sqrt_price_a.max(sqrt_price_b),
+    );
+    if round_up {
+        mul_div_rounding_up(Uint256::from(liquidity), b - a, q96())
+    } else {
+        mul_div(Uint256::from(liquidity), b - a, q96())
+    }
+}
+
+// Sqrt price after adding amount_in of the input token, rounded so the pool never gives out
+// more than the input pays for
+fn get_next_sqrt_price_from_input(
+    sqrt_price: Uint256,
+    liquidity: Uint128,
+    amount_in: Uint256,
+    input_token1: bool,
+) -> StdResult<Uint256> {
+    if liquidity.is_zero() {
+        return Err(StdError::generic_err("No liquidity"));
+    }
+    if amount_in.is_zero() {
+        return Ok(sqrt_price);
+    }
+    if input_token1 {
+        // L * P / (L + amount * P)
+        let numerator = Uint512::from(Uint256::from(liquidity) << 96);
+        let denominator = numerator
+            .checked_add(amount_in.full_mul(sqrt_price))
+            .map_err(StdError::overflow)?;
+        let product = numerator
+            .checked_mul(Uint512::from(sqrt_price))
+            .map_err(StdError::overflow)?;
+        let result = product / denominator;
+        let result = if result * denominator == product {
+            result
+        } else {
+            result + Uint512::from(1u8)
+        };
+        Ok(result.try_into()?)
+    } else {
+        // P + amount / L
+        let quotient = mul_div(amount_in, q96(), Uint256::from(liquidity))?;
+        sqrt_price.checked_add(quotient).map_err(StdError::overflow)
+    }
+}
+
+pub struct SwapStep {
+    pub sqrt_price_next: Uint256,
+    pub amount_in: Uint256,
+    pub amount_out: Uint256,
+    pub fee_amount: Uint256,
+}
+
+// Swaps amount_remaining, including the fee, towards the target price within one tick range.
+// fee is out of FEE_SCALE.
+pub fn compute_swap_step(
+    sqrt_price: Uint256,
+    sqrt_price_target: Uint256,
+    liquidity: Uint128,
+    amount_remaining: Uint256,
+    fee: Uint128,
+) -> StdResult<SwapStep> {
+    let input_token1 = sqrt_price >= sqrt_price_target;
+    let fee = Uint256::from(fee);
+    let fee_scale = Uint256::from(FEE_SCALE);
+    let amount_remaining_less_fee = mul_div(amount_remaining, fee_scale - fee, fee_scale)?;
+
+    let amount_to_target = if input_token1 {
+        get_amount1_delta(sqrt_price_target, sqrt_price, liquidity, true)?
+    } else {
+        get_amount2_delta(sqrt_price, sqrt_price_target, liquidity, true)?
+    };
+    let sqrt_price_next = if amount_remaining_less_fee >= amount_to_target {
+        sqrt_price_target
+    } else {
+        get_next_sqrt_price_from_input(
+            sqrt_price,
+            liquidity,
+            amount_remaining_less_fee,
+            input_token1,
+        )?
+    };
+    let reached_target = sqrt_price_next == sqrt_price_target;
+
+    let (amount_in, amount_out) = if input_token1 {
+        (
+            if reached_target {
+                amount_to_target
+            } else {
+                get_amount1_delta(sqrt_price_next, sqrt_price, liquidity, true)?
+            },
+            get_amount2_delta(sqrt_price_next, sqrt_price, liquidity, false)?,
+        )
+    } else {
+        (
+            if reached_target {
+                amount_to_target
+            } else {
+                get_amount2_delta(sqrt_price, sqrt_price_next, liquidity, true)?
+            },
+            get_amount1_delta(sqrt_price, sqrt_price_next, liquidity, false)?,
+        )
+    };
+    // The whole remainder is spent when the target is not reached
+    let fee_amount = if reached_target {
+        mul_div_rounding_up(amount_in, fee, fee_scale - fee)?
+    } else {
+        amount_remaining - amount_in
+    };
+
+    Ok(SwapStep {
+        sqrt_price_next,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+// Liquidity for the largest deposit of at most the given amounts in [lower, upper)
+pub fn get_liquidity_for_amounts(
+    sqrt_price: Uint256,
+    sqrt_price_lower: Uint256,
+    sqrt_price_upper: Uint256,
+    token1_amount: Uint128,
+    token2_amount: Uint128,
+) -> StdResult<Uint128> {
+    let liquidity1 = |a: Uint256, b: Uint256| -> StdResult<Uint256> {
+        mul_div(Uint256::from(token1_amount), mul_div(a, b, q96())?, b - a)
+    };
+    let liquidity2 = |a: Uint256, b: Uint256| -> StdResult<Uint256> {
+        mul_div(Uint256::from(token2_amount), q96(), b - a)
+    };
+    let liquidity = if sqrt_price <= sqrt_price_lower {
+        liquidity1(sqrt_price_lower, sqrt_price_upper)?
+    } else if sqrt_price < sqrt_price_upper {
+        std::cmp::min(
+            liquidity1(sqrt_price, sqrt_price_upper)?,
+            liquidity2(sqrt_price_lower, sqrt_price)?,
+        )
+    } else {
+        liquidity2(sqrt_price_lower, sqrt_price_upper)?
+    };
+    to_uint128(liquidity)
+}
+
+// Tokens backing liquidity in [lower, upper) at the sqrt price, rounded up for deposits and
+// down for withdrawals
+pub fn get_amounts_for_liquidity(
+    sqrt_price: Uint256,
+    sqrt_price_lower: Uint256,
+    sqrt_price_upper: Uint256,
+    liquidity: Uint128,
+    round_up: bool,
+) -> StdResult<(Uint128, Uint128)> {
+    let (token1_amount, token2_amount) = if sqrt_price <= sqrt_price_lower {
+        (
+            get_amount1_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+            Uint256::zero(),
+        )
+    } else if sqrt_price < sqrt_price_upper {
+        (
+            get_amount1_delta(sqrt_price, sqrt_price_upper, liquidity, round_up)?,
+            get_amount2_delta(sqrt_price_lower, sqrt_price, liquidity, round_up)?,
+        )
+    } else {
+        (
+            Uint256::zero(),
+            get_amount2_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+        )
+    };
+    Ok((to_uint128(token1_amount)?, to_uint128(token2_amount)?))
+}
+
+pub fn validate_ticks(lower_tick: i32, upper_tick: i32, tick_spacing: u32) -> StdResult<()> {
+    let spacing = tick_spacing as i32;
+    if lower_tick >= upper_tick
+        || lower_tick < MIN_TICK
+        || upper_tick > MAX_TICK
+        || lower_tick % spacing != 0
+        || upper_tick % spacing != 0
+    {
+        return Err(StdError::generic_err(format!(
+            "Invalid tick range: [{}, {}) with spacing {}",
+            lower_tick, upper_tick, tick_spacing
+        )));
+    }
+    Ok(())
+}
+
+// Closest initialized tick at or below tick when moving down, above tick when moving up
+fn next_initialized_tick(
+    storage: &dyn Storage,
+    tick: i32,
+    downwards: bool,
+) -> StdResult<Option<i32>> {
+    let key = if downwards {
+        TICKS
+            .keys_de(
+                storage,
+                None,
+                Some(Bound::inclusive_int(tick_key(tick))),
+                Order::Descending,
+            )
+            .next()
+    } else {
+        TICKS
+            .keys_de(
+                storage,
+                Some(Bound::exclusive_int(tick_key(tick))),
+                None,
+                Order::Ascending,
+            )
+            .next()
+    };
+    key.transpose().map(|key| key.map(key_tick))
+}
+
+// Fees per unit of liquidity earned while the price was in [lower, upper)
+fn get_fee_growth_inside(
+    pool: &ConcentratedPool,
+    lower_tick: i32,
+    lower: &TickInfo,
+    upper_tick: i32,
+    upper: &TickInfo,
+) -> (Uint256, Uint256) {
+    let inside = |global: Uint256, lower_outside: Uint256, upper_outside: Uint256| {
+        let below = if pool.tick >= lower_tick {
+            lower_outside
+        } else {
+            wrapping_sub(global, lower_outside)
+        };
+        let above = if pool.tick < upper_tick {
+            upper_outside
+        } else {
+            wrapping_sub(global, upper_outside)
+        };
+        wrapping_sub(wrapping_sub(global, below), above)
+    };
+    (
+        inside(
+            pool.fee_growth_global1,
+            lower.fee_growth_outside1,
+            upper.fee_growth_outside1,
+        ),
+        inside(
+            pool.fee_growth_global2,
+            lower.fee_growth_outside2,
+            upper.fee_growth_outside2,
+        ),
+    )
+}
+
+fn load_tick(storage: &dyn Storage, pool: &ConcentratedPool, tick: i32) -> StdResult<TickInfo> {
+    match TICKS.may_load(storage, tick_key(tick))? {
+        Some(info) => Ok(info),
+        // Fees so far are assumed to have been earned below the current tick
+        None if tick <= pool.tick => Ok(TickInfo {
+            fee_growth_outside1: pool.fee_growth_global1,
+            fee_growth_outside2: pool.fee_growth_global2,
+            ..TickInfo::default()
+        }),
+        None => Ok(TickInfo::default()),
+    }
+}
+
+fn save_tick(storage: &mut dyn Storage, tick: i32, info: &TickInfo) -> StdResult<()> {
+    if info.liquidity_lower.is_zero() && info.liquidity_upper.is_zero() {
+        TICKS.remove(storage, tick_key(tick));
+        Ok(())
+    } else {
+        TICKS.save(storage, tick_key(tick), info)
+    }
+}
+
+// Changes the liquidity of a position by liquidity_delta, accruing its fees first. Returns the
+// tokens added to or removed from the pool for the change.
+pub fn update_position(
+    storage: &mut dyn Storage,
+    pool: &mut ConcentratedPool,
+    position: &mut Position,
+    liquidity_delta: i128,
+) -> StdResult<(Uint128, Uint128)> {
+    let mut lower = load_tick(storage, pool, position.lower_tick)?;
+    let mut upper = load_tick(storage, pool, position.upper_tick)?;
+    lower.liquidity_lower = add_liquidity_delta(lower.liquidity_lower, liquidity_delta)?;
+    upper.liquidity_upper = add_liquidity_delta(upper.liquidity_upper, liquidity_delta)?;
+
+    let (fee_growth_inside1, fee_growth_inside2) = get_fee_growth_inside(
+        pool,
+        position.lower_tick,
+        &lower,
+        position.upper_tick,
+        &upper,
+    );
+    let owed = |fee_growth_inside: Uint256, last: Uint256| {
+        mul_div(
+            wrapping_sub(fee_growth_inside, last),
+            Uint256::from(position.liquidity),
+            q128(),
+        )
+        .and_then(to_uint128)
+    };
+    position.tokens_owed1 = position
+        .tokens_owed1
+        .checked_add(owed(fee_growth_inside1, position.fee_growth_inside1_last)?)
+        .map_err(StdError::overflow)?;
+    position.tokens_owed2 = position
+        .tokens_owed2
+        .checked_add(owed(fee_growth_inside2, position.fee_growth_inside2_last)?)
+        .map_err(StdError::overflow)?;
+    position.fee_growth_inside1_last = fee_growth_inside1;
+    position.fee_growth_inside2_last = fee_growth_inside2;
+    position.liquidity = add_liquidity_delta(position.liquidity, liquidity_delta)?;
+
+    save_tick(storage, position.lower_tick, &lower)?;
+    save_tick(storage, position.upper_tick, &upper)?;
+    if (position.lower_tick..position.upper_tick).contains(&pool.tick) {
+        pool.liquidity = add_liquidity_delta(pool.liquidity, liquidity_delta)?;
+    }
+
+    get_amounts_for_liquidity(
+        pool.sqrt_price,
+        get_sqrt_price_at_tick(position.lower_tick)?,
+        get_sqrt_price_at_tick(position.upper_tick)?,
+        Uint128::new(liquidity_delta.unsigned_abs()),
+        liquidity_delta > 0,
+    )
+}
+
+// Current amounts of a position and its fees, including the ones not accrued to it yet
+pub fn get_position_amounts(
+    storage: &dyn Storage,
+    pool: &ConcentratedPool,
+    position: &Position,
+) -> StdResult<(Uint128, Uint128, Uint128, Uint128)> {
+    let (token1_amount, token2_amount) = get_amounts_for_liquidity(
+        pool.sqrt_price,
+        get_sqrt_price_at_tick(position.lower_tick)?,
+        get_sqrt_price_at_tick(position.upper_tick)?,
+        position.liquidity,
+        false,
+    )?;
+    let lower = load_tick(storage, pool, position.lower_tick)?;
+    let upper = load_tick(storage, pool, position.upper_tick)?;
+    let (fee_growth_inside1, fee_growth_inside2) = get_fee_growth_inside(
+        pool,
+        position.lower_tick,
+        &lower,
+        position.upper_tick,
+        &upper,
+    );
+    let owed = |fee_growth_inside: Uint256, last: Uint256, owed: Uint128| {
+        let accrued = mul_div(
+            wrapping_sub(fee_growth_inside, last),
+            Uint256::from(position.liquidity),
+            q128(),
+        )
+        .and_then(to_uint128)?;
+        owed.checked_add(accrued).map_err(StdError::overflow)
+    };
+    Ok((
+        token1_amount,
+        token2_amount,
+        owed(
+            fee_growth_inside1,
+            position.fee_growth_inside1_last,
+            position.tokens_owed1,
+        )?,
+        owed(
+            fee_growth_inside2,
+            position.fee_growth_inside2_last,
+            position.tokens_owed2,
+        )?,
+    ))
+}
+
+pub struct SwapResult {
+    pub pool: ConcentratedPool,
+    pub output_amount: Uint128,
+    pub fee_amount: Uint128,
+    // Ticks crossed by the swap, with their updated fee growth outside
+    pub crossed_ticks: Vec<(i32, TickInfo)>,
+}
+
+// Swaps amount_in of the input token, including the lp fee, through the initialized ticks. The
+// result is only computed, save_swap writes it.
+pub fn compute_swap(
+    storage: &dyn Storage,
+    pool: &ConcentratedPool,
+    input_token1: bool,
+    amount_in: Uint128,
+    fee: Uint128,
+) -> StdResult<SwapResult> {
+    let mut state = pool.clone();
+    let mut amount_remaining = Uint256::from(amount_in);
+    let mut output_amount = Uint256::zero();
+    let mut fee_amount = Uint256::zero();
+    let mut crossed_ticks = vec![];
+
+    while !amount_remaining.is_zero() {
+        let next_tick = next_initialized_tick(storage, state.tick, input_token1)?;
+        let tick_next = match next_tick {
+            Some(tick) => tick,
+            None => return Err(StdError::generic_err("Not enough liquidity")),
+        };
+        let sqrt_price_target = get_sqrt_price_at_tick(tick_next)?;
+
+        let step = compute_swap_step(
+            state.sqrt_price,
+            sqrt_price_target,
+            state.liquidity,
+            amount_remaining,
+            fee,
+        )?;
+        amount_remaining -= step.amount_in + step.fee_amount;
+        output_amount += step.amount_out;
+        fee_amount += step.fee_amount;
+
+        if !state.liquidity.is_zero() {
+            let fee_growth = mul_div(step.fee_amount, q128(), Uint256::from(state.liquidity))?;
+            if input_token1 {
+                state.fee_growth_global1 = wrapping_add(state.fee_growth_global1, fee_growth);
+            } else {
+                state.fee_growth_global2 = wrapping_add(state.fee_growth_global2, fee_growth);
+            }
+        }
+
+        if step.sqrt_price_next == sqrt_price_target {
+            let mut info = TICKS.load(storage, tick_key(tick_next))?;
+            info.fee_growth_outside1 =
+                wrapping_sub(state.fee_growth_global1, info.fee_growth_outside1);
+            info.fee_growth_outside2 =
+                wrapping_sub(state.fee_growth_global2, info.fee_growth_outside2);
+            // Positions starting at the tick become active when moving up, the ones ending at
+            // it when moving down
+            let (added, removed) = if input_token1 {
+                (info.liquidity_upper, info.liquidity_lower)
+            } else {
+                (info.liquidity_lower, info.liquidity_upper)
+            };
+            state.liquidity = state
+                .liquidity
+                .checked_add(added)
+                .and_then(|liquidity| liquidity.checked_sub(removed))
+                .map_err(StdError::overflow)?;
+            crossed_ticks.push((tick_next, info));
+            state.tick = if input_token1 {
+                tick_next - 1
+            } else {
+                tick_next
+            };
+        } else {
+            state.tick = get_tick_at_sqrt_price(step.sqrt_price_next)?;
+        }
+        state.sqrt_price = step.sqrt_price_next;
+    }
+
+    Ok(SwapResult {
+        pool: state,
+        output_amount: to_uint128(output_amount)?,
+        fee_amount: to_uint128(fee_amount)?,
+        crossed_ticks,
+    })
+}
+
+pub fn save_swap(storage: &mut dyn Storage, swap: &SwapResult) -> StdResult<()> {
+    for (tick, info) in &swap.crossed_ticks {
+        TICKS.save(storage, tick_key(*tick), info)?;
+    }
+    crate::state::CONCENTRATED_POOL.save(storage, &swap.pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_sqrt_price_at_tick() {
+        assert_eq!(get_sqrt_price_at_tick(0).unwrap(), q96());
+        assert_eq!(
+            get_sqrt_price_at_tick(MIN_TICK).unwrap(),
+            Uint256::from(4295128739u64)
+        );
+        assert_eq!(
+            get_sqrt_price_at_tick(MAX_TICK).unwrap(),
+            Uint256::from_str("1461446703485210103287273052203988822378723970342").unwrap()
+        );
+        get_sqrt_price_at_tick(MAX_TICK + 1).unwrap_err();
+
+        for tick in [-887_271, -50_000, -1, 0, 1, 60, 50_000, 887_271] {
+            let sqrt_price = get_sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(get_tick_at_sqrt_price(sqrt_price).unwrap(), tick);
+            assert_eq!(
+                get_tick_at_sqrt_price(sqrt_price - Uint256::from(1u8)).unwrap(),
+                tick - 1
+            );
+        }
+    }
+
+    #[test]
+    fn test_amounts_round_trip() {
+        let (lower, upper) = (
+            get_sqrt_price_at_tick(-600).unwrap(),
+            get_sqrt_price_at_tick(600).unwrap(),
+        );
+        let sqrt_price = q96();
+        let liquidity = get_liquidity_for_amounts(
+            sqrt_price,
+            lower,
+            upper,
+            Uint128::new(1_000_000),
+            Uint128::new(1_000_000),
+        )
+        .unwrap();
+        let (token1_amount, token2_amount) =
+            get_amounts_for_liquidity(sqrt_price, lower, upper, liquidity, true).unwrap();
+        assert!(token1_amount <= Uint128::new(1_000_000));
+        assert!(token2_amount <= Uint128::new(1_000_000));
+        assert!(token1_amount >= Uint128::new(999_990));
+
+        // Below the range only token1 is deposited, above it only token2
+        let (token1_amount, token2_amount) =
+            get_amounts_for_liquidity(lower, lower, upper, liquidity, true).unwrap();
+        assert!(token2_amount.is_zero() && !token1_amount.is_zero());
+        let (token1_amount, token2_amount) =
+            get_amounts_for_liquidity(upper, lower, upper, liquidity, true).unwrap();
+        assert!(token1_amount.is_zero() && !token2_amount.is_zero());
+    }
+
+    #[test]
+    fn test_swap_step() {
+        let liquidity = Uint128::new(10u128.pow(18));
+        let target = get_sqrt_price_at_tick(-10).unwrap();
+        // 0.3% fee, the target is not reached and the whole input is spent
+        let amount = Uint256::from(1_000_000u128);
+        let step = compute_swap_step(q96(), target, liquidity, amount, Uint128::new(30)).unwrap();
+        assert!(step.sqrt_price_next < q96() && step.sqrt_price_next > target);
+        assert_eq!(step.amount_in + step.fee_amount, amount);
+        assert_eq!(step.fee_amount, Uint256::from(3_000u128));
+        // At a price of 1 the output is the input after fees minus rounding
+        assert!(step.amount_out < step.amount_in);
+        assert!(step.amount_out + Uint256::from(2u8) >= step.amount_in);
+
+        // A large input stops at the target
+        let amount = Uint256::from(10u128.pow(18));
+        let step = compute_swap_step(q96(), target, liquidity, amount, Uint128::new(30)).unwrap();
+        assert_eq!(step.sqrt_price_next, target);
+        assert!(step.amount_in + step.fee_amount < amount);
+    }
+}
@@ -1,65 +1,173 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, entry_point, to_binary, Addr, Binary, BlockInfo, CosmosMsg, Decimal, Deps, DepsMut,
-    Env, MessageInfo, Response, StdError, StdResult, Uint128, Uint256, Uint512,
-    WasmMsg,
+    attr, coins, from_binary, to_binary, Addr, Attribute, Binary, BlockInfo, CosmosMsg, Decimal,
+    Deps, DepsMut, Env, Event, Isqrt, MessageInfo, Order, Reply, Response, StdError, StdResult,
+    Storage, Uint128, Uint256, Uint512, WasmMsg,
 };
-use cw2::set_contract_version;
-use cw20::Denom::Cw20;
-use cw20::{Cw20ExecuteMsg, Denom, Expiration};
-use cw20_base::contract::query_balance;
+use cw0::nonpayable;
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Denom, Expiration};
+use cw_storage_plus::Bound;
+use pool_core::fee::{fee_decimal_to_uint128, get_fee_amount, FEE_SCALE_FACTOR};
+use pool_core::lp_token::{
+    get_burn_msg, get_instantiate_lp_token_msg, get_liquidity_amount, get_lp_token_supply,
+    get_token_balance, mint_lp_tokens, query_balance, save_lp_token,
+};
+use pool_core::msg::PoolDenomsResponse;
+use pool_core::ownership::{
+    assert_owner, execute_accept_ownership, execute_cancel_ownership_proposal,
+    execute_propose_new_owner, execute_renounce_ownership, query_ownership,
+};
+use pool_core::pause::{assert_not_paused, execute_freeze_deposits, PauseAction};
+use pool_core::pool::{
+    check_expiration, check_swap_min, commit_swap, execute_collect_protocol_fees, get_swap,
+    get_swap_output, instantiate_pool, provide_liquidity, query_fee, query_info,
+    query_protocol_fees, remove_liquidity, update_fees, validate_fee_percent, withdraw_liquidity,
+    PoolConfig, Swap,
+};
+use pool_core::state::{add_reserves, get_swap_tokens};
+use pool_core::transfer::{
+    get_bank_transfer_to_msg, get_cw20_transfer_from_msg, get_cw20_transfer_to_msg,
+    get_transfer_to_msg, validate_input_amounts,
+};
+use pool_core::{ConstantProduct, PoolCurve, PoolError};
+use semver::Version;
 use std::convert::TryInto;
-use std::str::FromStr;
 
+use crate::concentrated::{self, MAX_TICK_SPACING};
 use crate::error::ContractError;
 use crate::msg::{
-    ExecuteMsg, FeeResponse, InfoResponse, InstantiateMsg, QueryMsg, Token1ForToken2PriceResponse,
-    Token2ForToken1PriceResponse, TokenSelect,
+    AmpResponse, ExecuteMsg, InstantiateMsg, LimitsResponse, MigrateMsg, PauseResponse,
+    PositionResponse, QueryMsg, ReceiveMsg, ReverseSimulateResponse, SimulateSwapResponse,
+    TickResponse, TicksResponse, Token1ForToken2PriceResponse, Token2ForToken1PriceResponse,
+    TokenSelect,
+};
+use crate::oracle::{
+    init_price_accumulator, query_cumulative_prices, query_twap, update_price_accumulator,
+};
+use crate::stableswap::{
+    self, get_amp, load_amp, StableSwap, MAX_AMP, MAX_AMP_CHANGE, MIN_AMP, MIN_RAMP_SECONDS,
+};
+use crate::state::{
+    AmpConfig, BlockReserveChange, ConcentratedPool, Fees, FlashSwap, Limits, PauseFlags, Position,
+    ProtocolFees, AMP, BLOCK_RESERVE_CHANGE, CONCENTRATED_POOL, FEES, FLASH_SWAP, GUARDIAN, LIMITS,
+    LP_TOKEN, OWNER, OWNERSHIP_PROPOSAL, PAUSE_FLAGS, POSITIONS, POSITION_COUNT, PROTOCOL_FEES,
+    TICKS, TOKEN1, TOKEN2,
 };
-use crate::state::{Fees, Token, FEES, FROZEN, OWNER, TOKEN1, TOKEN2};
 
 // Version info for migration info
 pub const CONTRACT_NAME: &str = "crates.io:product-amm";
 pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-const FEE_SCALE_FACTOR: Uint128 = Uint128::new(10_000);
-const MAX_FEE_PERCENT: &str = "1";
-const FEE_DECIMAL_PRECISION: Uint128 = Uint128::new(10u128.pow(20));
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
 
 // Note, you can use StdResult in some functions where you do not
 // make use of the custom errors
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
-    _env: Env,
+    mut deps: DepsMut,
+    env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    let token1 = Token {
-        reserve: Uint128::zero(),
-        denom: msg.token1_denom.clone(),
-    };
-    TOKEN1.save(deps.storage, &token1)?;
+    if msg.amp.is_some() && msg.concentrated.is_some() {
+        return Err(ContractError::ConflictingPoolTypes {});
+    }
 
-    let token2 = Token {
-        denom: msg.token2_denom.clone(),
-        reserve: Uint128::zero(),
-    };
-    TOKEN2.save(deps.storage, &token2)?;
+    let response = instantiate_pool(
+        deps.branch(),
+        &env,
+        PoolConfig {
+            token1_denom: msg.token1_denom,
+            token2_denom: msg.token2_denom,
+            owner: msg.owner,
+            protocol_fee_recipient: msg.protocol_fee_recipient,
+            protocol_fee_percent: msg.protocol_fee_percent,
+            lp_fee_percent: msg.lp_fee_percent,
+            lp_token_code_id: msg.lp_token_code_id,
+        },
+    )?;
+    GUARDIAN.save(deps.storage, &None)?;
+    LIMITS.save(deps.storage, &Limits::default())?;
+
+    // Stable swap pools start without a ramp
+    if let Some(amp) = msg.amp {
+        validate_amp(amp)?;
+        let now = env.block.time.seconds();
+        AMP.save(
+            deps.storage,
+            &AmpConfig {
+                initial_amp: amp,
+                target_amp: amp,
+                start_time: now,
+                end_time: now,
+            },
+        )?;
+    }
+
+    // Concentrated liquidity pools start at the initial price without liquidity
+    if let Some(params) = msg.concentrated {
+        if params.tick_spacing == 0 || params.tick_spacing > MAX_TICK_SPACING {
+            return Err(ContractError::InvalidTickSpacing {
+                tick_spacing: params.tick_spacing,
+            });
+        }
+        let sqrt_price = concentrated::get_sqrt_price_at_tick(params.initial_tick)?;
+        CONCENTRATED_POOL.save(
+            deps.storage,
+            &ConcentratedPool {
+                tick_spacing: params.tick_spacing,
+                sqrt_price,
+                tick: concentrated::get_tick_at_sqrt_price(sqrt_price)?,
+                liquidity: Uint128::zero(),
+                fee_growth_global1: Uint256::zero(),
+                fee_growth_global2: Uint256::zero(),
+            },
+        )?;
+        POSITION_COUNT.save(deps.storage, &0)?;
+    }
+
+    init_price_accumulator(deps.storage, &env.block)?;
+
+    Ok(response)
+}
+
+fn validate_amp(amp: u64) -> Result<(), ContractError> {
+    if !(MIN_AMP..=MAX_AMP).contains(&amp) {
+        return Err(ContractError::InvalidAmp { amp });
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::CannotMigrate {
+            previous_contract: stored.contract,
+        });
+    }
+
+    let previous_version = Version::parse(&stored.version)?;
+    let new_version = Version::parse(CONTRACT_VERSION)?;
+    if new_version < previous_version {
+        return Err(ContractError::CannotMigrateVersion {
+            previous_version: stored.version,
+            new_version: CONTRACT_VERSION.to_string(),
+        });
+    }
 
     let owner = msg.owner.map(|h| deps.api.addr_validate(&h)).transpose()?;
     OWNER.save(deps.storage, &owner)?;
+    // The migration sets the owner, drop any proposal made by the previous one
+    OWNERSHIP_PROPOSAL.remove(deps.storage);
 
     let protocol_fee_recipient = deps.api.addr_validate(&msg.protocol_fee_recipient)?;
-    let total_fee_percent = msg.lp_fee_percent + msg.protocol_fee_percent;
-    let max_fee_percent = Decimal::from_str(MAX_FEE_PERCENT)?;
-    if total_fee_percent > max_fee_percent {
-        return Err(ContractError::FeesTooHigh {
-            max_fee_percent,
-            total_fee_percent,
-        });
-    }
+    validate_fee_percent(msg.lp_fee_percent, msg.protocol_fee_percent)?;
 
     let fees = Fees {
         lp_fee_percent: msg.lp_fee_percent,
@@ -68,28 +176,119 @@ pub fn instantiate(
     };
     FEES.save(deps.storage, &fees)?;
 
-    // Depositing is not frozen by default
-    FROZEN.save(deps.storage, &false)?;
-    
-    Ok(Response::new().add_attribute("key", "instantiate"))
+    // freeze_pool keeps its meaning from the single frozen flag, pausing deposits and swaps
+    let withdrawals = PAUSE_FLAGS
+        .may_load(deps.storage)?
+        .is_some_and(|flags| flags.withdrawals);
+    PAUSE_FLAGS.save(
+        deps.storage,
+        &PauseFlags {
+            deposits: msg.freeze_pool,
+            swaps: msg.freeze_pool,
+            withdrawals,
+        },
+    )?;
+    if GUARDIAN.may_load(deps.storage)?.is_none() {
+        GUARDIAN.save(deps.storage, &None)?;
+    }
+
+    // Pools migrated from a version sending protocol fees on every swap start accruing now
+    if PROTOCOL_FEES.may_load(deps.storage)?.is_none() {
+        PROTOCOL_FEES.save(deps.storage, &ProtocolFees::default())?;
+    }
+
+    // Pools from before the lp token get one through the instantiate reply. Their deposits
+    // were never tracked per provider, so only empty pools can be given an lp token.
+    let mut msgs = vec![];
+    if LP_TOKEN.may_load(deps.storage)?.is_none() {
+        let token1 = TOKEN1.load(deps.storage)?;
+        let token2 = TOKEN2.load(deps.storage)?;
+        if !token1.reserve.is_zero() || !token2.reserve.is_zero() {
+            return Err(ContractError::UntrackedLiquidity {});
+        }
+        let lp_token_code_id = msg
+            .lp_token_code_id
+            .ok_or(ContractError::MissingLpTokenCodeId {})?;
+        msgs.push(get_instantiate_lp_token_msg(
+            lp_token_code_id,
+            &env.contract.address,
+        )?);
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_submessages(msgs).add_attributes(vec![
+        attr("action", "migrate"),
+        attr("previous_version", previous_version.to_string()),
+        attr("new_version", new_version.to_string()),
+    ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    Ok(save_lp_token(deps, msg)?)
 }
 
 // And declare a custom Error variant for the ones where you will want to make use of it
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match execute_msg(deps.branch(), env, info.clone(), msg.clone()) {
+        Err(err) if is_circuit_breaker_error(&err) => {
+            if LIMITS
+                .may_load(deps.storage)?
+                .unwrap_or_default()
+                .auto_freeze
+            {
+                trip_circuit_breaker(deps, info, msg, err)
+            } else {
+                Err(err)
+            }
+        }
+        res => res,
+    }
+}
+
+fn execute_msg(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    // The lent tokens are not in the pool until the repayment check, nothing else may run
+    if FLASH_SWAP.may_load(deps.storage)?.is_some()
+        && !matches!(msg, ExecuteMsg::CompleteFlashSwap {})
+    {
+        return Err(ContractError::FlashSwapInProgress {});
+    }
+    // Only deposits and swaps take native funds, their handlers check them against the inputs
+    let payable = matches!(
+        msg,
+        ExecuteMsg::AddLiquidity { .. }
+            | ExecuteMsg::AddLiquiditySingle { .. }
+            | ExecuteMsg::Swap { .. }
+            | ExecuteMsg::SwapExactOutput { .. }
+            | ExecuteMsg::PassThroughSwap { .. }
+            | ExecuteMsg::SwapAndSendTo { .. }
+            | ExecuteMsg::CreatePosition { .. }
+    );
+    if !payable {
+        nonpayable(&info)?;
+    }
+
     match msg {
         ExecuteMsg::AddLiquidity {
             token1_amount,
             min_liquidity,
-            expiration, token2_amount } => {
-            if FROZEN.load(deps.storage)? {
-                return Err(ContractError::FrozenPool {});
-            }
+            expiration,
+            token2_amount,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Deposit)?;
+            assert_not_concentrated(deps.storage)?;
             execute_add_liquidity(
                 deps,
                 &info,
@@ -98,6 +297,7 @@ pub fn execute(
                 token1_amount,
                 token2_amount,
                 expiration,
+                None,
             )
         }
         ExecuteMsg::RemoveLiquidity {
@@ -105,7 +305,44 @@ pub fn execute(
             min_token1,
             min_token2,
             expiration,
-        } => execute_remove_liquidity(deps, info, env, amount, min_token1, min_token2, expiration),
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Withdrawal)?;
+            assert_not_concentrated(deps.storage)?;
+            execute_remove_liquidity(
+                deps, info, env, amount, min_token1, min_token2, expiration, false,
+            )
+        }
+        ExecuteMsg::AddLiquiditySingle {
+            token,
+            amount,
+            min_liquidity,
+            expiration,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Deposit)?;
+            assert_not_paused(deps.storage, PauseAction::Swap)?;
+            assert_not_concentrated(deps.storage)?;
+            execute_add_liquidity_single(deps, info, env, token, amount, min_liquidity, expiration)
+        }
+        ExecuteMsg::RemoveLiquiditySingle {
+            amount,
+            output_token,
+            min_output,
+            expiration,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Withdrawal)?;
+            // Withdrawing into a single token swaps, use RemoveLiquidity while swaps are paused
+            assert_not_paused(deps.storage, PauseAction::Swap)?;
+            assert_not_concentrated(deps.storage)?;
+            execute_remove_liquidity_single(
+                deps,
+                info,
+                env,
+                amount,
+                output_token,
+                min_output,
+                expiration,
+            )
+        }
         ExecuteMsg::Swap {
             input_token,
             input_amount,
@@ -113,9 +350,7 @@ pub fn execute(
             expiration,
             ..
         } => {
-            if FROZEN.load(deps.storage)? {
-                return Err(ContractError::FrozenPool {});
-            }
+            assert_not_paused(deps.storage, PauseAction::Swap)?;
             execute_swap(
                 deps,
                 &info,
@@ -125,241 +360,1077 @@ pub fn execute(
                 info.sender.to_string(),
                 min_output,
                 expiration,
+                false,
+            )
+        }
+        ExecuteMsg::SwapExactOutput {
+            output_token,
+            output_amount,
+            max_input,
+            expiration,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Swap)?;
+            assert_not_concentrated(deps.storage)?;
+            execute_swap_exact_output(
+                deps,
+                info,
+                env,
+                output_token,
+                output_amount,
+                max_input,
+                expiration,
+            )
+        }
+        ExecuteMsg::PassThroughSwap {
+            output_amm_address,
+            input_token,
+            input_token_amount,
+            output_min_token,
+            expiration,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Swap)?;
+            execute_pass_through_swap(
+                deps,
+                info,
+                env,
+                output_amm_address,
+                input_token,
+                input_token_amount,
+                output_min_token,
+                expiration,
+            )
+        }
+        ExecuteMsg::SwapAndSendTo {
+            input_token,
+            input_amount,
+            recipient,
+            min_token,
+            expiration,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Swap)?;
+            execute_swap(
+                deps,
+                &info,
+                input_amount,
+                env,
+                input_token,
+                recipient,
+                min_token,
+                expiration,
+                false,
             )
         }
         ExecuteMsg::UpdateConfig {
-            owner,
             protocol_fee_recipient,
             lp_fee_percent,
             protocol_fee_percent,
+            limits,
         } => execute_update_config(
             deps,
             info,
-            owner,
             lp_fee_percent,
             protocol_fee_percent,
             protocol_fee_recipient,
+            limits,
         ),
-        ExecuteMsg::FreezeDeposits { freeze } => execute_freeze_deposits(deps, info.sender, freeze),
+        ExecuteMsg::CollectProtocolFees {} => Ok(execute_collect_protocol_fees(deps, info)?),
+        ExecuteMsg::ProposeNewOwner { owner, expiry } => {
+            Ok(execute_propose_new_owner(deps, env, info, owner, expiry)?)
+        }
+        ExecuteMsg::AcceptOwnership {} => Ok(execute_accept_ownership(deps, env, info)?),
+        ExecuteMsg::CancelOwnershipProposal {} => {
+            Ok(execute_cancel_ownership_proposal(deps, info)?)
+        }
+        ExecuteMsg::RenounceOwnership {} => Ok(execute_renounce_ownership(deps, info)?),
+        ExecuteMsg::FreezeDeposits { freeze } => {
+            Ok(execute_freeze_deposits(deps, info.sender, freeze)?)
+        }
+        ExecuteMsg::UpdatePause {
+            deposits,
+            swaps,
+            withdrawals,
+        } => execute_update_pause(deps, info, deposits, swaps, withdrawals),
+        ExecuteMsg::UpdateGuardian { guardian } => execute_update_guardian(deps, info, guardian),
+        ExecuteMsg::RampAmp { target, end_time } => {
+            execute_ramp_amp(deps, info, env, target, end_time)
+        }
+        ExecuteMsg::StopRampAmp {} => execute_stop_ramp_amp(deps, info, env),
+        ExecuteMsg::FlashSwap {
+            token,
+            amount,
+            callback_msg,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Swap)?;
+            assert_not_concentrated(deps.storage)?;
+            execute_flash_swap(deps, info, env, token, amount, callback_msg)
+        }
+        ExecuteMsg::CompleteFlashSwap {} => execute_complete_flash_swap(deps, info, env),
+        ExecuteMsg::CreatePosition {
+            lower_tick,
+            upper_tick,
+            token1_amount,
+            token2_amount,
+            min_liquidity,
+            expiration,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Deposit)?;
+            execute_create_position(
+                deps,
+                info,
+                env,
+                lower_tick,
+                upper_tick,
+                token1_amount,
+                token2_amount,
+                min_liquidity,
+                expiration,
+            )
+        }
+        ExecuteMsg::WithdrawPosition {
+            id,
+            liquidity,
+            min_token1,
+            min_token2,
+            expiration,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Withdrawal)?;
+            execute_withdraw_position(
+                deps, info, env, id, liquidity, min_token1, min_token2, expiration,
+            )
+        }
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
     }
 }
 
-fn execute_freeze_deposits(
+fn is_circuit_breaker_error(err: &ContractError) -> bool {
+    matches!(
+        err,
+        ContractError::MaxPriceImpactError { .. }
+            | ContractError::MaxBlockReserveChangeError { .. }
+    )
+}
+
+// Returning the error would also revert the freeze, so the trade succeeds without swapping and
+// its input is returned. The limits are checked before a swap writes anything, except in
+// single sided withdrawals which write the withdrawal first and are only rejected.
+fn trip_circuit_breaker(
     deps: DepsMut,
-    sender: Addr,
-    freeze: bool,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+    err: ContractError,
 ) -> Result<Response, ContractError> {
-    if let Some(owner) = OWNER.load(deps.storage)? {
-        if sender != owner {
-            return Err(ContractError::UnauthorizedPoolFreeze {});
+    let mut msgs = vec![];
+    let refund_recipient = match msg {
+        ExecuteMsg::RemoveLiquiditySingle { .. } => return Err(err),
+        // The input goes to the recipient, so a pass through swap returns the intermediate
+        // token to the original sender instead of leaving it in the first pool
+        ExecuteMsg::SwapAndSendTo {
+            input_token,
+            input_amount,
+            recipient,
+            ..
+        } => {
+            let recipient = deps.api.addr_validate(&recipient)?;
+            let input_token = match input_token {
+                TokenSelect::Token1 => TOKEN1.load(deps.storage)?,
+                TokenSelect::Token2 => TOKEN2.load(deps.storage)?,
+            };
+            if let Denom::Cw20(addr) = input_token.denom {
+                msgs.push(get_cw20_transfer_from_msg(
+                    &info.sender,
+                    &recipient,
+                    &addr,
+                    input_amount,
+                )?);
+            }
+            recipient
         }
-    } else {
-        return Err(ContractError::UnauthorizedPoolFreeze {});
+        ExecuteMsg::Receive(wrapper) => {
+            let sender = deps.api.addr_validate(&wrapper.sender)?;
+            msgs.push(get_cw20_transfer_to_msg(
+                &sender,
+                &info.sender,
+                wrapper.amount,
+            )?);
+            sender
+        }
+        _ => info.sender.clone(),
+    };
+    for coin in info.funds.iter().filter(|c| !c.amount.is_zero()) {
+        msgs.push(get_bank_transfer_to_msg(
+            &refund_recipient,
+            &coin.denom,
+            coin.amount,
+        ));
     }
 
-    FROZEN.save(deps.storage, &freeze)?;
-    Ok(Response::new().add_attribute("action", "freezing-contracts"))
-}
+    PAUSE_FLAGS.update(deps.storage, |flags| -> StdResult<_> {
+        Ok(PauseFlags {
+            deposits: true,
+            swaps: true,
+            ..flags
+        })
+    })?;
 
-fn check_expiration(
-    expiration: &Option<Expiration>,
-    block: &BlockInfo,
-) -> Result<(), ContractError> {
-    match expiration {
-        Some(e) => {
-            if e.is_expired(block) {
-                return Err(ContractError::MsgExpirationError {});
-            }
-            Ok(())
-        }
-        None => Ok(()),
-    }
+    let freeze_event = Event::new("freeze")
+        .add_attribute("sender", &info.sender)
+        .add_attribute("frozen", true.to_string())
+        .add_attribute("reason", err.to_string());
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_event(freeze_event)
+        .add_attribute("action", "circuit_breaker"))
 }
 
-
-pub fn execute_add_liquidity(
+pub fn execute_receive(
     deps: DepsMut,
-    info: &MessageInfo,
     env: Env,
-    min_liquidity: Uint128,
-    token1_amount: Uint128,
-    token2_amount: Uint128,
-    expiration: Option<Expiration>,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
-    check_expiration(&expiration, &env.block)?;
-
-    let token1 = TOKEN1.load(deps.storage)?;
-    let token2 = TOKEN2.load(deps.storage)?;
-
-    let liquidity_amount = token1_amount+token2_amount;
-
-    if liquidity_amount < min_liquidity {
-        return Err(ContractError::MinLiquidityError {
+    let msg: ReceiveMsg = from_binary(&wrapper.msg)?;
+    // The tokens were already sent to the pool, act on behalf of the original sender
+    let sender_info = MessageInfo {
+        sender: deps.api.addr_validate(&wrapper.sender)?,
+        funds: vec![],
+    };
+    match msg {
+        ReceiveMsg::Swap {
+            min_output,
+            expiration,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Swap)?;
+            let input_token = get_received_token(deps.as_ref(), &info.sender)?;
+            execute_swap(
+                deps,
+                &sender_info,
+                wrapper.amount,
+                env,
+                input_token,
+                wrapper.sender,
+                min_output,
+                expiration,
+                true,
+            )
+        }
+        ReceiveMsg::AddLiquidity {
+            other_token_amount,
             min_liquidity,
-            liquidity_available: liquidity_amount,
-        });
+            expiration,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Deposit)?;
+            assert_not_concentrated(deps.storage)?;
+            let received_token = get_received_token(deps.as_ref(), &info.sender)?;
+            let (token1_amount, token2_amount, other_token) = match received_token {
+                TokenSelect::Token1 => (wrapper.amount, other_token_amount, TOKEN2),
+                TokenSelect::Token2 => (other_token_amount, wrapper.amount, TOKEN1),
+            };
+            // A cw20 hook can't carry native funds
+            if let Denom::Native(denom) = other_token.load(deps.storage)?.denom {
+                if !other_token_amount.is_zero() {
+                    return Err(ContractError::NativeDepositInReceive { denom });
+                }
+            }
+            execute_add_liquidity(
+                deps,
+                &sender_info,
+                env,
+                min_liquidity,
+                token1_amount,
+                token2_amount,
+                expiration,
+                Some(received_token),
+            )
+        }
+        ReceiveMsg::RemoveLiquidity {
+            min_token1,
+            min_token2,
+            expiration,
+        } => {
+            if info.sender != LP_TOKEN.load(deps.storage)? {
+                return Err(ContractError::Unauthorized {});
+            }
+            assert_not_paused(deps.storage, PauseAction::Withdrawal)?;
+            assert_not_concentrated(deps.storage)?;
+            execute_remove_liquidity(
+                deps,
+                sender_info,
+                env,
+                wrapper.amount,
+                min_token1,
+                min_token2,
+                expiration,
+                true,
+            )
+        }
     }
+}
 
-    // Generate cw20 transfer messages if necessary
-    let mut transfer_msgs: Vec<CosmosMsg> = vec![];
-    if let Cw20(addr) = token1.denom {
-        transfer_msgs.push(get_cw20_transfer_from_msg(
-            &info.sender,
-            &env.contract.address,
-            &addr,
-            token1_amount,
-        )?)
-    }
-    if let Cw20(addr) = token2.denom.clone() {
-        transfer_msgs.push(get_cw20_transfer_from_msg(
-            &info.sender,
-            &env.contract.address,
-            &addr,
-            token2_amount,
-        )?)
+fn get_received_token(deps: Deps, cw20_addr: &Addr) -> Result<TokenSelect, ContractError> {
+    if TOKEN1.load(deps.storage)?.denom == Denom::Cw20(cw20_addr.clone()) {
+        Ok(TokenSelect::Token1)
+    } else if TOKEN2.load(deps.storage)?.denom == Denom::Cw20(cw20_addr.clone()) {
+        Ok(TokenSelect::Token2)
+    } else {
+        Err(ContractError::Unauthorized {})
     }
-
-
-    TOKEN1.update(deps.storage, |mut token1| -> Result<_, ContractError> {
-        token1.reserve += token1_amount;
-        Ok(token1)
-    })?;
-    TOKEN2.update(deps.storage, |mut token2| -> Result<_, ContractError> {
-        token2.reserve += token2_amount;
-        Ok(token2)
-    })?;
-
-    Ok(Response::new()
-        .add_messages(transfer_msgs)
-        .add_attributes(vec![
-            attr("token1_amount", token1_amount),
-            attr("token2_amount", token2_amount),
-            attr("liquidity_received", liquidity_amount),
-        ]))
 }
 
-fn get_cw20_transfer_from_msg(
-    owner: &Addr,
-    recipient: &Addr,
-    token_addr: &Addr,
-    token_amount: Uint128,
-) -> StdResult<CosmosMsg> {
-    // create transfer cw20 msg
-    let transfer_cw20_msg = Cw20ExecuteMsg::TransferFrom {
-        owner: owner.into(),
-        recipient: recipient.into(),
-        amount: token_amount,
-    };
-    let exec_cw20_transfer = WasmMsg::Execute {
-        contract_addr: token_addr.into(),
-        msg: to_binary(&transfer_cw20_msg)?,
-        funds: vec![],
-    };
-    let cw20_transfer_cosmos_msg: CosmosMsg = exec_cw20_transfer.into();
-    Ok(cw20_transfer_cosmos_msg)
+// Liquidity of concentrated liquidity pools is in positions, the lp token and the reserve based
+// pricing don't apply to them
+fn assert_not_concentrated(storage: &dyn Storage) -> Result<(), ContractError> {
+    if CONCENTRATED_POOL.may_load(storage)?.is_some() {
+        return Err(ContractError::ConcentratedPoolUnsupported {});
+    }
+    Ok(())
 }
 
-pub fn execute_update_config(
+pub fn execute_update_pause(
     deps: DepsMut,
     info: MessageInfo,
-    new_owner: Option<String>,
-    lp_fee_percent: Decimal,
-    protocol_fee_percent: Decimal,
-    protocol_fee_recipient: String,
+    deposits: Option<bool>,
+    swaps: Option<bool>,
+    withdrawals: Option<bool>,
 ) -> Result<Response, ContractError> {
-    let owner = OWNER.load(deps.storage)?;
-    if Some(info.sender) != owner {
+    let is_owner = assert_owner(deps.as_ref(), &info.sender).is_ok();
+    if !is_owner && GUARDIAN.load(deps.storage)?.as_ref() != Some(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
 
-    let new_owner_addr = new_owner
-        .as_ref()
-        .map(|h| deps.api.addr_validate(h))
-        .transpose()?;
-    OWNER.save(deps.storage, &new_owner_addr)?;
-
-    let total_fee_percent = lp_fee_percent + protocol_fee_percent;
-    let max_fee_percent = Decimal::from_str(MAX_FEE_PERCENT)?;
-    if total_fee_percent > max_fee_percent {
-        return Err(ContractError::FeesTooHigh {
-            max_fee_percent,
-            total_fee_percent,
-        });
-    }
-
-    let protocol_fee_recipient = deps.api.addr_validate(&protocol_fee_recipient)?;
-    let updated_fees = Fees {
-        protocol_fee_recipient: protocol_fee_recipient.clone(),
-        lp_fee_percent,
-        protocol_fee_percent,
+    let flags = PAUSE_FLAGS.load(deps.storage)?;
+    let updated_flags = PauseFlags {
+        deposits: deposits.unwrap_or(flags.deposits),
+        swaps: swaps.unwrap_or(flags.swaps),
+        withdrawals: withdrawals.unwrap_or(flags.withdrawals),
     };
-    FEES.save(deps.storage, &updated_fees)?;
+    let unpauses = (flags.deposits && !updated_flags.deposits)
+        || (flags.swaps && !updated_flags.swaps)
+        || (flags.withdrawals && !updated_flags.withdrawals);
+    if unpauses && !is_owner {
+        return Err(ContractError::UnauthorizedUnpause {});
+    }
+    PAUSE_FLAGS.save(deps.storage, &updated_flags)?;
 
-    let new_owner = new_owner.unwrap_or_default();
-    Ok(Response::new().add_attributes(vec![
-        attr("new_owner", new_owner),
-        attr("lp_fee_percent", lp_fee_percent.to_string()),
-        attr("protocol_fee_percent", protocol_fee_percent.to_string()),
-        attr("protocol_fee_recipient", protocol_fee_recipient.to_string()),
-    ]))
+    let pause_event = Event::new("pause")
+        .add_attribute("sender", info.sender)
+        .add_attribute("deposits", updated_flags.deposits.to_string())
+        .add_attribute("swaps", updated_flags.swaps.to_string())
+        .add_attribute("withdrawals", updated_flags.withdrawals.to_string());
+    Ok(Response::new()
+        .add_event(pause_event)
+        .add_attribute("action", "update_pause"))
 }
 
-pub fn execute_remove_liquidity(
+pub fn execute_update_guardian(
+    deps: DepsMut,
+    info: MessageInfo,
+    guardian: Option<String>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+
+    let guardian = guardian
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    GUARDIAN.save(deps.storage, &guardian)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_guardian"),
+        attr(
+            "guardian",
+            guardian.map_or_else(|| "none".to_string(), |g| g.into_string()),
+        ),
+    ]))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_add_liquidity(
+    mut deps: DepsMut,
+    info: &MessageInfo,
+    env: Env,
+    min_liquidity: Uint128,
+    token1_amount: Uint128,
+    token2_amount: Uint128,
+    expiration: Option<Expiration>,
+    received_token: Option<TokenSelect>,
+) -> Result<Response, ContractError> {
+    update_price_accumulator(deps.storage, &env.block)?;
+
+    let curve = get_curve(load_amp(deps.storage, &env.block)?);
+    let deposit = provide_liquidity(
+        deps.branch(),
+        info,
+        &env,
+        curve.as_ref(),
+        token1_amount,
+        token2_amount,
+        min_liquidity,
+        expiration,
+        received_token,
+    )?;
+
+    let add_liquidity_event = liquidity_event(
+        deps.storage,
+        "add_liquidity",
+        &info.sender,
+        deposit.token1_amount,
+        deposit.token2_amount,
+        deposit.lp_token_supply,
+    )?
+    .add_attribute("liquidity_minted", deposit.liquidity_amount);
+
+    Ok(deposit.response.add_event(add_liquidity_event))
+}
+// Deposits and withdrawals carry the pool state after the action
+fn liquidity_event(
+    storage: &dyn Storage,
+    ty: &str,
+    sender: &Addr,
+    token1_amount: Uint128,
+    token2_amount: Uint128,
+    lp_token_supply: Uint128,
+) -> StdResult<Event> {
+    let token1 = TOKEN1.load(storage)?;
+    let token2 = TOKEN2.load(storage)?;
+    Ok(Event::new(ty)
+        .add_attribute("sender", sender)
+        .add_attribute("token1_denom", denom_to_string(&token1.denom))
+        .add_attribute("token1_amount", token1_amount)
+        .add_attribute("token2_denom", denom_to_string(&token2.denom))
+        .add_attribute("token2_amount", token2_amount)
+        .add_attribute("token1_reserve", token1.reserve)
+        .add_attribute("token2_reserve", token2.reserve)
+        .add_attribute("lp_token_supply", lp_token_supply))
+}
+
+pub fn execute_add_liquidity_single(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    input_token_enum: TokenSelect,
+    amount: Uint128,
+    min_liquidity: Uint128,
+    expiration: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    check_expiration(&expiration, &env.block)?;
+
+    let input_token = match input_token_enum {
+        TokenSelect::Token1 => TOKEN1.load(deps.storage)?,
+        TokenSelect::Token2 => TOKEN2.load(deps.storage)?,
+    };
+    validate_input_amounts(&info.funds, &[(amount, &input_token.denom)])?;
+
+    let lp_token_addr = LP_TOKEN.load(deps.storage)?;
+    let lp_token_supply = get_lp_token_supply(deps.as_ref(), &lp_token_addr)?;
+    if lp_token_supply.is_zero() {
+        return Err(StdError::generic_err("No liquidity").into());
+    }
+
+    let output_token = match input_token_enum {
+        TokenSelect::Token1 => TOKEN2.load(deps.storage)?,
+        TokenSelect::Token2 => TOKEN1.load(deps.storage)?,
+    };
+    let fees = FEES.load(deps.storage)?;
+    let total_fee_percent = fees.lp_fee_percent + fees.protocol_fee_percent;
+    let amp = load_amp(deps.storage, &env.block)?;
+    let swap_amount = match amp {
+        None => get_single_sided_swap_amount(amount, input_token.reserve, total_fee_percent)?,
+        Some(amp) => get_stable_single_sided_swap_amount(
+            &StableSwap { amp },
+            &input_token_enum,
+            amount,
+            input_token.reserve,
+            output_token.reserve,
+            total_fee_percent,
+        )?,
+    };
+    let deposit_amount = amount - swap_amount;
+
+    // Only the swapped part of native funds goes through the swap
+    let swap_info = MessageInfo {
+        sender: info.sender.clone(),
+        funds: match &input_token.denom {
+            Denom::Native(denom) => coins(swap_amount.u128(), denom),
+            Denom::Cw20(_) => vec![],
+        },
+    };
+    let (_, token_bought, mut msgs, swap_event) = swap_input(
+        deps.branch(),
+        &swap_info,
+        &env,
+        input_token_enum.clone(),
+        swap_amount,
+        Uint128::zero(),
+        false,
+        &env.contract.address,
+    )?;
+    if let Denom::Cw20(addr) = &input_token.denom {
+        msgs.push(get_cw20_transfer_from_msg(
+            &info.sender,
+            &env.contract.address,
+            addr,
+            deposit_amount,
+        )?);
+    }
+
+    // The bought tokens stay in the pool and are deposited with the rest of the input
+    let (token1_amount, token2_amount) = match input_token_enum {
+        TokenSelect::Token1 => (deposit_amount, token_bought),
+        TokenSelect::Token2 => (token_bought, deposit_amount),
+    };
+    let token1 = TOKEN1.load(deps.storage)?;
+    let token2 = TOKEN2.load(deps.storage)?;
+    let liquidity = get_curve(amp).mint_liquidity(
+        token1_amount,
+        token2_amount,
+        token1.reserve,
+        token2.reserve,
+        lp_token_supply,
+    )?;
+    let liquidity_amount = get_liquidity_amount(liquidity, lp_token_supply, min_liquidity)?;
+
+    // Rounding dust left over from the ratio is kept by the pool
+    add_reserves(deps.storage, token1_amount, token2_amount)?;
+
+    msgs.push(mint_lp_tokens(
+        &info.sender,
+        liquidity_amount,
+        &lp_token_addr,
+    )?);
+
+    let add_liquidity_event = liquidity_event(
+        deps.storage,
+        "add_liquidity",
+        &info.sender,
+        token1_amount,
+        token2_amount,
+        lp_token_supply + liquidity_amount,
+    )?
+    .add_attribute("liquidity_minted", liquidity_amount);
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_event(swap_event)
+        .add_event(add_liquidity_event)
+        .add_attributes(vec![
+            attr("input_amount", amount),
+            attr("swap_amount", swap_amount),
+            attr("token1_amount", token1_amount),
+            attr("token2_amount", token2_amount),
+            attr("liquidity_received", liquidity_amount),
+        ]))
+}
+
+// Part of a single sided deposit to swap, so the rest matches the pool ratio after the swap.
+// Solves (a - s) / (r + s) = (1 - f) * s / r for s, scaled by FEE_SCALE_FACTOR:
+// s = (sqrt(((2 - f) * r)^2 + 4 * (1 - f) * a * r) - (2 - f) * r) / (2 * (1 - f))
+fn get_single_sided_swap_amount(
+    amount: Uint128,
+    reserve: Uint128,
+    fee_percent: Decimal,
+) -> StdResult<Uint128> {
+    let fee_percent = fee_decimal_to_uint128(fee_percent)?;
+    let fee_reduction_percent = FEE_SCALE_FACTOR - fee_percent;
+    let reserve_factor = Uint512::from(reserve.full_mul(FEE_SCALE_FACTOR + fee_reduction_percent));
+    let discriminant = reserve_factor
+        .checked_mul(reserve_factor)
+        .map_err(StdError::overflow)?
+        .checked_add(
+            Uint512::from(amount.full_mul(reserve))
+                .checked_mul(Uint512::from(
+                    fee_reduction_percent.full_mul(FEE_SCALE_FACTOR) * Uint256::from(4u8),
+                ))
+                .map_err(StdError::overflow)?,
+        )
+        .map_err(StdError::overflow)?;
+
+    Ok((discriminant.isqrt() - reserve_factor)
+        .checked_div(Uint512::from(fee_reduction_percent.u128() * 2))
+        .map_err(StdError::divide_by_zero)?
+        .try_into()?)
+}
+
+// Stable swap counterpart of get_single_sided_swap_amount, the curve has no closed form so the
+// swap amount s is bisected until (a - s) / (x + s) matches o / (y - o) for the output o
+fn get_stable_single_sided_swap_amount(
+    curve: &StableSwap,
+    input_token: &TokenSelect,
+    amount: Uint128,
+    input_reserve: Uint128,
+    output_reserve: Uint128,
+    fee_percent: Decimal,
+) -> StdResult<Uint128> {
+    let (mut low, mut high) = (Uint128::zero(), amount);
+    while high - low > Uint128::new(1) {
+        let swap_amount = low + (high - low) / Uint128::new(2);
+        let output = curve.swap_output(
+            input_token,
+            swap_amount,
+            input_reserve,
+            output_reserve,
+            fee_percent,
+        )?;
+        let deposit_ratio = (amount - swap_amount).full_mul(output_reserve - output);
+        let pool_ratio = output.full_mul(input_reserve + swap_amount);
+        if deposit_ratio > pool_ratio {
+            low = swap_amount;
+        } else {
+            high = swap_amount;
+        }
+    }
+    Ok(low)
+}
+
+pub fn execute_update_config(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    lp_fee_percent: Decimal,
+    protocol_fee_percent: Decimal,
+    protocol_fee_recipient: String,
+    limits: Option<Limits>,
+) -> Result<Response, ContractError> {
+    let mut update_config_event = update_fees(
+        deps.branch(),
+        &info.sender,
+        lp_fee_percent,
+        protocol_fee_percent,
+        protocol_fee_recipient.clone(),
+    )?;
+
+    if let Some(limits) = limits {
+        LIMITS.save(deps.storage, &limits)?;
+        update_config_event = update_config_event
+            .add_attribute(
+                "max_price_impact",
+                optional_to_string(limits.max_price_impact),
+            )
+            .add_attribute(
+                "max_block_reserve_change",
+                optional_to_string(limits.max_block_reserve_change),
+            )
+            .add_attribute("auto_freeze", limits.auto_freeze.to_string());
+    }
+
+    Ok(Response::new()
+        .add_event(update_config_event)
+        .add_attributes(vec![
+            attr("lp_fee_percent", lp_fee_percent.to_string()),
+            attr("protocol_fee_percent", protocol_fee_percent.to_string()),
+            attr("protocol_fee_recipient", protocol_fee_recipient),
+        ]))
+}
+
+// Attribute values can't be empty, disabled limits are reported as "none"
+fn optional_to_string(value: Option<Decimal>) -> String {
+    value.map_or_else(|| "none".to_string(), |v| v.to_string())
+}
+
+pub fn execute_ramp_amp(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    target: u64,
+    end_time: u64,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+
+    let amp_config = AMP
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NotStableSwapPool {})?;
+    validate_amp(target)?;
+
+    let now = env.block.time.seconds();
+    let min_end_time = now + MIN_RAMP_SECONDS;
+    if end_time < min_end_time {
+        return Err(ContractError::AmpRampTooShort { min_end_time });
+    }
+    // The ramp starts from the current amplification, also when changing a ramp in progress
+    let current = get_amp(&amp_config, &env.block);
+    if target > current * MAX_AMP_CHANGE || target * MAX_AMP_CHANGE < current {
+        return Err(ContractError::AmpChangeTooLarge { current, target });
+    }
+
+    AMP.save(
+        deps.storage,
+        &AmpConfig {
+            initial_amp: current,
+            target_amp: target,
+            start_time: now,
+            end_time,
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "ramp_amp"),
+        attr("initial_amp", current.to_string()),
+        attr("target_amp", target.to_string()),
+        attr("end_time", end_time.to_string()),
+    ]))
+}
+
+pub fn execute_stop_ramp_amp(
     deps: DepsMut,
     info: MessageInfo,
     env: Env,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+
+    let amp_config = AMP
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NotStableSwapPool {})?;
+    let current = get_amp(&amp_config, &env.block);
+    let now = env.block.time.seconds();
+    AMP.save(
+        deps.storage,
+        &AmpConfig {
+            initial_amp: current,
+            target_amp: current,
+            start_time: now,
+            end_time: now,
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "stop_ramp_amp"),
+        attr("amp", current.to_string()),
+    ]))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_remove_liquidity(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
     amount: Uint128,
     min_token1: Uint128,
     min_token2: Uint128,
     expiration: Option<Expiration>,
+    lp_received: bool,
+) -> Result<Response, ContractError> {
+    update_price_accumulator(deps.storage, &env.block)?;
+
+    let curve = get_curve(load_amp(deps.storage, &env.block)?);
+    let withdrawal = withdraw_liquidity(
+        deps.branch(),
+        &info,
+        &env,
+        curve.as_ref(),
+        amount,
+        min_token1,
+        min_token2,
+        expiration,
+        lp_received,
+    )?;
+
+    let remove_liquidity_event = liquidity_event(
+        deps.storage,
+        "remove_liquidity",
+        &info.sender,
+        withdrawal.token1_amount,
+        withdrawal.token2_amount,
+        withdrawal.lp_token_supply,
+    )?
+    .add_attribute("liquidity_burned", amount);
+
+    Ok(withdrawal.response.add_event(remove_liquidity_event))
+}
+#[allow(clippy::too_many_arguments)]
+pub fn execute_remove_liquidity_single(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    amount: Uint128,
+    output_token_enum: TokenSelect,
+    min_output: Uint128,
+    expiration: Option<Expiration>,
 ) -> Result<Response, ContractError> {
     check_expiration(&expiration, &env.block)?;
 
+    update_price_accumulator(deps.storage, &env.block)?;
+
+    let lp_token_addr = LP_TOKEN.load(deps.storage)?;
+    let balance = get_token_balance(deps.as_ref(), &lp_token_addr, &info.sender)?;
+    if amount > balance {
+        return Err(PoolError::InsufficientLiquidityError {
+            requested: amount,
+            available: balance,
+        }
+        .into());
+    }
+    let total_token_supply = get_lp_token_supply(deps.as_ref(), &lp_token_addr)?;
+
+    let curve = get_curve(load_amp(deps.storage, &env.block)?);
+    let (token1_amount, token2_amount) = remove_liquidity(
+        deps.storage,
+        curve.as_ref(),
+        amount,
+        total_token_supply,
+        Uint128::zero(),
+        Uint128::zero(),
+    )?;
+    let (output_amount, other_amount, other_token_enum) = match output_token_enum {
+        TokenSelect::Token1 => (token1_amount, token2_amount, TokenSelect::Token2),
+        TokenSelect::Token2 => (token2_amount, token1_amount, TokenSelect::Token1),
+    };
+    let remove_liquidity_event = liquidity_event(
+        deps.storage,
+        "remove_liquidity",
+        &info.sender,
+        token1_amount,
+        token2_amount,
+        total_token_supply - amount,
+    )?
+    .add_attribute("liquidity_burned", amount);
+
+    // The withdrawn other token is still held by the pool, swap it in place
+    let (output_denom, token_bought, mut msgs, swap_event) = swap_input(
+        deps.branch(),
+        &info,
+        &env,
+        other_token_enum,
+        other_amount,
+        Uint128::zero(),
+        true,
+        &info.sender,
+    )?;
+    let total_output = output_amount + token_bought;
+    check_swap_min(min_output, total_output)?;
+
+    msgs.push(get_transfer_to_msg(
+        &info.sender,
+        &output_denom,
+        total_output,
+    )?);
+    msgs.push(get_burn_msg(&lp_token_addr, &info.sender, amount)?);
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_event(remove_liquidity_event)
+        .add_event(swap_event)
+        .add_attributes(vec![
+            attr("liquidity_burned", amount),
+            attr("output_amount", total_output),
+            attr("token_swapped", other_amount),
+        ]))
+}
+
+fn to_liquidity_delta(liquidity: Uint128) -> StdResult<i128> {
+    liquidity
+        .u128()
+        .try_into()
+        .map_err(|_| StdError::generic_err("Liquidity overflow"))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_create_position(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    lower_tick: i32,
+    upper_tick: i32,
+    token1_amount: Uint128,
+    token2_amount: Uint128,
+    min_liquidity: Uint128,
+    expiration: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    check_expiration(&expiration, &env.block)?;
+
+    let mut pool = CONCENTRATED_POOL
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NotConcentratedPool {})?;
+    concentrated::validate_ticks(lower_tick, upper_tick, pool.tick_spacing)?;
 
     let token1 = TOKEN1.load(deps.storage)?;
     let token2 = TOKEN2.load(deps.storage)?;
-    let total_token_supply = token1.reserve+token2.reserve;
+    validate_input_amounts(
+        &info.funds,
+        &[
+            (token1_amount, &token1.denom),
+            (token2_amount, &token2.denom),
+        ],
+    )?;
 
-    if amount > total_token_supply {
-        return Err(ContractError::InsufficientLiquidityError {
-            requested: amount,
-            available: total_token_supply,
-        });
+    let liquidity = concentrated::get_liquidity_for_amounts(
+        pool.sqrt_price,
+        concentrated::get_sqrt_price_at_tick(lower_tick)?,
+        concentrated::get_sqrt_price_at_tick(upper_tick)?,
+        token1_amount,
+        token2_amount,
+    )?;
+    let min_liquidity = std::cmp::max(min_liquidity, Uint128::new(1));
+    if liquidity < min_liquidity {
+        return Err(PoolError::MinLiquidityError {
+            min_liquidity,
+            liquidity_available: liquidity,
+        }
+        .into());
     }
 
-    let token1_amount = amount
-        .checked_mul(token1.reserve)
-        .map_err(StdError::overflow)?
-        .checked_div(total_token_supply)
-        .map_err(StdError::divide_by_zero)?;
+    update_price_accumulator(deps.storage, &env.block)?;
+
+    let id = POSITION_COUNT.load(deps.storage)? + 1;
+    let mut position = Position {
+        owner: info.sender.clone(),
+        lower_tick,
+        upper_tick,
+        liquidity: Uint128::zero(),
+        fee_growth_inside1_last: Uint256::zero(),
+        fee_growth_inside2_last: Uint256::zero(),
+        tokens_owed1: Uint128::zero(),
+        tokens_owed2: Uint128::zero(),
+    };
+    let (token1_deposit, token2_deposit) = concentrated::update_position(
+        deps.storage,
+        &mut pool,
+        &mut position,
+        to_liquidity_delta(liquidity)?,
+    )?;
+    // Deposits are rounded up, which can exceed the given amounts by a unit
+    for (max_token, tokens_required) in [
+        (token1_amount, token1_deposit),
+        (token2_amount, token2_deposit),
+    ] {
+        if tokens_required > max_token {
+            return Err(PoolError::MaxTokenError {
+                max_token,
+                tokens_required,
+            }
+            .into());
+        }
+    }
+    POSITIONS.save(deps.storage, id, &position)?;
+    POSITION_COUNT.save(deps.storage, &id)?;
+    CONCENTRATED_POOL.save(deps.storage, &pool)?;
+
+    TOKEN1.update(deps.storage, |mut token1| -> StdResult<_> {
+        token1.reserve = token1
+            .reserve
+            .checked_add(token1_deposit)
+            .map_err(StdError::overflow)?;
+        Ok(token1)
+    })?;
+    TOKEN2.update(deps.storage, |mut token2| -> StdResult<_> {
+        token2.reserve = token2
+            .reserve
+            .checked_add(token2_deposit)
+            .map_err(StdError::overflow)?;
+        Ok(token2)
+    })?;
+
+    // Cw20 deposits are pulled from the sender, native amounts sent above them are refunded
+    let mut msgs = vec![];
+    for (denom, amount, deposit) in [
+        (&token1.denom, token1_amount, token1_deposit),
+        (&token2.denom, token2_amount, token2_deposit),
+    ] {
+        match denom {
+            Denom::Cw20(addr) if !deposit.is_zero() => msgs.push(get_cw20_transfer_from_msg(
+                &info.sender,
+                &env.contract.address,
+                addr,
+                deposit,
+            )?),
+            Denom::Native(denom) if amount > deposit => msgs.push(get_bank_transfer_to_msg(
+                &info.sender,
+                denom,
+                amount - deposit,
+            )),
+            _ => {}
+        }
+    }
+
+    let position_event = Event::new("create_position")
+        .add_attribute("sender", &info.sender)
+        .add_attribute("position_id", id.to_string())
+        .add_attribute("lower_tick", lower_tick.to_string())
+        .add_attribute("upper_tick", upper_tick.to_string())
+        .add_attribute("liquidity", liquidity)
+        .add_attribute("token1_amount", token1_deposit)
+        .add_attribute("token2_amount", token2_deposit)
+        .add_attributes(reserve_attributes(deps.storage)?);
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_event(position_event)
+        .add_attributes(vec![
+            attr("action", "create_position"),
+            attr("position_id", id.to_string()),
+        ]))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_withdraw_position(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    id: u64,
+    liquidity: Uint128,
+    min_token1: Uint128,
+    min_token2: Uint128,
+    expiration: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    check_expiration(&expiration, &env.block)?;
+
+    let mut pool = CONCENTRATED_POOL
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NotConcentratedPool {})?;
+    let mut position = POSITIONS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::PositionNotFound { id })?;
+    if position.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if liquidity > position.liquidity {
+        return Err(PoolError::InsufficientLiquidityError {
+            requested: liquidity,
+            available: position.liquidity,
+        }
+        .into());
+    }
+
+    update_price_accumulator(deps.storage, &env.block)?;
+
+    let (token1_withdrawn, token2_withdrawn) = concentrated::update_position(
+        deps.storage,
+        &mut pool,
+        &mut position,
+        -to_liquidity_delta(liquidity)?,
+    )?;
+    let (token1_fees, token2_fees) = (position.tokens_owed1, position.tokens_owed2);
+    let token1_amount = token1_withdrawn + token1_fees;
+    let token2_amount = token2_withdrawn + token2_fees;
     if token1_amount < min_token1 {
-        return Err(ContractError::MinToken1Error {
+        return Err(PoolError::MinToken1Error {
             requested: min_token1,
             available: token1_amount,
-        });
+        }
+        .into());
     }
-
-    let token2_amount = amount
-        .checked_mul(token2.reserve)
-        .map_err(StdError::overflow)?
-        .checked_div(total_token_supply)
-        .map_err(StdError::divide_by_zero)?;
     if token2_amount < min_token2 {
-        return Err(ContractError::MinToken2Error {
+        return Err(PoolError::MinToken2Error {
             requested: min_token2,
             available: token2_amount,
-        });
+        }
+        .into());
     }
 
-    TOKEN1.update(deps.storage, |mut token1| -> Result<_, ContractError> {
+    // Fees are always collected, so an empty position has nothing left
+    position.tokens_owed1 = Uint128::zero();
+    position.tokens_owed2 = Uint128::zero();
+    if position.liquidity.is_zero() {
+        POSITIONS.remove(deps.storage, id);
+    } else {
+        POSITIONS.save(deps.storage, id, &position)?;
+    }
+    CONCENTRATED_POOL.save(deps.storage, &pool)?;
+
+    let token1 = TOKEN1.update(deps.storage, |mut token1| -> StdResult<_> {
         token1.reserve = token1
             .reserve
             .checked_sub(token1_amount)
             .map_err(StdError::overflow)?;
         Ok(token1)
     })?;
-
-    TOKEN2.update(deps.storage, |mut token2| -> Result<_, ContractError> {
+    let token2 = TOKEN2.update(deps.storage, |mut token2| -> StdResult<_> {
         token2.reserve = token2
             .reserve
             .checked_sub(token2_amount)
@@ -367,335 +1438,1052 @@ pub fn execute_remove_liquidity(
         Ok(token2)
     })?;
 
-    let token1_transfer_msg = match token1.denom {
-        Denom::Cw20(addr) => get_cw20_transfer_to_msg(&info.sender, &addr, token1_amount)?,
-        Denom::Native(_denom) => {unimplemented!()},
+    let mut msgs = vec![];
+    for (denom, amount) in [
+        (&token1.denom, token1_amount),
+        (&token2.denom, token2_amount),
+    ] {
+        if amount.is_zero() {
+            continue;
+        }
+        msgs.push(get_transfer_to_msg(&info.sender, denom, amount)?);
+    }
+
+    let position_event = Event::new("withdraw_position")
+        .add_attribute("sender", &info.sender)
+        .add_attribute("position_id", id.to_string())
+        .add_attribute("liquidity", liquidity)
+        .add_attribute("token1_amount", token1_withdrawn)
+        .add_attribute("token2_amount", token2_withdrawn)
+        .add_attribute("token1_fees", token1_fees)
+        .add_attribute("token2_fees", token2_fees)
+        .add_attributes(reserve_attributes(deps.storage)?);
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_event(position_event)
+        .add_attributes(vec![
+            attr("action", "withdraw_position"),
+            attr("position_id", id.to_string()),
+        ]))
+}
+
+// The pool curve, amp is only set for stable swap pools
+fn get_curve(amp: Option<u64>) -> Box<dyn PoolCurve> {
+    match amp {
+        Some(amp) => Box::new(StableSwap { amp }),
+        None => Box::new(ConstantProduct),
+    }
+}
+
+// Output tokens per input token for an infinitesimal swap
+fn get_spot_price(
+    amp: Option<u64>,
+    input_reserve: Uint128,
+    output_reserve: Uint128,
+) -> StdResult<Decimal> {
+    match amp {
+        None => Ok(Decimal::from_ratio(output_reserve, input_reserve)),
+        Some(amp) => stableswap::get_spot_price(amp, input_reserve, output_reserve),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_swap(
+    deps: DepsMut,
+    info: &MessageInfo,
+    input_amount: Uint128,
+    _env: Env,
+    input_token_enum: TokenSelect,
+    recipient: String,
+    min_token: Uint128,
+    expiration: Option<Expiration>,
+    input_received: bool,
+) -> Result<Response, ContractError> {
+    check_expiration(&expiration, &_env.block)?;
+
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let (output_denom, token_bought, mut msgs, swap_event) = swap_input(
+        deps,
+        info,
+        &_env,
+        input_token_enum,
+        input_amount,
+        min_token,
+        input_received,
+        &recipient,
+    )?;
+
+    // Create transfer to message
+    msgs.push(get_transfer_to_msg(
+        &recipient,
+        &output_denom,
+        token_bought,
+    )?);
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_event(swap_event)
+        .add_attributes(vec![
+            attr("native_sold", input_amount),
+            attr("token_bought", token_bought),
+        ]))
+}
+
+pub fn execute_swap_exact_output(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    output_token_enum: TokenSelect,
+    output_amount: Uint128,
+    max_input: Uint128,
+    expiration: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    check_expiration(&expiration, &env.block)?;
+
+    let (input_token_enum, input_token, output_token) = match output_token_enum {
+        TokenSelect::Token1 => (
+            TokenSelect::Token2,
+            TOKEN2.load(deps.storage)?,
+            TOKEN1.load(deps.storage)?,
+        ),
+        TokenSelect::Token2 => (
+            TokenSelect::Token1,
+            TOKEN1.load(deps.storage)?,
+            TOKEN2.load(deps.storage)?,
+        ),
     };
-    let token2_transfer_msg = match token2.denom {
-        Denom::Cw20(addr) => get_cw20_transfer_to_msg(&info.sender, &addr, token2_amount)?,
-        Denom::Native(_denom) => {unimplemented!()},
+
+    // Native input is sent as max_input, the unused part is refunded
+    validate_input_amounts(&info.funds, &[(max_input, &input_token.denom)])?;
+
+    let fees = FEES.load(deps.storage)?;
+    let total_fee_percent = fees.lp_fee_percent + fees.protocol_fee_percent;
+    let input_amount = get_curve(load_amp(deps.storage, &env.block)?).swap_input(
+        &input_token_enum,
+        output_amount,
+        input_token.reserve,
+        output_token.reserve,
+        total_fee_percent,
+    )?;
+    if input_amount > max_input {
+        return Err(ContractError::SwapMaxInputError {
+            max: max_input,
+            required: input_amount,
+        });
+    }
+
+    let swap_info = MessageInfo {
+        sender: info.sender.clone(),
+        funds: match &input_token.denom {
+            Denom::Native(denom) => coins(input_amount.u128(), denom),
+            Denom::Cw20(_) => vec![],
+        },
     };
+    let (output_denom, token_bought, mut msgs, swap_event) = swap_input(
+        deps,
+        &swap_info,
+        &env,
+        input_token_enum,
+        input_amount,
+        output_amount,
+        false,
+        &info.sender,
+    )?;
+
+    msgs.push(get_transfer_to_msg(
+        &info.sender,
+        &output_denom,
+        token_bought,
+    )?);
+
+    let refund_amount = max_input - input_amount;
+    if let Denom::Native(denom) = input_token.denom {
+        if !refund_amount.is_zero() {
+            msgs.push(get_bank_transfer_to_msg(
+                &info.sender,
+                &denom,
+                refund_amount,
+            ));
+        }
+    }
 
     Ok(Response::new()
-        .add_messages(vec![
-            token1_transfer_msg,
-            token2_transfer_msg,
-        ])
+        .add_messages(msgs)
+        .add_event(swap_event)
         .add_attributes(vec![
-            attr("liquidity_burned", amount),
-            attr("token1_returned", token1_amount),
-            attr("token2_returned", token2_amount),
+            attr("native_sold", input_amount),
+            attr("token_bought", token_bought),
         ]))
 }
 
-fn get_cw20_transfer_to_msg(
+// Takes the input token from the sender and updates the reserves, the caller is
+// responsible for sending the returned amount of the output denom to the recipient
+#[allow(clippy::too_many_arguments)]
+fn swap_input(
+    deps: DepsMut,
+    info: &MessageInfo,
+    env: &Env,
+    input_token_enum: TokenSelect,
+    input_amount: Uint128,
+    min_token: Uint128,
+    input_received: bool,
     recipient: &Addr,
-    token_addr: &Addr,
-    token_amount: Uint128,
-) -> StdResult<CosmosMsg> {
-    // create transfer cw20 msg
-    let transfer_cw20_msg = Cw20ExecuteMsg::Transfer {
-        recipient: recipient.into(),
-        amount: token_amount,
-    };
-    let exec_cw20_transfer = WasmMsg::Execute {
-        contract_addr: token_addr.into(),
-        msg: to_binary(&transfer_cw20_msg)?,
-        funds: vec![],
+) -> Result<(Denom, Uint128, Vec<CosmosMsg>, Event), ContractError> {
+    let (input_token, output_token) = get_swap_tokens(deps.storage, &input_token_enum)?;
+
+    // Received input is already held by the pool
+    if !input_received {
+        validate_input_amounts(&info.funds, &[(input_amount, &input_token.denom)])?;
+    }
+
+    let fees = FEES.load(deps.storage)?;
+    let total_fee_percent = fees.lp_fee_percent + fees.protocol_fee_percent;
+    let amp = load_amp(deps.storage, &env.block)?;
+    let concentrated_swap =
+        get_concentrated_swap(deps.storage, &input_token_enum, input_amount, &fees)?;
+    let swap = match &concentrated_swap {
+        Some(swap) => {
+            check_swap_min(min_token, swap.output_amount)?;
+            Swap {
+                input_token: input_token_enum.clone(),
+                input_amount,
+                output_amount: swap.output_amount,
+                protocol_fee_amount: get_fee_amount(input_amount, fees.protocol_fee_percent)?,
+            }
+        }
+        None => get_swap(
+            deps.storage,
+            get_curve(amp).as_ref(),
+            input_token_enum.clone(),
+            input_amount,
+            min_token,
+        )?,
     };
-    let cw20_transfer_cosmos_msg: CosmosMsg = exec_cw20_transfer.into();
-    Ok(cw20_transfer_cosmos_msg)
-}
-
+    let token_bought = swap.output_amount;
+    let protocol_fee_amount = swap.protocol_fee_amount;
+    let input_amount_minus_protocol_fee = input_amount - protocol_fee_amount;
 
-fn get_fee_transfer_msg(
-    sender: &Addr,
-    recipient: &Addr,
-    fee_denom: &Denom,
-    amount: Uint128,
-) -> StdResult<CosmosMsg> {
-    match fee_denom {
-        Denom::Cw20(addr) => get_cw20_transfer_from_msg(sender, recipient, addr, amount),
-        Denom::Native(_denom) => {unimplemented!()},
+    // Circuit breaker, checked before anything is written so a tripped breaker can freeze the
+    // pool without leaving a partial swap behind
+    let limits = LIMITS.may_load(deps.storage)?.unwrap_or_default();
+    if let Some(max_price_impact) = limits.max_price_impact {
+        let price_impact = match &concentrated_swap {
+            Some(swap) => get_concentrated_price_impact(
+                deps.storage,
+                &input_token_enum,
+                input_amount_minus_protocol_fee,
+                swap,
+            )?,
+            None => get_price_impact(
+                amp,
+                input_amount,
+                input_token.reserve,
+                output_token.reserve,
+                total_fee_percent,
+            )?,
+        };
+        if price_impact > max_price_impact {
+            return Err(ContractError::MaxPriceImpactError {
+                max: max_price_impact,
+                price_impact,
+            });
+        }
     }
-}
+    let block_reserve_change = get_block_reserve_change(
+        deps.storage,
+        &env.block,
+        &input_token_enum,
+        input_amount_minus_protocol_fee,
+        token_bought,
+    )?;
+    if let Some(max_reserve_change) = limits.max_block_reserve_change {
+        let reserve_change = std::cmp::max(
+            get_reserve_change_ratio(
+                block_reserve_change.token1_change,
+                block_reserve_change.token1_reserve,
+            ),
+            get_reserve_change_ratio(
+                block_reserve_change.token2_change,
+                block_reserve_change.token2_reserve,
+            ),
+        );
+        if reserve_change > max_reserve_change {
+            return Err(ContractError::MaxBlockReserveChangeError {
+                max: max_reserve_change,
+                reserve_change,
+            });
+        }
+    }
+    BLOCK_RESERVE_CHANGE.save(deps.storage, &block_reserve_change)?;
+    update_price_accumulator(deps.storage, &env.block)?;
 
-fn fee_decimal_to_uint128(decimal: Decimal) -> StdResult<Uint128> {
-    let result: Uint128 = decimal
-        .atomics()
-        .checked_mul(FEE_SCALE_FACTOR)
-        .map_err(StdError::overflow)?;
+    let msgs = commit_swap(deps.storage, info, env, &swap, input_received)?;
 
-    Ok(result / FEE_DECIMAL_PRECISION)
+    let lp_fee_amount = match &concentrated_swap {
+        Some(swap) => {
+            concentrated::save_swap(deps.storage, swap)?;
+            swap.fee_amount
+        }
+        None => get_fee_amount(input_amount, total_fee_percent)? - protocol_fee_amount,
+    };
+    let swap_event = Event::new("swap")
+        .add_attribute("sender", &info.sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("input_denom", denom_to_string(&input_token.denom))
+        .add_attribute("input_amount", input_amount)
+        .add_attribute("output_denom", denom_to_string(&output_token.denom))
+        .add_attribute("output_amount", token_bought)
+        .add_attribute("lp_fee_amount", lp_fee_amount)
+        .add_attribute("protocol_fee_amount", protocol_fee_amount)
+        .add_attributes(reserve_attributes(deps.storage)?);
+
+    Ok((output_token.denom, token_bought, msgs, swap_event))
 }
 
-fn get_input_price(
+// Swap of input_amount on the curve of a concentrated liquidity pool, None for other pools. The
+// protocol fee is taken from the input first, the lp fee in every tick range crossed.
+fn get_concentrated_swap(
+    storage: &dyn Storage,
+    input_token: &TokenSelect,
     input_amount: Uint128,
-    input_reserve: Uint128,
-    output_reserve: Uint128,
-    fee_percent: Decimal,
-) -> StdResult<Uint128> {
-    if input_reserve == Uint128::zero() || output_reserve == Uint128::zero() {
-        return Err(StdError::generic_err("No liquidity"));
+    fees: &Fees,
+) -> StdResult<Option<concentrated::SwapResult>> {
+    let pool = match CONCENTRATED_POOL.may_load(storage)? {
+        Some(pool) => pool,
+        None => return Ok(None),
     };
+    let protocol_fee_amount = get_fee_amount(input_amount, fees.protocol_fee_percent)?;
+    concentrated::compute_swap(
+        storage,
+        &pool,
+        input_token == &TokenSelect::Token1,
+        input_amount - protocol_fee_amount,
+        fee_decimal_to_uint128(fees.lp_fee_percent)?,
+    )
+    .map(Some)
+}
 
-    let fee_percent = fee_decimal_to_uint128(fee_percent)?;
-    let fee_reduction_percent = FEE_SCALE_FACTOR - fee_percent;
-    let input_amount_with_fee = Uint512::from(input_amount.full_mul(fee_reduction_percent));
-    let numerator = input_amount_with_fee
-        .checked_mul(Uint512::from(output_reserve))
-        .map_err(StdError::overflow)?;
-    let denominator = Uint512::from(input_reserve)
-        .checked_mul(Uint512::from(FEE_SCALE_FACTOR))
-        .map_err(StdError::overflow)?
-        .checked_add(input_amount_with_fee)
-        .map_err(StdError::overflow)?;
+fn get_concentrated_spot_price(
+    pool: &ConcentratedPool,
+    input_token: &TokenSelect,
+) -> StdResult<Decimal> {
+    let price = concentrated::get_spot_price(pool.sqrt_price, input_token == &TokenSelect::Token1)?;
+    let atomics: Uint128 = price.atomics().try_into()?;
+    Ok(Decimal::from_ratio(atomics, 10u128.pow(18)))
+}
 
-    Ok(numerator
-        .checked_div(denominator)
-        .map_err(StdError::divide_by_zero)?
-        .try_into()?)
+fn get_concentrated_price_impact(
+    storage: &dyn Storage,
+    input_token: &TokenSelect,
+    input_amount: Uint128,
+    swap: &concentrated::SwapResult,
+) -> StdResult<Decimal> {
+    let input_amount_after_fee = input_amount - swap.fee_amount;
+    if input_amount_after_fee.is_zero() {
+        return Ok(Decimal::zero());
+    }
+    let spot_price = get_concentrated_spot_price(&CONCENTRATED_POOL.load(storage)?, input_token)?;
+    Ok(get_price_shortfall(
+        spot_price,
+        swap.output_amount,
+        input_amount_after_fee,
+    ))
 }
 
-fn get_protocol_fee_amount(input_amount: Uint128, fee_percent: Decimal) -> StdResult<Uint128> {
-    if fee_percent.is_zero() {
-        return Ok(Uint128::zero());
+fn get_pool_balance(deps: Deps, env: &Env, denom: &Denom) -> StdResult<Uint128> {
+    match denom {
+        Denom::Cw20(addr) => get_token_balance(deps, addr, &env.contract.address),
+        Denom::Native(denom) => Ok(deps
+            .querier
+            .query_balance(&env.contract.address, denom)?
+            .amount),
     }
+}
 
+// The fee of a flash swap is the swap fee of the lent amount, rounded up
+fn get_flash_swap_fee(amount: Uint128, fee_percent: Decimal) -> StdResult<Uint128> {
     let fee_percent = fee_decimal_to_uint128(fee_percent)?;
-    Ok(input_amount
+    Ok(amount
         .full_mul(fee_percent)
+        .checked_add(Uint256::from(FEE_SCALE_FACTOR - Uint128::new(1)))
+        .map_err(StdError::overflow)?
         .checked_div(Uint256::from(FEE_SCALE_FACTOR))
         .map_err(StdError::divide_by_zero)?
         .try_into()?)
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn execute_swap(
+// Sends amount of token to the sender, executes the callback on it and then checks the
+// repayment with a message to the pool itself. All three run in this transaction, so a failed
+// callback or repayment reverts the tokens sent.
+pub fn execute_flash_swap(
     deps: DepsMut,
-    info: &MessageInfo,
-    input_amount: Uint128,
-    _env: Env,
-    input_token_enum: TokenSelect,
-    recipient: String,
-    min_token: Uint128,
-    expiration: Option<Expiration>,
+    info: MessageInfo,
+    env: Env,
+    token_enum: TokenSelect,
+    amount: Uint128,
+    callback_msg: Binary,
 ) -> Result<Response, ContractError> {
-    check_expiration(&expiration, &_env.block)?;
-
-    let input_token_item = match input_token_enum {
-        TokenSelect::Token1 => TOKEN1,
-        TokenSelect::Token2 => TOKEN2,
+    let token = match token_enum {
+        TokenSelect::Token1 => TOKEN1.load(deps.storage)?,
+        TokenSelect::Token2 => TOKEN2.load(deps.storage)?,
     };
-    let input_token = input_token_item.load(deps.storage)?;
-    let output_token_item = match input_token_enum {
-        TokenSelect::Token1 => TOKEN2,
-        TokenSelect::Token2 => TOKEN1,
-    };
-    let output_token = output_token_item.load(deps.storage)?;
+    if amount.is_zero() || amount >= token.reserve {
+        return Err(PoolError::InsufficientLiquidityError {
+            requested: amount,
+            available: token.reserve,
+        }
+        .into());
+    }
 
+    update_price_accumulator(deps.storage, &env.block)?;
 
-    let fees = FEES.load(deps.storage)?;
-    let total_fee_percent = fees.lp_fee_percent + fees.protocol_fee_percent;
-    let token_bought = get_input_price(
-        input_amount,
-        input_token.reserve,
-        output_token.reserve,
-        total_fee_percent,
+    let balance_before = get_pool_balance(deps.as_ref(), &env, &token.denom)?;
+    FLASH_SWAP.save(
+        deps.storage,
+        &FlashSwap {
+            borrower: info.sender.clone(),
+            token: token_enum,
+            amount,
+            balance_before,
+        },
     )?;
 
-    if min_token > token_bought {
-        return Err(ContractError::SwapMinError {
-            min: min_token,
-            available: token_bought,
-        });
-    }
-    // Calculate fees
-    let protocol_fee_amount = get_protocol_fee_amount(input_amount, fees.protocol_fee_percent)?;
-    let input_amount_minus_protocol_fee = input_amount - protocol_fee_amount;
-
-    let mut msgs = match input_token.denom.clone() {
-        Denom::Cw20(addr) => vec![get_cw20_transfer_from_msg(
-            &info.sender,
-            &_env.contract.address,
-            &addr,
-            input_amount_minus_protocol_fee,
-        )?],
-        Denom::Native(_) => vec![],
+    let transfer_msg = get_transfer_to_msg(&info.sender, &token.denom, amount)?;
+    let callback_msg = WasmMsg::Execute {
+        contract_addr: info.sender.to_string(),
+        msg: callback_msg,
+        funds: vec![],
+    };
+    let complete_msg = WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        msg: to_binary(&ExecuteMsg::CompleteFlashSwap {})?,
+        funds: vec![],
     };
 
-    // Send protocol fee to protocol fee recipient
-    if !protocol_fee_amount.is_zero() {
-        msgs.push(get_fee_transfer_msg(
-            &info.sender,
-            &fees.protocol_fee_recipient,
-            &input_token.denom,
-            protocol_fee_amount,
-        )?)
+    Ok(Response::new()
+        .add_message(transfer_msg)
+        .add_message(callback_msg)
+        .add_message(complete_msg)
+        .add_attributes(vec![
+            attr("action", "flash_swap"),
+            attr("denom", denom_to_string(&token.denom)),
+            attr("amount", amount),
+        ]))
+}
+
+pub fn execute_complete_flash_swap(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
     }
+    let flash_swap = FLASH_SWAP
+        .may_load(deps.storage)?
+        .ok_or(ContractError::Unauthorized {})?;
+    FLASH_SWAP.remove(deps.storage);
 
-    let recipient = deps.api.addr_validate(&recipient)?;
-    // Create transfer to message
-    msgs.push(match output_token.denom {
-        Denom::Cw20(addr) => get_cw20_transfer_to_msg(&recipient, &addr, token_bought)?,
-        Denom::Native(_denom) => {unimplemented!()},
-    });
+    let token_item = match flash_swap.token {
+        TokenSelect::Token1 => TOKEN1,
+        TokenSelect::Token2 => TOKEN2,
+    };
+    let token = token_item.load(deps.storage)?;
+    let fees = FEES.load(deps.storage)?;
+    let total_fee_percent = fees.lp_fee_percent + fees.protocol_fee_percent;
+    let fee_amount = get_flash_swap_fee(flash_swap.amount, total_fee_percent)?;
+
+    // Anything repaid above the lent amount is a fee, a larger repayment is kept by the pool
+    let balance = get_pool_balance(deps.as_ref(), &env, &token.denom)?;
+    let repaid = (balance + flash_swap.amount).saturating_sub(flash_swap.balance_before);
+    let required = flash_swap.amount + fee_amount;
+    if repaid < required {
+        return Err(ContractError::FlashSwapNotRepaid { required, repaid });
+    }
+    let repaid_fee = repaid - flash_swap.amount;
+    let protocol_fee_amount = std::cmp::min(
+        get_flash_swap_fee(flash_swap.amount, fees.protocol_fee_percent)?,
+        repaid_fee,
+    );
 
-    input_token_item.update(
+    PROTOCOL_FEES.update(
         deps.storage,
-        |mut input_token| -> Result<_, ContractError> {
-            input_token.reserve = input_token
-                .reserve
-                .checked_add(input_amount_minus_protocol_fee)
+        |mut protocol_fees| -> Result<_, ContractError> {
+            let accrued = match flash_swap.token {
+                TokenSelect::Token1 => &mut protocol_fees.token1_amount,
+                TokenSelect::Token2 => &mut protocol_fees.token2_amount,
+            };
+            *accrued = accrued
+                .checked_add(protocol_fee_amount)
                 .map_err(StdError::overflow)?;
-            Ok(input_token)
+            Ok(protocol_fees)
         },
     )?;
+    token_item.update(deps.storage, |mut token| -> Result<_, ContractError> {
+        token.reserve = token
+            .reserve
+            .checked_add(repaid_fee - protocol_fee_amount)
+            .map_err(StdError::overflow)?;
+        Ok(token)
+    })?;
 
-    output_token_item.update(
-        deps.storage,
-        |mut output_token| -> Result<_, ContractError> {
-            output_token.reserve = output_token
-                .reserve
-                .checked_sub(token_bought)
-                .map_err(StdError::overflow)?;
-            Ok(output_token)
+    let flash_swap_event = Event::new("flash_swap")
+        .add_attribute("borrower", &flash_swap.borrower)
+        .add_attribute("denom", denom_to_string(&token.denom))
+        .add_attribute("amount", flash_swap.amount)
+        .add_attribute("lp_fee_amount", repaid_fee - protocol_fee_amount)
+        .add_attribute("protocol_fee_amount", protocol_fee_amount)
+        .add_attributes(reserve_attributes(deps.storage)?);
+
+    Ok(Response::new()
+        .add_event(flash_swap_event)
+        .add_attributes(vec![
+            attr("action", "complete_flash_swap"),
+            attr("repaid", repaid),
+        ]))
+}
+
+// Adds a swap to the reserve changes of the current block, starting over in a new block
+fn get_block_reserve_change(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    input_token_enum: &TokenSelect,
+    input_amount: Uint128,
+    output_amount: Uint128,
+) -> StdResult<BlockReserveChange> {
+    let mut block_reserve_change = match BLOCK_RESERVE_CHANGE.may_load(storage)? {
+        Some(change) if change.height == block.height => change,
+        _ => BlockReserveChange {
+            height: block.height,
+            token1_reserve: TOKEN1.load(storage)?.reserve,
+            token2_reserve: TOKEN2.load(storage)?.reserve,
+            token1_change: Uint128::zero(),
+            token2_change: Uint128::zero(),
         },
+    };
+    let (token1_change, token2_change) = match input_token_enum {
+        TokenSelect::Token1 => (input_amount, output_amount),
+        TokenSelect::Token2 => (output_amount, input_amount),
+    };
+    block_reserve_change.token1_change = block_reserve_change
+        .token1_change
+        .checked_add(token1_change)
+        .map_err(StdError::overflow)?;
+    block_reserve_change.token2_change = block_reserve_change
+        .token2_change
+        .checked_add(token2_change)
+        .map_err(StdError::overflow)?;
+    Ok(block_reserve_change)
+}
+
+fn get_reserve_change_ratio(change: Uint128, reserve: Uint128) -> Decimal {
+    if reserve.is_zero() {
+        return Decimal::zero();
+    }
+    Decimal::from_ratio(change, reserve)
+}
+
+fn denom_to_string(denom: &Denom) -> String {
+    match denom {
+        Denom::Native(denom) => denom.clone(),
+        Denom::Cw20(addr) => addr.to_string(),
+    }
+}
+
+// Reserves after the action, pool events carry them so indexers can follow the pool state
+fn reserve_attributes(storage: &dyn Storage) -> StdResult<Vec<Attribute>> {
+    Ok(vec![
+        attr("token1_reserve", TOKEN1.load(storage)?.reserve),
+        attr("token2_reserve", TOKEN2.load(storage)?.reserve),
+    ])
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_pass_through_swap(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    output_amm_address: String,
+    input_token_enum: TokenSelect,
+    input_token_amount: Uint128,
+    output_min_token: Uint128,
+    expiration: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    check_expiration(&expiration, &env.block)?;
+
+    let output_amm_address = deps.api.addr_validate(&output_amm_address)?;
+    if output_amm_address == env.contract.address {
+        return Err(ContractError::InvalidOutputPool {});
+    }
+    let output_amm_info: PoolDenomsResponse = deps
+        .querier
+        .query_wasm_smart(&output_amm_address, &QueryMsg::Info {})?;
+
+    // The minimum is only enforced on the final output
+    let (output_denom, token_bought, mut msgs, swap_event) = swap_input(
+        deps,
+        &info,
+        &env,
+        input_token_enum,
+        input_token_amount,
+        Uint128::zero(),
+        false,
+        &output_amm_address,
     )?;
 
-    Ok(Response::new().add_messages(msgs).add_attributes(vec![
-        attr("native_sold", input_amount),
-        attr("token_bought", token_bought),
-    ]))
+    let transfer_input_token_enum = if output_denom == output_amm_info.token1_denom {
+        TokenSelect::Token1
+    } else if output_denom == output_amm_info.token2_denom {
+        TokenSelect::Token2
+    } else {
+        return Err(ContractError::InvalidOutputPool {});
+    };
+
+    let swap_msg = to_binary(&ExecuteMsg::SwapAndSendTo {
+        input_token: transfer_input_token_enum,
+        input_amount: token_bought,
+        recipient: info.sender.to_string(),
+        min_token: output_min_token,
+        expiration,
+    })?;
+    match output_denom {
+        Denom::Cw20(addr) => {
+            msgs.push(
+                WasmMsg::Execute {
+                    contract_addr: addr.into(),
+                    msg: to_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                        spender: output_amm_address.to_string(),
+                        amount: token_bought,
+                        expires: None,
+                    })?,
+                    funds: vec![],
+                }
+                .into(),
+            );
+            msgs.push(
+                WasmMsg::Execute {
+                    contract_addr: output_amm_address.into(),
+                    msg: swap_msg,
+                    funds: vec![],
+                }
+                .into(),
+            );
+        }
+        Denom::Native(denom) => msgs.push(
+            WasmMsg::Execute {
+                contract_addr: output_amm_address.into(),
+                msg: swap_msg,
+                funds: coins(token_bought.u128(), denom),
+            }
+            .into(),
+        ),
+    };
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_event(swap_event)
+        .add_attributes(vec![
+            attr("input_token_amount", input_token_amount),
+            attr("native_transferred", token_bought),
+        ]))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Balance { address } => to_binary(&query_balance(deps, address)?),
         QueryMsg::Info {} => to_binary(&query_info(deps)?),
         QueryMsg::Token1ForToken2Price { token1_amount } => {
-            to_binary(&query_token1_for_token2_price(deps, token1_amount)?)
+            to_binary(&query_token1_for_token2_price(deps, env, token1_amount)?)
         }
         QueryMsg::Token2ForToken1Price { token2_amount } => {
-            to_binary(&query_token2_for_token1_price(deps, token2_amount)?)
+            to_binary(&query_token2_for_token1_price(deps, env, token2_amount)?)
         }
         QueryMsg::Fee {} => to_binary(&query_fee(deps)?),
+        QueryMsg::SimulateSwap {
+            input_token,
+            input_amount,
+        } => to_binary(&query_simulate_swap(deps, env, input_token, input_amount)?),
+        QueryMsg::ReverseSimulate {
+            output_token,
+            output_amount,
+        } => to_binary(&query_reverse_simulate(
+            deps,
+            env,
+            output_token,
+            output_amount,
+        )?),
+        QueryMsg::Twap {
+            token,
+            window_seconds,
+        } => to_binary(&query_twap(deps, env, token, window_seconds)?),
+        QueryMsg::CumulativePrices {} => to_binary(&query_cumulative_prices(deps, env)?),
+        QueryMsg::Ownership {} => to_binary(&query_ownership(deps)?),
+        QueryMsg::ProtocolFees {} => to_binary(&query_protocol_fees(deps)?),
+        QueryMsg::Limits {} => to_binary(&query_limits(deps)?),
+        QueryMsg::Pause {} => to_binary(&query_pause(deps)?),
+        QueryMsg::Amp {} => to_binary(&query_amp(deps, env)?),
+        QueryMsg::Position { id } => to_binary(&query_position(deps, id)?),
+        QueryMsg::Ticks { start_after, limit } => {
+            to_binary(&query_ticks(deps, start_after, limit)?)
+        }
     }
 }
 
-pub fn query_info(deps: Deps) -> StdResult<InfoResponse> {
-    let token1 = TOKEN1.load(deps.storage)?;
-    let token2 = TOKEN2.load(deps.storage)?;
-  
-    // TODO get total supply
-    Ok(InfoResponse {
-        token1_reserve: token1.reserve,
-        token1_denom: token1.denom,
-        token2_reserve: token2.reserve,
-        token2_denom: token2.denom,
-       
-    })
-}
-
 pub fn query_token1_for_token2_price(
     deps: Deps,
+    env: Env,
     token1_amount: Uint128,
 ) -> StdResult<Token1ForToken2PriceResponse> {
-    let token1 = TOKEN1.load(deps.storage)?;
-    let token2 = TOKEN2.load(deps.storage)?;
-
     let fees = FEES.load(deps.storage)?;
-    let total_fee_percent = fees.lp_fee_percent + fees.protocol_fee_percent;
-    let token2_amount = get_input_price(
-        token1_amount,
-        token1.reserve,
-        token2.reserve,
-        total_fee_percent,
-    )?;
+    let token2_amount =
+        match get_concentrated_swap(deps.storage, &TokenSelect::Token1, token1_amount, &fees)? {
+            Some(swap) => swap.output_amount,
+            None => get_swap_output(
+                deps.storage,
+                get_curve(load_amp(deps.storage, &env.block)?).as_ref(),
+                &TokenSelect::Token1,
+                token1_amount,
+            )?,
+        };
     Ok(Token1ForToken2PriceResponse { token2_amount })
 }
 
 pub fn query_token2_for_token1_price(
     deps: Deps,
+    env: Env,
     token2_amount: Uint128,
 ) -> StdResult<Token2ForToken1PriceResponse> {
-    let token1 = TOKEN1.load(deps.storage)?;
-    let token2 = TOKEN2.load(deps.storage)?;
+    let fees = FEES.load(deps.storage)?;
+    let token1_amount =
+        match get_concentrated_swap(deps.storage, &TokenSelect::Token2, token2_amount, &fees)? {
+            Some(swap) => swap.output_amount,
+            None => get_swap_output(
+                deps.storage,
+                get_curve(load_amp(deps.storage, &env.block)?).as_ref(),
+                &TokenSelect::Token2,
+                token2_amount,
+            )?,
+        };
+    Ok(Token2ForToken1PriceResponse { token1_amount })
+}
+
+// Relative price move caused by the input that reaches the reserves after fees
+fn get_price_impact(
+    amp: Option<u64>,
+    input_amount: Uint128,
+    input_reserve: Uint128,
+    output_reserve: Uint128,
+    fee_percent: Decimal,
+) -> StdResult<Decimal> {
+    let input_amount_after_fee = input_amount - get_fee_amount(input_amount, fee_percent)?;
+    if input_amount_after_fee.is_zero() {
+        return Ok(Decimal::zero());
+    }
+    let amp = match amp {
+        Some(amp) => amp,
+        None => {
+            return Ok(Decimal::from_ratio(
+                input_amount_after_fee,
+                input_reserve
+                    .checked_add(input_amount_after_fee)
+                    .map_err(StdError::overflow)?,
+            ))
+        }
+    };
+
+    let spot_price = stableswap::get_spot_price(amp, input_reserve, output_reserve)?;
+    let output_amount =
+        stableswap::get_output_amount(amp, input_amount_after_fee, input_reserve, output_reserve)?;
+    Ok(get_price_shortfall(
+        spot_price,
+        output_amount,
+        input_amount_after_fee,
+    ))
+}
+
+// Shortfall of the execution price from the spot price
+fn get_price_shortfall(
+    spot_price: Decimal,
+    output_amount: Uint128,
+    input_amount: Uint128,
+) -> Decimal {
+    let execution_price = Decimal::from_ratio(output_amount, input_amount);
+    if execution_price >= spot_price {
+        return Decimal::zero();
+    }
+    Decimal::one() - Decimal::from_ratio(execution_price.atomics(), spot_price.atomics())
+}
 
+pub fn query_simulate_swap(
+    deps: Deps,
+    env: Env,
+    input_token_enum: TokenSelect,
+    input_amount: Uint128,
+) -> StdResult<SimulateSwapResponse> {
     let fees = FEES.load(deps.storage)?;
+    if let Some(swap) = get_concentrated_swap(deps.storage, &input_token_enum, input_amount, &fees)?
+    {
+        let protocol_fee_amount = get_fee_amount(input_amount, fees.protocol_fee_percent)?;
+        let pool = CONCENTRATED_POOL.load(deps.storage)?;
+        return Ok(SimulateSwapResponse {
+            output_amount: swap.output_amount,
+            lp_fee_amount: swap.fee_amount,
+            protocol_fee_amount,
+            spot_price: get_concentrated_spot_price(&pool, &input_token_enum)?,
+            price_impact: get_concentrated_price_impact(
+                deps.storage,
+                &input_token_enum,
+                input_amount - protocol_fee_amount,
+                &swap,
+            )?,
+        });
+    }
+    let (input_token, output_token) = get_swap_tokens(deps.storage, &input_token_enum)?;
+
     let total_fee_percent = fees.lp_fee_percent + fees.protocol_fee_percent;
-    let token1_amount = get_input_price(
-        token2_amount,
-        token2.reserve,
-        token1.reserve,
+    let amp = load_amp(deps.storage, &env.block)?;
+    let output_amount = get_curve(amp).swap_output(
+        &input_token_enum,
+        input_amount,
+        input_token.reserve,
+        output_token.reserve,
         total_fee_percent,
     )?;
-    Ok(Token2ForToken1PriceResponse { token1_amount })
+    let protocol_fee_amount = get_fee_amount(input_amount, fees.protocol_fee_percent)?;
+    let lp_fee_amount = get_fee_amount(input_amount, total_fee_percent)? - protocol_fee_amount;
+
+    Ok(SimulateSwapResponse {
+        output_amount,
+        lp_fee_amount,
+        protocol_fee_amount,
+        spot_price: get_spot_price(amp, input_token.reserve, output_token.reserve)?,
+        price_impact: get_price_impact(
+            amp,
+            input_amount,
+            input_token.reserve,
+            output_token.reserve,
+            total_fee_percent,
+        )?,
+    })
 }
 
-pub fn query_fee(deps: Deps) -> StdResult<FeeResponse> {
+pub fn query_reverse_simulate(
+    deps: Deps,
+    env: Env,
+    output_token: TokenSelect,
+    output_amount: Uint128,
+) -> StdResult<ReverseSimulateResponse> {
+    if CONCENTRATED_POOL.may_load(deps.storage)?.is_some() {
+        return Err(StdError::generic_err(
+            "Not supported by concentrated liquidity pools",
+        ));
+    }
+    let input_token_enum = match output_token {
+        TokenSelect::Token1 => TokenSelect::Token2,
+        TokenSelect::Token2 => TokenSelect::Token1,
+    };
+    let (input_token, output_token) = get_swap_tokens(deps.storage, &input_token_enum)?;
+
     let fees = FEES.load(deps.storage)?;
-    let owner = OWNER.load(deps.storage)?.map(|o| o.into_string());
+    let total_fee_percent = fees.lp_fee_percent + fees.protocol_fee_percent;
+    let amp = load_amp(deps.storage, &env.block)?;
+    let input_amount = get_curve(amp).swap_input(
+        &input_token_enum,
+        output_amount,
+        input_token.reserve,
+        output_token.reserve,
+        total_fee_percent,
+    )?;
+    let protocol_fee_amount = get_fee_amount(input_amount, fees.protocol_fee_percent)?;
+    let lp_fee_amount = get_fee_amount(input_amount, total_fee_percent)? - protocol_fee_amount;
+
+    Ok(ReverseSimulateResponse {
+        input_amount,
+        lp_fee_amount,
+        protocol_fee_amount,
+        spot_price: get_spot_price(amp, input_token.reserve, output_token.reserve)?,
+        price_impact: get_price_impact(
+            amp,
+            input_amount,
+            input_token.reserve,
+            output_token.reserve,
+            total_fee_percent,
+        )?,
+    })
+}
+
+pub fn query_limits(deps: Deps) -> StdResult<LimitsResponse> {
+    let limits = LIMITS.may_load(deps.storage)?.unwrap_or_default();
+
+    Ok(LimitsResponse {
+        max_price_impact: limits.max_price_impact,
+        max_block_reserve_change: limits.max_block_reserve_change,
+        auto_freeze: limits.auto_freeze,
+    })
+}
 
-    Ok(FeeResponse {
-        owner,
-        lp_fee_percent: fees.lp_fee_percent,
-        protocol_fee_percent: fees.protocol_fee_percent,
-        protocol_fee_recipient: fees.protocol_fee_recipient.into_string(),
+pub fn query_pause(deps: Deps) -> StdResult<PauseResponse> {
+    let flags = PAUSE_FLAGS.load(deps.storage)?;
+    let guardian = GUARDIAN.load(deps.storage)?;
+
+    Ok(PauseResponse {
+        deposits: flags.deposits,
+        swaps: flags.swaps,
+        withdrawals: flags.withdrawals,
+        guardian: guardian.map(|g| g.into_string()),
+    })
+}
+
+pub fn query_amp(deps: Deps, env: Env) -> StdResult<AmpResponse> {
+    let amp_config = AMP
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("Not a stable swap pool"))?;
+
+    Ok(AmpResponse {
+        amp: get_amp(&amp_config, &env.block),
+        initial_amp: amp_config.initial_amp,
+        target_amp: amp_config.target_amp,
+        start_time: amp_config.start_time,
+        end_time: amp_config.end_time,
     })
 }
 
+fn load_concentrated_pool(storage: &dyn Storage) -> StdResult<ConcentratedPool> {
+    CONCENTRATED_POOL
+        .may_load(storage)?
+        .ok_or_else(|| StdError::generic_err("Not a concentrated liquidity pool"))
+}
+
+pub fn query_position(deps: Deps, id: u64) -> StdResult<PositionResponse> {
+    let pool = load_concentrated_pool(deps.storage)?;
+    let position = POSITIONS.load(deps.storage, id)?;
+    let (token1_amount, token2_amount, tokens_owed1, tokens_owed2) =
+        concentrated::get_position_amounts(deps.storage, &pool, &position)?;
+
+    Ok(PositionResponse {
+        id,
+        owner: position.owner.into_string(),
+        lower_tick: position.lower_tick,
+        upper_tick: position.upper_tick,
+        liquidity: position.liquidity,
+        token1_amount,
+        token2_amount,
+        tokens_owed1,
+        tokens_owed2,
+    })
+}
 
+pub fn query_ticks(
+    deps: Deps,
+    start_after: Option<i32>,
+    limit: Option<u32>,
+) -> StdResult<TicksResponse> {
+    let pool = load_concentrated_pool(deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|tick| Bound::exclusive_int(concentrated::tick_key(tick)));
+    let ticks = TICKS
+        .range_de(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(key, info)| TickResponse {
+                tick: concentrated::key_tick(key),
+                liquidity_lower: info.liquidity_lower,
+                liquidity_upper: info.liquidity_upper,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TicksResponse {
+        ticks,
+        current_tick: pool.tick,
+        sqrt_price: pool.sqrt_price,
+        liquidity: pool.liquidity,
+    })
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use std::str::FromStr;
 
     #[test]
-    fn test_get_input_price() {
-        let fee_percent = Decimal::from_str("0.03").unwrap();
-        // Base case
+    fn test_migrate_version_checks() {
+        let mut deps = mock_dependencies();
+        let msg = MigrateMsg {
+            owner: None,
+            protocol_fee_recipient: "fee_recipient".to_string(),
+            protocol_fee_percent: Decimal::zero(),
+            lp_fee_percent: Decimal::zero(),
+            freeze_pool: false,
+            lp_token_code_id: None,
+        };
+
+        // Different contract
+        set_contract_version(deps.as_mut().storage, "crates.io:sum-amm", CONTRACT_VERSION).unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), msg.clone()).unwrap_err();
         assert_eq!(
-            get_input_price(
-                Uint128::new(10),
-                Uint128::new(100),
-                Uint128::new(100),
-                fee_percent
-            )
-            .unwrap(),
-            Uint128::new(9)
+            err,
+            ContractError::CannotMigrate {
+                previous_contract: "crates.io:sum-amm".to_string()
+            }
         );
 
-        // No input reserve error
-        let err = get_input_price(
-            Uint128::new(10),
-            Uint128::new(0),
-            Uint128::new(100),
-            fee_percent,
-        )
-        .unwrap_err();
-        assert_eq!(err, StdError::generic_err("No liquidity"));
-
-        // No output reserve error
-        let err = get_input_price(
-            Uint128::new(10),
-            Uint128::new(100),
-            Uint128::new(0),
-            fee_percent,
-        )
-        .unwrap_err();
-        assert_eq!(err, StdError::generic_err("No liquidity"));
-
-        // No reserve error
-        let err = get_input_price(
-            Uint128::new(10),
-            Uint128::new(0),
-            Uint128::new(0),
-            fee_percent,
-        )
-        .unwrap_err();
-        assert_eq!(err, StdError::generic_err("No liquidity"));
+        // Downgrade
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "99.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), msg.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CannotMigrateVersion {
+                previous_version: "99.0.0".to_string(),
+                new_version: CONTRACT_VERSION.to_string()
+            }
+        );
+
+        // Same version
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, CONTRACT_VERSION).unwrap();
+        LP_TOKEN
+            .save(deps.as_mut().storage, &Addr::unchecked("lp_token"))
+            .unwrap();
+        migrate(deps.as_mut(), mock_env(), msg).unwrap();
+        assert_eq!(
+            PAUSE_FLAGS.load(deps.as_ref().storage).unwrap(),
+            PauseFlags::default()
+        );
+    }
+
+    #[test]
+    fn test_get_single_sided_swap_amount() {
+        // No fee, s = (sqrt(4r^2 + 4ar) - 2r) / 2
+        let swap_amount =
+            get_single_sided_swap_amount(Uint128::new(2000), Uint128::new(10000), Decimal::zero())
+                .unwrap();
+        assert_eq!(swap_amount, Uint128::new(954));
+
+        // The swap fee raises the amount to swap
+        let fee_percent = Decimal::from_str("0.3").unwrap();
+        let swap_amount =
+            get_single_sided_swap_amount(Uint128::new(2000), Uint128::new(10000), fee_percent)
+                .unwrap();
+        assert_eq!(swap_amount, Uint128::new(955));
+
+        // The rest of the deposit matches the pool ratio after the swap
+        let amount = Uint128::new(1_000_000_000);
+        let reserve = Uint128::new(7_000_000_000);
+        let swap_amount = get_single_sided_swap_amount(amount, reserve, fee_percent).unwrap();
+        let bought = ConstantProduct
+            .swap_output(
+                &TokenSelect::Token1,
+                swap_amount,
+                reserve,
+                reserve,
+                fee_percent,
+            )
+            .unwrap();
+        let deposit_ratio = Decimal::from_ratio(amount - swap_amount, reserve + swap_amount);
+        let bought_ratio = Decimal::from_ratio(bought, reserve - bought);
+        assert!(deposit_ratio - bought_ratio < Decimal::from_str("0.000001").unwrap());
     }
 }
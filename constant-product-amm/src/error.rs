@@ -1,4 +1,6 @@
 use cosmwasm_std::{Decimal, StdError, Uint128};
+use cw0::PaymentError;
+use pool_core::PoolError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,9 +8,15 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Pool(PoolError),
+
     #[error("{0}")]
     Cw20Error(#[from] cw20_base::ContractError),
 
+    #[error("{0}")]
+    SemVer(String),
+
     #[error("None Error")]
     NoneError {},
 
@@ -16,66 +24,92 @@ pub enum ContractError {
     Unauthorized {},
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
-    #[error("Min liquidity error: requested: {min_liquidity}, available: {liquidity_available}")]
-    MinLiquidityError {
-        min_liquidity: Uint128,
-        liquidity_available: Uint128,
-    },
+    #[error("Swap max input error: max: {max}, required: {required}")]
+    SwapMaxInputError { max: Uint128, required: Uint128 },
 
-    #[error("Max token error: max_token: {max_token}, tokens_required: {tokens_required}")]
-    MaxTokenError {
-        max_token: Uint128,
-        tokens_required: Uint128,
-    },
+    #[error("Max price impact error: max: {max}, price impact: {price_impact}")]
+    MaxPriceImpactError { max: Decimal, price_impact: Decimal },
 
-    #[error("Insufficient liquidity error: requested: {requested}, available: {available}")]
-    InsufficientLiquidityError {
-        requested: Uint128,
-        available: Uint128,
+    #[error("Max block reserve change error: max: {max}, reserve change: {reserve_change}")]
+    MaxBlockReserveChangeError {
+        max: Decimal,
+        reserve_change: Decimal,
     },
 
-    #[error("Min token1 error: requested: {requested}, available: {available}")]
-    MinToken1Error {
-        requested: Uint128,
-        available: Uint128,
-    },
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
 
-    #[error("Min token2 error: requested: {requested}, available: {available}")]
-    MinToken2Error {
-        requested: Uint128,
-        available: Uint128,
-    },
+    #[error("The output amm provided is invalid")]
+    InvalidOutputPool {},
 
-    #[error("Incorrect native denom: provided: {provided}, required: {required}")]
-    IncorrectNativeDenom { provided: String, required: String },
+    #[error("Only the owner can unpause the pool")]
+    UnauthorizedUnpause {},
 
-    #[error("Swap min error: min: {min}, available: {available}")]
-    SwapMinError { min: Uint128, available: Uint128 },
+    #[error("Not a stable swap pool")]
+    NotStableSwapPool {},
 
-    #[error("MsgExpirationError")]
-    MsgExpirationError {},
+    #[error("Invalid amplification: {amp}")]
+    InvalidAmp { amp: u64 },
 
-    #[error("Total fee ({total_fee_percent}) percent is higher than max ({max_fee_percent})")]
-    FeesTooHigh {
-        max_fee_percent: Decimal,
-        total_fee_percent: Decimal,
-    },
+    #[error("Amplification change from {current} to {target} is too large")]
+    AmpChangeTooLarge { current: u64, target: u64 },
 
-    #[error("InsufficientFunds")]
-    InsufficientFunds {},
+    #[error("Amplification ramp ends too early, min end time: {min_end_time}")]
+    AmpRampTooShort { min_end_time: u64 },
 
-    #[error("Uknown reply id: {id}")]
-    UnknownReplyId { id: u64 },
+    #[error("Not a concentrated liquidity pool")]
+    NotConcentratedPool {},
 
-    #[error("Failed to instantiate lp token")]
-    InstantiateLpTokenError {},
+    #[error("Not supported by concentrated liquidity pools")]
+    ConcentratedPoolUnsupported {},
 
-    #[error("The output amm provided is invalid")]
-    InvalidOutputPool {},
+    #[error("A pool can't be both a stable swap and a concentrated liquidity pool")]
+    ConflictingPoolTypes {},
+
+    #[error("Invalid tick spacing: {tick_spacing}")]
+    InvalidTickSpacing { tick_spacing: u32 },
+
+    #[error("Position not found: {id}")]
+    PositionNotFound { id: u64 },
+
+    #[error("A flash swap is in progress")]
+    FlashSwapInProgress {},
+
+    #[error("Flash swap not repaid: required: {required}, repaid: {repaid}")]
+    FlashSwapNotRepaid { required: Uint128, repaid: Uint128 },
+
+    #[error("Native {denom} can't be deposited through a cw20 hook, use add_liquidity")]
+    NativeDepositInReceive { denom: String },
+
+    #[error("Cannot migrate from a different contract: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
 
-    #[error("Unauthorized pool freeze - sender is not an owner or owner has not been set")]
-    UnauthorizedPoolFreeze {},
+    #[error("The pool has no lp token, lp_token_code_id is required to migrate it")]
+    MissingLpTokenCodeId {},
+
+    #[error("The pool holds deposits without an lp token, their owners are unknown")]
+    UntrackedLiquidity {},
+
+    #[error("Cannot migrate from version {previous_version} to older version {new_version}")]
+    CannotMigrateVersion {
+        previous_version: String,
+        new_version: String,
+    },
+}
+
+impl From<PoolError> for ContractError {
+    fn from(err: PoolError) -> Self {
+        match err {
+            PoolError::Std(err) => Self::Std(err),
+            // Same error whether the pool or the contract rejects the sender
+            PoolError::Unauthorized {} => Self::Unauthorized {},
+            err => Self::Pool(err),
+        }
+    }
+}
 
-    #[error("This pools is frozen - you can not deposit or swap tokens")]
-    FrozenPool {},
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
 }
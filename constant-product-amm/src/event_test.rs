@@ -0,0 +1,383 @@
+#![cfg(test)]
+
+use std::borrow::BorrowMut;
+use std::str::FromStr;
+
+use cosmwasm_std::{coins, Addr, Decimal, Event, Uint128};
+use cw20::{Cw20Contract, Cw20ExecuteMsg, Denom};
+use cw_multi_test::{AppResponse, Executor};
+
+use crate::integration_test::{create_amm, create_cw20, mock_app};
+use crate::msg::{ExecuteMsg, InfoResponse, QueryMsg, TokenSelect};
+
+const NATIVE_TOKEN_DENOM: &str = "juno";
+
+fn find_event<'a>(res: &'a AppResponse, ty: &str) -> &'a Event {
+    let mut events = res.events.iter().filter(|e| e.ty == ty);
+    let event = events.next().unwrap();
+    assert!(events.next().is_none(), "more than one {} event", ty);
+    event
+}
+
+// Asserts the event attributes in order, ignoring the contract address added by wasmd
+fn assert_event(res: &AppResponse, ty: &str, expected: &[(&str, &str)]) {
+    let event = find_event(res, ty);
+    let attributes: Vec<(&str, &str)> = event
+        .attributes
+        .iter()
+        .filter(|a| a.key != "_contract_addr")
+        .map(|a| (a.key.as_str(), a.value.as_str()))
+        .collect();
+    assert_eq!(attributes, expected);
+}
+
+#[test]
+fn test_pool_events() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let funds = coins(100_000, NATIVE_TOKEN_DENOM);
+    router.borrow_mut().init_modules(|router, _, storage| {
+        router.bank.init_balance(storage, &owner, funds).unwrap()
+    });
+
+    let cw20_token = create_cw20(
+        &mut router,
+        &owner,
+        "token".to_string(),
+        "CWTOKEN".to_string(),
+        Uint128::new(100_000),
+    );
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Native(NATIVE_TOKEN_DENOM.into()),
+        Denom::Cw20(cw20_token.addr()),
+        Decimal::from_str("0.2").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        "fee_recipient".to_string(),
+    );
+    let info: InfoResponse = router
+        .wrap()
+        .query_wasm_smart(&amm, &QueryMsg::Info {})
+        .unwrap();
+    let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+    let cw20_denom = cw20_token.addr().to_string();
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(100_000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), cw20_token.addr(), &allowance_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), lp_token.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    let res = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &coins(10000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+    assert_event(
+        &res,
+        "wasm-add_liquidity",
+        &[
+            ("sender", "owner"),
+            ("token1_denom", NATIVE_TOKEN_DENOM),
+            ("token1_amount", "10000"),
+            ("token2_denom", &cw20_denom),
+            ("token2_amount", "10000"),
+            ("token1_reserve", "10000"),
+            ("token2_reserve", "10000"),
+            ("lp_token_supply", "10000"),
+            ("liquidity_minted", "9000"),
+        ],
+    );
+
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(1000),
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+    let res = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg,
+            &coins(1000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+    assert_event(
+        &res,
+        "wasm-swap",
+        &[
+            ("sender", "owner"),
+            ("recipient", "owner"),
+            ("input_denom", NATIVE_TOKEN_DENOM),
+            ("input_amount", "1000"),
+            ("output_denom", &cw20_denom),
+            ("output_amount", "906"),
+            ("lp_fee_amount", "2"),
+            ("protocol_fee_amount", "1"),
+            ("token1_reserve", "10999"),
+            ("token2_reserve", "9094"),
+        ],
+    );
+
+    // The recipient of SwapAndSendTo is recorded
+    let swap_msg = ExecuteMsg::SwapAndSendTo {
+        input_token: TokenSelect::Token2,
+        input_amount: Uint128::new(1000),
+        recipient: "recipient".to_string(),
+        min_token: Uint128::zero(),
+        expiration: None,
+    };
+    let res = router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap();
+    let event = find_event(&res, "wasm-swap");
+    assert!(event
+        .attributes
+        .iter()
+        .any(|a| a.key == "recipient" && a.value == "recipient"));
+
+    let info: InfoResponse = router
+        .wrap()
+        .query_wasm_smart(&amm, &QueryMsg::Info {})
+        .unwrap();
+    let remove_liquidity_msg = ExecuteMsg::RemoveLiquidity {
+        amount: Uint128::new(1000),
+        min_token1: Uint128::zero(),
+        min_token2: Uint128::zero(),
+        expiration: None,
+    };
+    let res = router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap();
+    let token1_amount = info.token1_reserve.multiply_ratio(1000u128, 10000u128);
+    let token2_amount = info.token2_reserve.multiply_ratio(1000u128, 10000u128);
+    assert_event(
+        &res,
+        "wasm-remove_liquidity",
+        &[
+            ("sender", "owner"),
+            ("token1_denom", NATIVE_TOKEN_DENOM),
+            ("token1_amount", &token1_amount.to_string()),
+            ("token2_denom", &cw20_denom),
+            ("token2_amount", &token2_amount.to_string()),
+            (
+                "token1_reserve",
+                &(info.token1_reserve - token1_amount).to_string(),
+            ),
+            (
+                "token2_reserve",
+                &(info.token2_reserve - token2_amount).to_string(),
+            ),
+            ("lp_token_supply", "9000"),
+            ("liquidity_burned", "1000"),
+        ],
+    );
+
+    let update_config_msg = ExecuteMsg::UpdateConfig {
+        lp_fee_percent: Decimal::from_str("0.25").unwrap(),
+        protocol_fee_percent: Decimal::from_str("0.05").unwrap(),
+        protocol_fee_recipient: "new_fee_recipient".to_string(),
+        limits: None,
+    };
+    let res = router
+        .execute_contract(owner.clone(), amm.clone(), &update_config_msg, &[])
+        .unwrap();
+    assert_event(
+        &res,
+        "wasm-update_config",
+        &[
+            ("sender", "owner"),
+            ("lp_fee_percent", "0.25"),
+            ("protocol_fee_percent", "0.05"),
+            ("protocol_fee_recipient", "new_fee_recipient"),
+        ],
+    );
+
+    let res = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::FreezeDeposits { freeze: true },
+            &[],
+        )
+        .unwrap();
+    assert_event(
+        &res,
+        "wasm-freeze",
+        &[("sender", "owner"), ("frozen", "true")],
+    );
+}
+
+#[test]
+fn test_single_sided_liquidity_events() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(100_000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(100_000),
+    );
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        Decimal::from_str("0.2").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        "fee_recipient".to_string(),
+    );
+    let info: InfoResponse = router
+        .wrap()
+        .query_wasm_smart(&amm, &QueryMsg::Info {})
+        .unwrap();
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(100_000),
+        expires: None,
+    };
+    for token in [
+        token1.addr(),
+        token2.addr(),
+        Addr::unchecked(info.lp_token_address),
+    ] {
+        router
+            .execute_contract(owner.clone(), token, &allowance_msg, &[])
+            .unwrap();
+    }
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+
+    // The internal swap is reported before the deposit, the swap output stays in the pool
+    let add_single_msg = ExecuteMsg::AddLiquiditySingle {
+        token: TokenSelect::Token1,
+        amount: Uint128::new(2000),
+        min_liquidity: Uint128::zero(),
+        expiration: None,
+    };
+    let res = router
+        .execute_contract(owner.clone(), amm.clone(), &add_single_msg, &[])
+        .unwrap();
+    let types: Vec<&str> = res.events.iter().map(|e| e.ty.as_str()).collect();
+    let swap_index = types.iter().position(|t| *t == "wasm-swap").unwrap();
+    let add_index = types
+        .iter()
+        .position(|t| *t == "wasm-add_liquidity")
+        .unwrap();
+    assert!(swap_index < add_index);
+    assert_event(
+        &res,
+        "wasm-swap",
+        &[
+            ("sender", "owner"),
+            ("recipient", amm.as_str()),
+            ("input_denom", token1.addr().as_str()),
+            ("input_amount", "955"),
+            ("output_denom", token2.addr().as_str()),
+            ("output_amount", "869"),
+            ("lp_fee_amount", "2"),
+            ("protocol_fee_amount", "0"),
+            ("token1_reserve", "10955"),
+            ("token2_reserve", "9131"),
+        ],
+    );
+    assert_event(
+        &res,
+        "wasm-add_liquidity",
+        &[
+            ("sender", "owner"),
+            ("token1_denom", token1.addr().as_str()),
+            ("token1_amount", "1045"),
+            ("token2_denom", token2.addr().as_str()),
+            ("token2_amount", "869"),
+            ("token1_reserve", "12000"),
+            ("token2_reserve", "10000"),
+            ("lp_token_supply", "10951"),
+            ("liquidity_minted", "951"),
+        ],
+    );
+
+    // The withdrawal is reported before the swap of the other side
+    let remove_single_msg = ExecuteMsg::RemoveLiquiditySingle {
+        amount: Uint128::new(951),
+        output_token: TokenSelect::Token2,
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+    let res = router
+        .execute_contract(owner.clone(), amm.clone(), &remove_single_msg, &[])
+        .unwrap();
+    let types: Vec<&str> = res.events.iter().map(|e| e.ty.as_str()).collect();
+    let remove_index = types
+        .iter()
+        .position(|t| *t == "wasm-remove_liquidity")
+        .unwrap();
+    let swap_index = types.iter().position(|t| *t == "wasm-swap").unwrap();
+    assert!(remove_index < swap_index);
+    assert_event(
+        &res,
+        "wasm-remove_liquidity",
+        &[
+            ("sender", "owner"),
+            ("token1_denom", token1.addr().as_str()),
+            ("token1_amount", "1042"),
+            ("token2_denom", token2.addr().as_str()),
+            ("token2_amount", "868"),
+            ("token1_reserve", "10958"),
+            ("token2_reserve", "9132"),
+            ("lp_token_supply", "10000"),
+            ("liquidity_burned", "951"),
+        ],
+    );
+    assert_event(
+        &res,
+        "wasm-swap",
+        &[
+            ("sender", "owner"),
+            ("recipient", "owner"),
+            ("input_denom", token1.addr().as_str()),
+            ("input_amount", "1042"),
+            ("output_denom", token2.addr().as_str()),
+            ("output_amount", "790"),
+            ("lp_fee_amount", "2"),
+            ("protocol_fee_amount", "1"),
+            ("token1_reserve", "11999"),
+            ("token2_reserve", "8342"),
+        ],
+    );
+}
@@ -2,15 +2,31 @@
 
 use std::borrow::BorrowMut;
 
+use crate::contract::CONTRACT_NAME;
 use crate::error::ContractError;
-use cosmwasm_std::{coins, Addr, Coin, Decimal, Empty, Uint128};
-use cw20::{Cw20Coin, Cw20Contract, Cw20ExecuteMsg, Denom};
+use crate::oracle::MAX_PRICE_SNAPSHOTS;
+use crate::state::{Fees, Limits, Token, FEES, OWNER, TOKEN1, TOKEN2};
+use cosmwasm_std::{
+    coin, coins, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Decimal256, Deps,
+    DepsMut, Empty, Env, MessageInfo, Response, StdError, StdResult, Uint128, WasmMsg,
+};
+use cw0::PaymentError;
+use cw2::set_contract_version;
+use cw20::{Cw20Coin, Cw20Contract, Cw20ExecuteMsg, Denom, Expiration};
 use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+use cw_storage_plus::Item;
+use pool_core::PoolError;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
-use crate::msg::{ExecuteMsg, FeeResponse, InfoResponse, InstantiateMsg, QueryMsg, TokenSelect};
+use crate::msg::{
+    AmpResponse, ConcentratedParams, CumulativePricesResponse, ExecuteMsg, FeeResponse,
+    InfoResponse, InstantiateMsg, LimitsResponse, MigrateMsg, OwnershipResponse, PauseResponse,
+    PositionResponse, ProtocolFeesResponse, QueryMsg, ReceiveMsg, ReverseSimulateResponse,
+    SimulateSwapResponse, TicksResponse, Token1ForToken2PriceResponse, TokenSelect, TwapResponse,
+};
 
-fn mock_app() -> App {
+pub fn mock_app() -> App {
     App::default()
 }
 
@@ -19,7 +35,9 @@ pub fn contract_amm() -> Box<dyn Contract<Empty>> {
         crate::contract::execute,
         crate::contract::instantiate,
         crate::contract::query,
-    );
+    )
+    .with_reply(crate::contract::reply)
+    .with_migrate(crate::contract::migrate);
     Box::new(contract)
 }
 
@@ -46,7 +64,14 @@ fn get_fee(router: &App, contract_addr: &Addr) -> FeeResponse {
         .unwrap()
 }
 
-fn create_amm(
+fn get_protocol_fees(router: &App, contract_addr: &Addr) -> ProtocolFeesResponse {
+    router
+        .wrap()
+        .query_wasm_smart(contract_addr, &QueryMsg::ProtocolFees {})
+        .unwrap()
+}
+
+pub fn create_amm(
     router: &mut App,
     owner: &Addr,
     token1_denom: Denom,
@@ -56,6 +81,7 @@ fn create_amm(
     protocol_fee_recipient: String,
 ) -> Addr {
     // set up amm contract
+    let cw20_id = router.store_code(contract_cw20());
     let amm_id = router.store_code(contract_amm());
     let msg = InstantiateMsg {
         token1_denom,
@@ -64,6 +90,9 @@ fn create_amm(
         lp_fee_percent,
         protocol_fee_percent,
         protocol_fee_recipient,
+        lp_token_code_id: cw20_id,
+        amp: None,
+        concentrated: None,
     };
     router
         .instantiate_contract(amm_id, owner.clone(), &msg, &[], "amm", None)
@@ -71,7 +100,7 @@ fn create_amm(
 }
 
 // CreateCW20 create new cw20 with given initial balance belonging to owner
-fn create_cw20(
+pub fn create_cw20(
     router: &mut App,
     owner: &Addr,
     name: String,
@@ -139,7 +168,10 @@ fn test_instantiate() {
 
     assert_ne!(cw20_token.addr(), amm_addr);
 
-    let _info = get_info(&router, &amm_addr);
+    let info = get_info(&router, &amm_addr);
+    // Ensure the lp token was instantiated and registered by the pool
+    assert_eq!(info.lp_token_address, "Contract #2");
+    assert_eq!(info.lp_token_supply, Uint128::zero());
 
     let fee = get_fee(&router, &amm_addr);
     assert_eq!(fee.lp_fee_percent, lp_fee_percent);
@@ -150,6 +182,7 @@ fn test_instantiate() {
     // Test instantiation with invalid fee amount
     let lp_fee_percent = Decimal::from_str("1.01").unwrap();
     let protocol_fee_percent = Decimal::zero();
+    let cw20_id = router.store_code(contract_cw20());
     let amm_id = router.store_code(contract_amm());
     let msg = InstantiateMsg {
         token1_denom: Denom::Native(NATIVE_TOKEN_DENOM.into()),
@@ -158,6 +191,9 @@ fn test_instantiate() {
         lp_fee_percent,
         protocol_fee_percent,
         protocol_fee_recipient: owner.to_string(),
+        lp_token_code_id: cw20_id,
+        amp: None,
+        concentrated: None,
     };
     let err = router
         .instantiate_contract(amm_id, owner.clone(), &msg, &[], "amm", None)
@@ -165,15 +201,14 @@ fn test_instantiate() {
         .downcast()
         .unwrap();
     assert_eq!(
-        ContractError::FeesTooHigh {
+        ContractError::Pool(PoolError::FeesTooHigh {
             max_fee_percent: Decimal::from_str("1").unwrap(),
             total_fee_percent: Decimal::from_str("1.01").unwrap()
-        },
+        }),
         err
     );
 }
 
-
 #[test]
 fn update_config() {
     let mut router = mock_app();
@@ -209,10 +244,10 @@ fn update_config() {
     let lp_fee_percent = Decimal::from_str("0.15").unwrap();
     let protocol_fee_percent = Decimal::from_str("0.15").unwrap();
     let msg = ExecuteMsg::UpdateConfig {
-        owner: Some(owner.to_string()),
         protocol_fee_recipient: "new_fee_recpient".to_string(),
         lp_fee_percent,
         protocol_fee_percent,
+        limits: None,
     };
     let _res = router
         .execute_contract(owner.clone(), amm_addr.clone(), &msg, &[])
@@ -228,10 +263,10 @@ fn update_config() {
     let lp_fee_percent = Decimal::from_str("1.01").unwrap();
     let protocol_fee_percent = Decimal::zero();
     let msg = ExecuteMsg::UpdateConfig {
-        owner: Some(owner.to_string()),
         protocol_fee_recipient: "new_fee_recpient".to_string(),
         lp_fee_percent,
         protocol_fee_percent,
+        limits: None,
     };
     let err = router
         .execute_contract(owner.clone(), amm_addr.clone(), &msg, &[])
@@ -239,10 +274,10 @@ fn update_config() {
         .downcast()
         .unwrap();
     assert_eq!(
-        ContractError::FeesTooHigh {
+        ContractError::Pool(PoolError::FeesTooHigh {
             max_fee_percent: Decimal::from_str("1").unwrap(),
             total_fee_percent: Decimal::from_str("1.01").unwrap()
-        },
+        }),
         err
     );
 
@@ -250,10 +285,10 @@ fn update_config() {
     let lp_fee_percent = Decimal::from_str("0.21").unwrap();
     let protocol_fee_percent = Decimal::from_str("0.09").unwrap();
     let msg = ExecuteMsg::UpdateConfig {
-        owner: Some(owner.to_string()),
         protocol_fee_recipient: owner.to_string(),
         lp_fee_percent,
         protocol_fee_percent,
+        limits: None,
     };
     let err = router
         .execute_contract(
@@ -267,12 +302,12 @@ fn update_config() {
         .unwrap();
     assert_eq!(ContractError::Unauthorized {}, err);
 
-    // Try updating owner and fee params
+    // Try updating fee params, the owner is left untouched
     let msg = ExecuteMsg::UpdateConfig {
-        owner: Some("new_owner".to_string()),
         protocol_fee_recipient: owner.to_string(),
         lp_fee_percent,
         protocol_fee_percent,
+        limits: None,
     };
     let _res = router
         .execute_contract(owner.clone(), amm_addr.clone(), &msg, &[])
@@ -282,7 +317,7 @@ fn update_config() {
     assert_eq!(fee.protocol_fee_recipient, owner.to_string());
     assert_eq!(fee.protocol_fee_percent, protocol_fee_percent);
     assert_eq!(fee.lp_fee_percent, lp_fee_percent);
-    assert_eq!(fee.owner.unwrap(), "new_owner".to_string());
+    assert_eq!(fee.owner.unwrap(), owner.to_string());
 }
 
 #[test]
@@ -302,14 +337,14 @@ fn test_pass_through_swap() {
         &owner,
         "token1".to_string(),
         "TOKENONE".to_string(),
-        Uint128::new(5000),
+        Uint128::new(50000),
     );
     let token2 = create_cw20(
         &mut router,
         &owner,
         "token2".to_string(),
         "TOKENTWO".to_string(),
-        Uint128::new(5000),
+        Uint128::new(50000),
     );
 
     let lp_fee_percent = Decimal::from_str("0.03").unwrap();
@@ -327,7 +362,7 @@ fn test_pass_through_swap() {
     // Add initial liquidity to both pools
     let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
         spender: amm.to_string(),
-        amount: Uint128::new(100),
+        amount: Uint128::new(10000),
         expires: None,
     };
     let _res = router
@@ -338,9 +373,9 @@ fn test_pass_through_swap() {
         .unwrap();
 
     let add_liquidity_msg = ExecuteMsg::AddLiquidity {
-        token1_amount: Uint128::new(100),
-        min_liquidity: Uint128::new(100),
-        token2_amount: Uint128::new(100),
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::new(9000),
+        token2_amount: Uint128::new(10000),
         expiration: None,
     };
     router
@@ -377,10 +412,10 @@ fn test_pass_through_swap() {
 
     // ensure balances updated
     let token1_balance = token1.balance(&router, owner.clone()).unwrap();
-    assert_eq!(token1_balance, Uint128::new(4890));
+    assert_eq!(token1_balance, Uint128::new(39990));
 
     let token2_balance = token2.balance(&router, owner.clone()).unwrap();
-    assert_eq!(token2_balance, Uint128::new(4909));
+    assert_eq!(token2_balance, Uint128::new(40009));
 
     let amm_native_balance = bank_balance(&mut router, &amm, NATIVE_TOKEN_DENOM.to_string());
     assert_eq!(amm_native_balance.amount, Uint128::zero());
@@ -393,4 +428,3668 @@ fn test_pass_through_swap() {
     println!("{} {}", token1_balance, token2_balance);
     assert_eq!(info_amm.token2_reserve, token2_balance);
     assert_eq!(info_amm.token1_reserve, token1_balance);
+
+    // Route token1 through a second pool trading token2 for token3
+    let token3 = create_cw20(
+        &mut router,
+        &owner,
+        "token3".to_string(),
+        "TOKENTHREE".to_string(),
+        Uint128::new(50000),
+    );
+    let amm2 = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token2.addr()),
+        Denom::Cw20(token3.addr()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        owner.to_string(),
+    );
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm2.to_string(),
+        amount: Uint128::new(10000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token2.addr(), &allowance_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), token3.addr(), &allowance_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), amm2.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(1000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token1.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    let token2_bought: Token1ForToken2PriceResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm,
+            &QueryMsg::Token1ForToken2Price {
+                token1_amount: Uint128::new(1000),
+            },
+        )
+        .unwrap();
+    let token3_bought: Token1ForToken2PriceResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm2,
+            &QueryMsg::Token1ForToken2Price {
+                token1_amount: token2_bought.token2_amount,
+            },
+        )
+        .unwrap();
+
+    // The final minimum is enforced by the output pool
+    let pass_through_msg = ExecuteMsg::PassThroughSwap {
+        output_amm_address: amm2.to_string(),
+        input_token: TokenSelect::Token1,
+        input_token_amount: Uint128::new(1000),
+        output_min_token: token3_bought.token2_amount + Uint128::new(1),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &pass_through_msg, &[])
+        .unwrap_err();
+
+    // A pool that does not trade the intermediate token is rejected
+    let pass_through_msg = ExecuteMsg::PassThroughSwap {
+        output_amm_address: amm.to_string(),
+        input_token: TokenSelect::Token1,
+        input_token_amount: Uint128::new(1000),
+        output_min_token: Uint128::zero(),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &pass_through_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::InvalidOutputPool {}, err);
+
+    let pass_through_msg = ExecuteMsg::PassThroughSwap {
+        output_amm_address: amm2.to_string(),
+        input_token: TokenSelect::Token1,
+        input_token_amount: Uint128::new(1000),
+        output_min_token: token3_bought.token2_amount,
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &pass_through_msg, &[])
+        .unwrap();
+
+    let token1_balance = token1.balance(&router, owner.clone()).unwrap();
+    assert_eq!(token1_balance, Uint128::new(38990));
+    let token3_balance = token3.balance(&router, owner.clone()).unwrap();
+    assert_eq!(
+        token3_balance,
+        Uint128::new(40000) + token3_bought.token2_amount
+    );
+
+    // Both pools stay consistent with their balances
+    let info_amm = get_info(&router, &amm);
+    assert_eq!(
+        info_amm.token1_reserve,
+        token1.balance(&router, amm.clone()).unwrap()
+    );
+    assert_eq!(
+        info_amm.token2_reserve,
+        token2.balance(&router, amm.clone()).unwrap()
+    );
+    let info_amm2 = get_info(&router, &amm2);
+    assert_eq!(
+        info_amm2.token1_reserve,
+        token2.balance(&router, amm2.clone()).unwrap()
+    );
+    assert_eq!(
+        info_amm2.token2_reserve,
+        token3.balance(&router, amm2.clone()).unwrap()
+    );
+}
+
+#[test]
+fn test_lp_token_mint_and_burn() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(50000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(50000),
+    );
+
+    let lp_fee_percent = Decimal::from_str("0.03").unwrap();
+    let protocol_fee_percent = Decimal::zero();
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        owner.to_string(),
+    );
+
+    let info = get_info(&router, &amm);
+    let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+
+    // Add liquidity
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(10000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token1.addr(), &allowance_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), token2.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::new(9000),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+
+    // The depositor holds real lp tokens
+    let lp_balance = lp_token.balance(&router, owner.clone()).unwrap();
+    assert_eq!(lp_balance, Uint128::new(9000));
+    let balance: cw20::BalanceResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm,
+            &QueryMsg::Balance {
+                address: owner.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance.balance, lp_balance);
+    let info = get_info(&router, &amm);
+    assert_eq!(info.lp_token_supply, Uint128::new(10000));
+
+    // Lp tokens are transferable
+    let transfer_msg = Cw20ExecuteMsg::Transfer {
+        recipient: "user".to_string(),
+        amount: Uint128::new(500),
+    };
+    router
+        .execute_contract(owner.clone(), lp_token.addr(), &transfer_msg, &[])
+        .unwrap();
+
+    // Removing more than the sender holds fails
+    let remove_liquidity_msg = ExecuteMsg::RemoveLiquidity {
+        amount: Uint128::new(9000),
+        min_token1: Uint128::zero(),
+        min_token2: Uint128::zero(),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::Pool(PoolError::InsufficientLiquidityError {
+            requested: Uint128::new(9000),
+            available: Uint128::new(8500),
+        }),
+        err
+    );
+
+    // Remove liquidity, the pool burns the returned lp tokens
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(500),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), lp_token.addr(), &allowance_msg, &[])
+        .unwrap();
+    let remove_liquidity_msg = ExecuteMsg::RemoveLiquidity {
+        amount: Uint128::new(500),
+        min_token1: Uint128::zero(),
+        min_token2: Uint128::zero(),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap();
+
+    let lp_balance = lp_token.balance(&router, owner.clone()).unwrap();
+    assert_eq!(lp_balance, Uint128::new(8000));
+    let info = get_info(&router, &amm);
+    assert_eq!(info.lp_token_supply, Uint128::new(9500));
+    // The pool holds the reserves and the uncollected protocol fees
+    let protocol_fees = get_protocol_fees(&router, &amm);
+    assert_eq!(
+        info.token1_reserve + protocol_fees.token1_amount,
+        token1.balance(&router, amm.clone()).unwrap()
+    );
+    assert_eq!(
+        info.token2_reserve + protocol_fees.token2_amount,
+        token2.balance(&router, amm.clone()).unwrap()
+    );
+}
+
+#[test]
+fn test_add_liquidity_ratio() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(50000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(50000),
+    );
+
+    let lp_fee_percent = Decimal::from_str("0.03").unwrap();
+    let protocol_fee_percent = Decimal::zero();
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        owner.to_string(),
+    );
+    let info = get_info(&router, &amm);
+    let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(50000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token1.addr(), &allowance_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), token2.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    // First deposit below the locked minimum fails
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(100),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(100),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::Pool(PoolError::MinLiquidityError {
+            min_liquidity: Uint128::new(1000),
+            liquidity_available: Uint128::new(100),
+        }),
+        err
+    );
+
+    // First deposit mints sqrt(x * y) minus the locked minimum
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(4000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(16000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+    assert_eq!(
+        lp_token.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(7000)
+    );
+    assert_eq!(
+        lp_token.balance(&router, amm.clone()).unwrap(),
+        Uint128::new(1000)
+    );
+
+    // Lopsided deposit only pulls token2 in the pool ratio
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(1000),
+        min_liquidity: Uint128::new(2000),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+    assert_eq!(
+        token2.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(30000)
+    );
+    assert_eq!(
+        lp_token.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(9000)
+    );
+
+    // Not enough token2 for the pool ratio
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(1000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(3999),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::Pool(PoolError::MaxTokenError {
+            max_token: Uint128::new(3999),
+            tokens_required: Uint128::new(4000),
+        }),
+        err
+    );
+
+    // Withdrawal is pro rata to the lp supply
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(2000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), lp_token.addr(), &allowance_msg, &[])
+        .unwrap();
+    let remove_liquidity_msg = ExecuteMsg::RemoveLiquidity {
+        amount: Uint128::new(2000),
+        min_token1: Uint128::new(1000),
+        min_token2: Uint128::new(4000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap();
+
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(4000));
+    assert_eq!(info.token2_reserve, Uint128::new(16000));
+    assert_eq!(info.lp_token_supply, Uint128::new(8000));
+}
+
+#[test]
+fn test_native_native_pool() {
+    let mut router = mock_app();
+
+    const NATIVE_TOKEN_DENOM: &str = "juno";
+    const IBC_TOKEN_DENOM: &str = "atom";
+
+    let owner = Addr::unchecked("owner");
+    let funds = vec![
+        coin(100_000, IBC_TOKEN_DENOM),
+        coin(100_000, NATIVE_TOKEN_DENOM),
+    ];
+    router.borrow_mut().init_modules(|router, _, storage| {
+        router.bank.init_balance(storage, &owner, funds).unwrap()
+    });
+
+    let lp_fee_percent = Decimal::from_str("0.2").unwrap();
+    let protocol_fee_percent = Decimal::from_str("0.1").unwrap();
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Native(NATIVE_TOKEN_DENOM.into()),
+        Denom::Native(IBC_TOKEN_DENOM.into()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        "fee_recipient".to_string(),
+    );
+    let info = get_info(&router, &amm);
+    let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+
+    // Funds must match the declared amounts
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &[coin(10000, IBC_TOKEN_DENOM), coin(9000, NATIVE_TOKEN_DENOM)],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::Pool(PoolError::InsufficientFunds {}), err);
+
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &[
+                coin(10000, IBC_TOKEN_DENOM),
+                coin(10000, NATIVE_TOKEN_DENOM),
+            ],
+        )
+        .unwrap();
+
+    // Excess native token2 is refunded
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(1000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(1500),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &[coin(1500, IBC_TOKEN_DENOM), coin(1000, NATIVE_TOKEN_DENOM)],
+        )
+        .unwrap();
+    let owner_balance = bank_balance(&mut router, &owner, IBC_TOKEN_DENOM.to_string());
+    assert_eq!(owner_balance.amount, Uint128::new(89000));
+
+    // Swap with the wrong denom attached
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(1000),
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg,
+            &[coin(1000, IBC_TOKEN_DENOM)],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::Pool(PoolError::IncorrectNativeDenom {
+            provided: IBC_TOKEN_DENOM.to_string(),
+            required: NATIVE_TOKEN_DENOM.to_string(),
+        }),
+        err
+    );
+
+    // Coins that are not an input of the message are rejected instead of kept
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg,
+            &[coin(1000, NATIVE_TOKEN_DENOM), coin(1, IBC_TOKEN_DENOM)],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::UnexpectedFunds {
+            denom: IBC_TOKEN_DENOM.to_string(),
+        }),
+        err.downcast().unwrap()
+    );
+
+    // Swap token1 for token2, protocol fee is kept from the attached coins
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg,
+            &[coin(1000, NATIVE_TOKEN_DENOM)],
+        )
+        .unwrap();
+    let owner_balance = bank_balance(&mut router, &owner, IBC_TOKEN_DENOM.to_string());
+    assert_eq!(owner_balance.amount, Uint128::new(89914));
+    assert_eq!(
+        get_protocol_fees(&router, &amm).token1_amount,
+        Uint128::new(1)
+    );
+
+    // Only the protocol fee recipient can collect
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::CollectProtocolFees {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+    router
+        .execute_contract(
+            Addr::unchecked("fee_recipient"),
+            amm.clone(),
+            &ExecuteMsg::CollectProtocolFees {},
+            &[],
+        )
+        .unwrap();
+    let fee_balance = bank_balance(
+        &mut router,
+        &Addr::unchecked("fee_recipient"),
+        NATIVE_TOKEN_DENOM.to_string(),
+    );
+    assert_eq!(fee_balance.amount, Uint128::new(1));
+    assert_eq!(
+        get_protocol_fees(&router, &amm),
+        ProtocolFeesResponse {
+            token1_amount: Uint128::zero(),
+            token2_amount: Uint128::zero(),
+            protocol_fee_recipient: "fee_recipient".to_string(),
+        }
+    );
+
+    // Remove liquidity pays out native coins
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(5000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), lp_token.addr(), &allowance_msg, &[])
+        .unwrap();
+    let remove_liquidity_msg = ExecuteMsg::RemoveLiquidity {
+        amount: Uint128::new(5000),
+        min_token1: Uint128::zero(),
+        min_token2: Uint128::zero(),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &remove_liquidity_msg,
+            &[coin(10, NATIVE_TOKEN_DENOM)],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Payment(PaymentError::NonPayable {}),
+        err.downcast().unwrap()
+    );
+    router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap();
+
+    let info = get_info(&router, &amm);
+    let token1_balance = bank_balance(&mut router, &amm, NATIVE_TOKEN_DENOM.to_string());
+    let token2_balance = bank_balance(&mut router, &amm, IBC_TOKEN_DENOM.to_string());
+    assert_eq!(info.token1_reserve, token1_balance.amount);
+    assert_eq!(info.token2_reserve, token2_balance.amount);
+    assert_eq!(info.lp_token_supply, Uint128::new(6000));
+}
+
+#[test]
+fn test_native_cw20_pool() {
+    let mut router = mock_app();
+
+    const NATIVE_TOKEN_DENOM: &str = "juno";
+
+    let owner = Addr::unchecked("owner");
+    let funds = coins(100_000, NATIVE_TOKEN_DENOM);
+    router.borrow_mut().init_modules(|router, _, storage| {
+        router.bank.init_balance(storage, &owner, funds).unwrap()
+    });
+
+    let cw20_token = create_cw20(
+        &mut router,
+        &owner,
+        "token".to_string(),
+        "CWTOKEN".to_string(),
+        Uint128::new(100_000),
+    );
+
+    let lp_fee_percent = Decimal::from_str("0.2").unwrap();
+    let protocol_fee_percent = Decimal::from_str("0.1").unwrap();
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Native(NATIVE_TOKEN_DENOM.into()),
+        Denom::Cw20(cw20_token.addr()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        "fee_recipient".to_string(),
+    );
+    let info = get_info(&router, &amm);
+    let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(100_000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), cw20_token.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &coins(10000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+
+    // The cw20 hook can't deposit the native side
+    let send_msg = Cw20ExecuteMsg::Send {
+        contract: amm.to_string(),
+        amount: Uint128::new(1000),
+        msg: to_binary(&ReceiveMsg::AddLiquidity {
+            other_token_amount: Uint128::new(1000),
+            min_liquidity: Uint128::zero(),
+            expiration: None,
+        })
+        .unwrap(),
+    };
+    let err = router
+        .execute_contract(owner.clone(), cw20_token.addr(), &send_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::NativeDepositInReceive {
+            denom: NATIVE_TOKEN_DENOM.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    // Swap cw20 for native
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token2,
+        input_amount: Uint128::new(1000),
+        min_output: Uint128::new(906),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap();
+    let owner_balance = bank_balance(&mut router, &owner, NATIVE_TOKEN_DENOM.to_string());
+    assert_eq!(owner_balance.amount, Uint128::new(90906));
+    assert_eq!(
+        get_protocol_fees(&router, &amm).token2_amount,
+        Uint128::new(1)
+    );
+
+    // Swap native for cw20
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(1000),
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg,
+            &coins(1000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+    assert_eq!(
+        get_protocol_fees(&router, &amm).token1_amount,
+        Uint128::new(1)
+    );
+
+    // Fees accrued in both tokens are collected together
+    router
+        .execute_contract(
+            Addr::unchecked("fee_recipient"),
+            amm.clone(),
+            &ExecuteMsg::CollectProtocolFees {},
+            &[],
+        )
+        .unwrap();
+    let fee_balance = bank_balance(
+        &mut router,
+        &Addr::unchecked("fee_recipient"),
+        NATIVE_TOKEN_DENOM.to_string(),
+    );
+    assert_eq!(fee_balance.amount, Uint128::new(1));
+    let fee_balance = cw20_token
+        .balance(&router, Addr::unchecked("fee_recipient"))
+        .unwrap();
+    assert_eq!(fee_balance, Uint128::new(1));
+
+    // Remove all removable liquidity
+    let lp_balance = lp_token.balance(&router, owner.clone()).unwrap();
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: lp_balance,
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), lp_token.addr(), &allowance_msg, &[])
+        .unwrap();
+    let remove_liquidity_msg = ExecuteMsg::RemoveLiquidity {
+        amount: lp_balance,
+        min_token1: Uint128::zero(),
+        min_token2: Uint128::zero(),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap();
+
+    let info = get_info(&router, &amm);
+    let token1_balance = bank_balance(&mut router, &amm, NATIVE_TOKEN_DENOM.to_string());
+    let token2_balance = cw20_token.balance(&router, amm.clone()).unwrap();
+    assert_eq!(info.token1_reserve, token1_balance.amount);
+    assert_eq!(info.token2_reserve, token2_balance);
+    assert_eq!(info.lp_token_supply, Uint128::new(1000));
+}
+
+#[test]
+fn test_cw20_cw20_pool() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(100_000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(100_000),
+    );
+
+    let lp_fee_percent = Decimal::from_str("0.2").unwrap();
+    let protocol_fee_percent = Decimal::from_str("0.1").unwrap();
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        "fee_recipient".to_string(),
+    );
+    let info = get_info(&router, &amm);
+    let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(100_000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token1.addr(), &allowance_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), token2.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+
+    // Swap token1 for token2, protocol fee is pulled from the sender
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(1000),
+        min_output: Uint128::new(906),
+        expiration: None,
+    };
+
+    // A cw20 pair takes no native funds
+    router.borrow_mut().init_modules(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &owner, coins(1000, "juno"))
+            .unwrap()
+    });
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &coins(1000, "juno"))
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::UnexpectedFunds {
+            denom: "juno".to_string(),
+        }),
+        err.downcast().unwrap()
+    );
+
+    router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap();
+    assert_eq!(
+        token2.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(90906)
+    );
+    assert_eq!(
+        get_protocol_fees(&router, &amm).token1_amount,
+        Uint128::new(1)
+    );
+
+    let lp_balance = lp_token.balance(&router, owner.clone()).unwrap();
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: lp_balance,
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), lp_token.addr(), &allowance_msg, &[])
+        .unwrap();
+    let remove_liquidity_msg = ExecuteMsg::RemoveLiquidity {
+        amount: lp_balance,
+        min_token1: Uint128::zero(),
+        min_token2: Uint128::zero(),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap();
+
+    let info = get_info(&router, &amm);
+    // The pool holds the reserves and the uncollected protocol fees
+    let protocol_fees = get_protocol_fees(&router, &amm);
+    assert_eq!(
+        info.token1_reserve + protocol_fees.token1_amount,
+        token1.balance(&router, amm.clone()).unwrap()
+    );
+    assert_eq!(
+        info.token2_reserve + protocol_fees.token2_amount,
+        token2.balance(&router, amm.clone()).unwrap()
+    );
+    assert_eq!(info.lp_token_supply, Uint128::new(1000));
+}
+
+#[test]
+fn test_cw20_receive_hooks() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(100_000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(100_000),
+    );
+    let other_token = create_cw20(
+        &mut router,
+        &owner,
+        "other".to_string(),
+        "OTHER".to_string(),
+        Uint128::new(100_000),
+    );
+
+    let lp_fee_percent = Decimal::from_str("0.2").unwrap();
+    let protocol_fee_percent = Decimal::from_str("0.1").unwrap();
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        "fee_recipient".to_string(),
+    );
+    let info = get_info(&router, &amm);
+    let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+
+    // Send token1, token2 is pulled with an allowance
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(10000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token2.addr(), &allowance_msg, &[])
+        .unwrap();
+    let send_msg = Cw20ExecuteMsg::Send {
+        contract: amm.to_string(),
+        amount: Uint128::new(10000),
+        msg: to_binary(&ReceiveMsg::AddLiquidity {
+            other_token_amount: Uint128::new(10000),
+            min_liquidity: Uint128::new(9000),
+            expiration: None,
+        })
+        .unwrap(),
+    };
+    router
+        .execute_contract(owner.clone(), token1.addr(), &send_msg, &[])
+        .unwrap();
+    assert_eq!(
+        lp_token.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(9000)
+    );
+
+    // Send token2 above the pool ratio, the excess is refunded
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(1000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token1.addr(), &allowance_msg, &[])
+        .unwrap();
+    let send_msg = Cw20ExecuteMsg::Send {
+        contract: amm.to_string(),
+        amount: Uint128::new(5000),
+        msg: to_binary(&ReceiveMsg::AddLiquidity {
+            other_token_amount: Uint128::new(1000),
+            min_liquidity: Uint128::zero(),
+            expiration: None,
+        })
+        .unwrap(),
+    };
+    router
+        .execute_contract(owner.clone(), token2.addr(), &send_msg, &[])
+        .unwrap();
+    assert_eq!(
+        token2.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(89000)
+    );
+    assert_eq!(
+        lp_token.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(10000)
+    );
+
+    // Swap in a single transaction
+    let send_msg = Cw20ExecuteMsg::Send {
+        contract: amm.to_string(),
+        amount: Uint128::new(1000),
+        msg: to_binary(&ReceiveMsg::Swap {
+            min_output: Uint128::new(900),
+            expiration: None,
+        })
+        .unwrap(),
+    };
+    router
+        .execute_contract(owner.clone(), token1.addr(), &send_msg, &[])
+        .unwrap();
+    assert_eq!(
+        token2.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(89914)
+    );
+    assert_eq!(
+        get_protocol_fees(&router, &amm).token1_amount,
+        Uint128::new(1)
+    );
+
+    // Tokens outside of the pool are rejected
+    let send_msg = Cw20ExecuteMsg::Send {
+        contract: amm.to_string(),
+        amount: Uint128::new(1000),
+        msg: to_binary(&ReceiveMsg::Swap {
+            min_output: Uint128::zero(),
+            expiration: None,
+        })
+        .unwrap(),
+    };
+    let err = router
+        .execute_contract(owner.clone(), other_token.addr(), &send_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::Unauthorized {}, err);
+
+    // Only lp tokens can be sent back to remove liquidity
+    let send_msg = Cw20ExecuteMsg::Send {
+        contract: amm.to_string(),
+        amount: Uint128::new(1000),
+        msg: to_binary(&ReceiveMsg::RemoveLiquidity {
+            min_token1: Uint128::zero(),
+            min_token2: Uint128::zero(),
+            expiration: None,
+        })
+        .unwrap(),
+    };
+    let err = router
+        .execute_contract(owner.clone(), token1.addr(), &send_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::Unauthorized {}, err);
+
+    router
+        .execute_contract(owner.clone(), lp_token.addr(), &send_msg, &[])
+        .unwrap();
+    assert_eq!(
+        lp_token.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(9000)
+    );
+
+    let info = get_info(&router, &amm);
+    assert_eq!(info.lp_token_supply, Uint128::new(10000));
+    // The pool holds the reserves and the uncollected protocol fees
+    let protocol_fees = get_protocol_fees(&router, &amm);
+    assert_eq!(
+        info.token1_reserve + protocol_fees.token1_amount,
+        token1.balance(&router, amm.clone()).unwrap()
+    );
+    assert_eq!(
+        info.token2_reserve + protocol_fees.token2_amount,
+        token2.balance(&router, amm.clone()).unwrap()
+    );
+}
+
+#[test]
+fn test_migrate() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(5000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(5000),
+    );
+
+    let cw20_id = router.store_code(contract_cw20());
+    let amm_id = router.store_code(contract_amm());
+    let msg = InstantiateMsg {
+        token1_denom: Denom::Cw20(token1.addr()),
+        token2_denom: Denom::Cw20(token2.addr()),
+        owner: Some(owner.to_string()),
+        lp_fee_percent: Decimal::from_str("0.3").unwrap(),
+        protocol_fee_percent: Decimal::zero(),
+        protocol_fee_recipient: owner.to_string(),
+        lp_token_code_id: cw20_id,
+        amp: None,
+        concentrated: None,
+    };
+    let amm = router
+        .instantiate_contract(
+            amm_id,
+            owner.clone(),
+            &msg,
+            &[],
+            "amm",
+            Some(owner.to_string()),
+        )
+        .unwrap();
+
+    // Fees above the maximum are rejected like on instantiation
+    let new_amm_id = router.store_code(contract_amm());
+    let migrate_msg = MigrateMsg {
+        owner: Some("new_owner".to_string()),
+        protocol_fee_recipient: "new_fee_recipient".to_string(),
+        protocol_fee_percent: Decimal::from_str("0.5").unwrap(),
+        lp_fee_percent: Decimal::from_str("0.6").unwrap(),
+        freeze_pool: true,
+        lp_token_code_id: None,
+    };
+    let err = router
+        .migrate_contract(owner.clone(), amm.clone(), &migrate_msg, new_amm_id)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::Pool(PoolError::FeesTooHigh {
+            max_fee_percent: Decimal::from_str("1").unwrap(),
+            total_fee_percent: Decimal::from_str("1.1").unwrap()
+        }),
+        err
+    );
+
+    let migrate_msg = MigrateMsg {
+        lp_fee_percent: Decimal::from_str("0.2").unwrap(),
+        ..migrate_msg
+    };
+    router
+        .migrate_contract(owner.clone(), amm.clone(), &migrate_msg, new_amm_id)
+        .unwrap();
+
+    let fee = get_fee(&router, &amm);
+    assert_eq!(fee.owner.unwrap(), "new_owner".to_string());
+    assert_eq!(fee.protocol_fee_recipient, "new_fee_recipient".to_string());
+    assert_eq!(fee.protocol_fee_percent, Decimal::from_str("0.5").unwrap());
+    assert_eq!(fee.lp_fee_percent, Decimal::from_str("0.2").unwrap());
+
+    // The migrated pool is frozen
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(2000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(2000),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::Pool(PoolError::DepositsPaused {}), err);
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct BaselineInstantiateMsg {
+    token1_denom: Denom,
+    token2_denom: Denom,
+    reserve: Uint128,
+}
+
+// Storage of a pool deployed before the lp token, deposits weren't tracked per provider
+fn baseline_instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: BaselineInstantiateMsg,
+) -> StdResult<Response> {
+    set_contract_version(deps.storage, CONTRACT_NAME, "0.1.0")?;
+    let token1 = Token {
+        reserve: msg.reserve,
+        denom: msg.token1_denom,
+    };
+    TOKEN1.save(deps.storage, &token1)?;
+    let token2 = Token {
+        reserve: msg.reserve,
+        denom: msg.token2_denom,
+    };
+    TOKEN2.save(deps.storage, &token2)?;
+    OWNER.save(deps.storage, &Some(info.sender.clone()))?;
+    let fees = Fees {
+        protocol_fee_recipient: info.sender,
+        protocol_fee_percent: Decimal::zero(),
+        lp_fee_percent: Decimal::from_str("0.3").unwrap(),
+    };
+    FEES.save(deps.storage, &fees)?;
+    Item::<bool>::new("frozen").save(deps.storage, &false)?;
+    Ok(Response::new())
+}
+
+fn baseline_execute(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: Empty,
+) -> StdResult<Response> {
+    Err(StdError::generic_err("No messages"))
+}
+
+fn baseline_query(_deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+    Err(StdError::generic_err("No queries"))
+}
+
+#[test]
+fn test_migrate_without_lp_token() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let funds = vec![coin(2000, "token1"), coin(2000, "token2")];
+    router.borrow_mut().init_modules(|router, _, storage| {
+        router.bank.init_balance(storage, &owner, funds).unwrap()
+    });
+
+    let baseline_id = router.store_code(Box::new(ContractWrapper::new(
+        baseline_execute,
+        baseline_instantiate,
+        baseline_query,
+    )));
+    let cw20_id = router.store_code(contract_cw20());
+    let amm_id = router.store_code(contract_amm());
+    let mut create_baseline_pool = |reserve: u128| {
+        let msg = BaselineInstantiateMsg {
+            token1_denom: Denom::Native("token1".to_string()),
+            token2_denom: Denom::Native("token2".to_string()),
+            reserve: Uint128::new(reserve),
+        };
+        router
+            .instantiate_contract(
+                baseline_id,
+                owner.clone(),
+                &msg,
+                &[],
+                "amm",
+                Some(owner.to_string()),
+            )
+            .unwrap()
+    };
+    let amm = create_baseline_pool(0);
+    let funded_amm = create_baseline_pool(1000);
+
+    let migrate_msg = MigrateMsg {
+        owner: Some(owner.to_string()),
+        protocol_fee_recipient: owner.to_string(),
+        protocol_fee_percent: Decimal::zero(),
+        lp_fee_percent: Decimal::from_str("0.3").unwrap(),
+        freeze_pool: false,
+        lp_token_code_id: None,
+    };
+    let err = router
+        .migrate_contract(owner.clone(), amm.clone(), &migrate_msg, amm_id)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::MissingLpTokenCodeId {}, err);
+
+    // The deposits of a funded pool have no known owners to mint lp tokens to
+    let migrate_msg = MigrateMsg {
+        lp_token_code_id: Some(cw20_id),
+        ..migrate_msg
+    };
+    let err = router
+        .migrate_contract(owner.clone(), funded_amm, &migrate_msg, amm_id)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::UntrackedLiquidity {}, err);
+
+    // An empty pool gets its lp token through the instantiate reply
+    router
+        .migrate_contract(owner.clone(), amm.clone(), &migrate_msg, amm_id)
+        .unwrap();
+    let info = get_info(&router, &amm);
+    assert_eq!(info.lp_token_supply, Uint128::zero());
+    let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(2000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(2000),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &[coin(2000, "token1"), coin(2000, "token2")],
+        )
+        .unwrap();
+    assert_eq!(
+        lp_token.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(1000)
+    );
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(1000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), lp_token.addr(), &allowance_msg, &[])
+        .unwrap();
+    let remove_liquidity_msg = ExecuteMsg::RemoveLiquidity {
+        amount: Uint128::new(1000),
+        min_token1: Uint128::new(1000),
+        min_token2: Uint128::new(1000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap();
+    assert_eq!(get_info(&router, &amm).lp_token_supply, Uint128::new(1000));
+}
+
+#[test]
+fn test_pass_through_swap_native() {
+    let mut router = mock_app();
+
+    const NATIVE_TOKEN_DENOM: &str = "juno";
+
+    let owner = Addr::unchecked("owner");
+    let funds = coins(50000, NATIVE_TOKEN_DENOM);
+    router.borrow_mut().init_modules(|router, _, storage| {
+        router.bank.init_balance(storage, &owner, funds).unwrap()
+    });
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(50000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(50000),
+    );
+
+    let lp_fee_percent = Decimal::from_str("0.03").unwrap();
+    let protocol_fee_percent = Decimal::zero();
+    let amm1 = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Native(NATIVE_TOKEN_DENOM.into()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        owner.to_string(),
+    );
+    let amm2 = create_amm(
+        &mut router,
+        &owner,
+        Denom::Native(NATIVE_TOKEN_DENOM.into()),
+        Denom::Cw20(token2.addr()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        owner.to_string(),
+    );
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm1.to_string(),
+        amount: Uint128::new(11000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token1.addr(), &allowance_msg, &[])
+        .unwrap();
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm2.to_string(),
+        amount: Uint128::new(10000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token2.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm1.clone(),
+            &add_liquidity_msg,
+            &coins(10000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+    router
+        .execute_contract(
+            owner.clone(),
+            amm2.clone(),
+            &add_liquidity_msg,
+            &coins(10000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+
+    // token1 -> juno -> token2, the intermediate native coins are attached to the second swap
+    let pass_through_msg = ExecuteMsg::PassThroughSwap {
+        output_amm_address: amm2.to_string(),
+        input_token: TokenSelect::Token1,
+        input_token_amount: Uint128::new(1000),
+        output_min_token: Uint128::new(800),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm1.clone(), &pass_through_msg, &[])
+        .unwrap();
+
+    // 1000 token1 buys 908 juno, which buys 832 token2
+    let token2_balance = token2.balance(&router, owner.clone()).unwrap();
+    assert_eq!(token2_balance, Uint128::new(40832));
+
+    let info_amm1 = get_info(&router, &amm1);
+    let info_amm2 = get_info(&router, &amm2);
+    assert_eq!(info_amm1.token2_reserve, Uint128::new(9092));
+    assert_eq!(
+        info_amm1.token2_reserve,
+        bank_balance(&mut router, &amm1, NATIVE_TOKEN_DENOM.to_string()).amount
+    );
+    assert_eq!(
+        info_amm2.token1_reserve,
+        bank_balance(&mut router, &amm2, NATIVE_TOKEN_DENOM.to_string()).amount
+    );
+    assert_eq!(
+        info_amm2.token2_reserve,
+        token2.balance(&router, amm2.clone()).unwrap()
+    );
+}
+
+#[test]
+fn test_twap() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(50000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(50000),
+    );
+
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        Decimal::zero(),
+        Decimal::zero(),
+        owner.to_string(),
+    );
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(50000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token1.addr(), &allowance_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), token2.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+
+    // Price stays at 1 for 300 seconds
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(300);
+        block.height += 1;
+    });
+
+    // Move the price to 4 token2 per token1
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token2,
+        input_amount: Uint128::new(10000),
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap();
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(5000));
+    assert_eq!(info.token2_reserve, Uint128::new(20000));
+
+    router.update_block(|block| {
+        block.time = block.time.plus_seconds(300);
+        block.height += 1;
+    });
+
+    let cumulative: CumulativePricesResponse = router
+        .wrap()
+        .query_wasm_smart(&amm, &QueryMsg::CumulativePrices {})
+        .unwrap();
+    assert_eq!(
+        cumulative.price1_cumulative,
+        Decimal256::from_ratio(1500u64, 1u64)
+    );
+    assert_eq!(
+        cumulative.price2_cumulative,
+        Decimal256::from_ratio(375u64, 1u64)
+    );
+
+    // The last 300 seconds were spent at the new price
+    let twap: TwapResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm,
+            &QueryMsg::Twap {
+                token: TokenSelect::Token1,
+                window_seconds: 300,
+            },
+        )
+        .unwrap();
+    assert_eq!(twap.price, Decimal256::from_ratio(4u64, 1u64));
+    assert_eq!(twap.window_seconds, 300);
+
+    // Over the whole history the average is between both prices
+    let twap: TwapResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm,
+            &QueryMsg::Twap {
+                token: TokenSelect::Token1,
+                window_seconds: 600,
+            },
+        )
+        .unwrap();
+    assert_eq!(twap.price, Decimal256::from_ratio(5u64, 2u64));
+    let twap: TwapResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm,
+            &QueryMsg::Twap {
+                token: TokenSelect::Token2,
+                window_seconds: 600,
+            },
+        )
+        .unwrap();
+    assert_eq!(twap.price, Decimal256::from_ratio(5u64, 8u64));
+
+    // The pool has no history before its creation
+    router
+        .wrap()
+        .query_wasm_smart::<TwapResponse>(
+            &amm,
+            &QueryMsg::Twap {
+                token: TokenSelect::Token1,
+                window_seconds: 900,
+            },
+        )
+        .unwrap_err();
+
+    // Dust swaps in every block can't push a window out of the history, only one snapshot is
+    // kept per interval
+    for i in 0..MAX_PRICE_SNAPSHOTS + 12 {
+        router.update_block(|block| {
+            block.time = block.time.plus_seconds(1);
+            block.height += 1;
+        });
+        let swap_msg = ExecuteMsg::Swap {
+            input_token: if i % 2 == 0 {
+                TokenSelect::Token1
+            } else {
+                TokenSelect::Token2
+            },
+            input_amount: Uint128::new(10),
+            min_output: Uint128::zero(),
+            expiration: None,
+        };
+        router
+            .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+            .unwrap();
+    }
+    let twap: TwapResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm,
+            &QueryMsg::Twap {
+                token: TokenSelect::Token1,
+                window_seconds: 600 + MAX_PRICE_SNAPSHOTS + 12,
+            },
+        )
+        .unwrap();
+    assert_eq!(twap.window_seconds, 600 + MAX_PRICE_SNAPSHOTS + 12);
+}
+
+#[test]
+fn test_simulate_swap() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(100_000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(100_000),
+    );
+
+    let lp_fee_percent = Decimal::from_str("0.2").unwrap();
+    let protocol_fee_percent = Decimal::from_str("0.1").unwrap();
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        "fee_recipient".to_string(),
+    );
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(100_000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token1.addr(), &allowance_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), token2.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(20000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+
+    let simulation: SimulateSwapResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm,
+            &QueryMsg::SimulateSwap {
+                input_token: TokenSelect::Token1,
+                input_amount: Uint128::new(1000),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        simulation,
+        SimulateSwapResponse {
+            output_amount: Uint128::new(1813),
+            lp_fee_amount: Uint128::new(2),
+            protocol_fee_amount: Uint128::new(1),
+            spot_price: Decimal::from_str("2").unwrap(),
+            price_impact: Decimal::from_ratio(997u128, 10997u128),
+        }
+    );
+
+    // Quote an exact output
+    let reverse: ReverseSimulateResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm,
+            &QueryMsg::ReverseSimulate {
+                output_token: TokenSelect::Token2,
+                output_amount: simulation.output_amount,
+            },
+        )
+        .unwrap();
+    assert_eq!(reverse.input_amount, Uint128::new(1000));
+    assert_eq!(reverse.protocol_fee_amount, Uint128::new(1));
+    assert_eq!(reverse.spot_price, Decimal::from_str("2").unwrap());
+
+    // The simulation matches the executed swap
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(1000),
+        min_output: simulation.output_amount,
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap();
+    assert_eq!(
+        token2.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(80000) + simulation.output_amount
+    );
+    assert_eq!(
+        get_protocol_fees(&router, &amm).token1_amount,
+        simulation.protocol_fee_amount
+    );
+}
+
+#[test]
+fn test_swap_exact_output() {
+    let mut router = mock_app();
+
+    const NATIVE_TOKEN_DENOM: &str = "juno";
+
+    let owner = Addr::unchecked("owner");
+    let funds = coins(100_000, NATIVE_TOKEN_DENOM);
+    router.borrow_mut().init_modules(|router, _, storage| {
+        router.bank.init_balance(storage, &owner, funds).unwrap()
+    });
+
+    let cw20_token = create_cw20(
+        &mut router,
+        &owner,
+        "token".to_string(),
+        "CWTOKEN".to_string(),
+        Uint128::new(100_000),
+    );
+
+    let lp_fee_percent = Decimal::from_str("0.2").unwrap();
+    let protocol_fee_percent = Decimal::from_str("0.1").unwrap();
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Native(NATIVE_TOKEN_DENOM.into()),
+        Denom::Cw20(cw20_token.addr()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        "fee_recipient".to_string(),
+    );
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(100_000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), cw20_token.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &coins(10000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+
+    // Max input is too low to buy the requested output
+    let swap_msg = ExecuteMsg::SwapExactOutput {
+        output_token: TokenSelect::Token1,
+        output_amount: Uint128::new(906),
+        max_input: Uint128::new(999),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::SwapMaxInputError {
+            max: Uint128::new(999),
+            required: Uint128::new(1000),
+        },
+        err.downcast().unwrap()
+    );
+
+    // Buy native with cw20, only the required input is pulled
+    let swap_msg = ExecuteMsg::SwapExactOutput {
+        output_token: TokenSelect::Token1,
+        output_amount: Uint128::new(906),
+        max_input: Uint128::new(1500),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap();
+    let owner_balance = bank_balance(&mut router, &owner, NATIVE_TOKEN_DENOM.to_string());
+    assert_eq!(owner_balance.amount, Uint128::new(90906));
+    let owner_balance = cw20_token.balance(&router, owner.clone()).unwrap();
+    assert_eq!(owner_balance, Uint128::new(89000));
+    assert_eq!(
+        get_protocol_fees(&router, &amm).token2_amount,
+        Uint128::new(1)
+    );
+
+    // Buy cw20 with native, the unused native funds are refunded
+    let swap_msg = ExecuteMsg::SwapExactOutput {
+        output_token: TokenSelect::Token2,
+        output_amount: Uint128::new(1000),
+        max_input: Uint128::new(2000),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg,
+            &coins(2000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+    let owner_balance = bank_balance(&mut router, &owner, NATIVE_TOKEN_DENOM.to_string());
+    assert_eq!(owner_balance.amount, Uint128::new(90906 - 913));
+    let owner_balance = cw20_token.balance(&router, owner.clone()).unwrap();
+    assert_eq!(owner_balance, Uint128::new(90000));
+
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(9094 + 913));
+    assert_eq!(info.token2_reserve, Uint128::new(10999 - 1000));
+
+    // Native funds must match max_input
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg,
+            &coins(1000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::InsufficientFunds {}),
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn test_ownership_transfer() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(5000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(5000),
+    );
+
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        Decimal::from_str("0.3").unwrap(),
+        Decimal::zero(),
+        owner.to_string(),
+    );
+    let new_owner = Addr::unchecked("new_owner");
+
+    // Only the owner can propose
+    let propose_msg = ExecuteMsg::ProposeNewOwner {
+        owner: new_owner.to_string(),
+        expiry: Some(Expiration::AtHeight(router.block_info().height + 10)),
+    };
+    let err = router
+        .execute_contract(new_owner.clone(), amm.clone(), &propose_msg, &[])
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+    // Nothing to accept yet
+    let err = router
+        .execute_contract(
+            new_owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::NoOwnershipProposal {}),
+        err.downcast().unwrap()
+    );
+
+    router
+        .execute_contract(owner.clone(), amm.clone(), &propose_msg, &[])
+        .unwrap();
+    let ownership: OwnershipResponse = router
+        .wrap()
+        .query_wasm_smart(&amm, &QueryMsg::Ownership {})
+        .unwrap();
+    assert_eq!(
+        ownership,
+        OwnershipResponse {
+            owner: Some(owner.to_string()),
+            pending_owner: Some(new_owner.to_string()),
+            pending_expiry: Some(Expiration::AtHeight(router.block_info().height + 10)),
+        }
+    );
+
+    // Only the proposed owner can accept
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+    // The proposal can't be accepted after the expiry
+    router.update_block(|block| block.height += 10);
+    let err = router
+        .execute_contract(
+            new_owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::OwnershipProposalExpired {}),
+        err.downcast().unwrap()
+    );
+
+    // A cancelled proposal can't be accepted
+    let propose_msg = ExecuteMsg::ProposeNewOwner {
+        owner: new_owner.to_string(),
+        expiry: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &propose_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::CancelOwnershipProposal {},
+            &[],
+        )
+        .unwrap();
+    let err = router
+        .execute_contract(
+            new_owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::NoOwnershipProposal {}),
+        err.downcast().unwrap()
+    );
+
+    // The proposed owner accepts and takes over fee administration
+    router
+        .execute_contract(owner.clone(), amm.clone(), &propose_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(
+            new_owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap();
+    let ownership: OwnershipResponse = router
+        .wrap()
+        .query_wasm_smart(&amm, &QueryMsg::Ownership {})
+        .unwrap();
+    assert_eq!(ownership.owner, Some(new_owner.to_string()));
+    assert_eq!(ownership.pending_owner, None);
+
+    let update_config_msg = ExecuteMsg::UpdateConfig {
+        lp_fee_percent: Decimal::from_str("0.2").unwrap(),
+        protocol_fee_percent: Decimal::from_str("0.1").unwrap(),
+        protocol_fee_recipient: new_owner.to_string(),
+        limits: None,
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &update_config_msg, &[])
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+    router
+        .execute_contract(new_owner.clone(), amm.clone(), &update_config_msg, &[])
+        .unwrap();
+
+    // Renouncing removes the owner for good
+    router
+        .execute_contract(
+            new_owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::RenounceOwnership {},
+            &[],
+        )
+        .unwrap();
+    assert_eq!(get_fee(&router, &amm).owner, None);
+    let err = router
+        .execute_contract(new_owner.clone(), amm.clone(), &update_config_msg, &[])
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+}
+
+#[test]
+fn test_single_sided_liquidity() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(100_000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(100_000),
+    );
+
+    let lp_fee_percent = Decimal::from_str("0.2").unwrap();
+    let protocol_fee_percent = Decimal::from_str("0.1").unwrap();
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        "fee_recipient".to_string(),
+    );
+    let info = get_info(&router, &amm);
+    let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(100_000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), token1.addr(), &allowance_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), token2.addr(), &allowance_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), lp_token.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    // Needs an existing pool ratio
+    let add_single_msg = ExecuteMsg::AddLiquiditySingle {
+        token: TokenSelect::Token1,
+        amount: Uint128::new(2000),
+        min_liquidity: Uint128::zero(),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_single_msg, &[])
+        .unwrap_err();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+    assert_eq!(
+        lp_token.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(9000)
+    );
+
+    // 955 token1 are swapped for 869 token2 and deposited with the remaining 1045 token1
+    let add_single_msg = ExecuteMsg::AddLiquiditySingle {
+        token: TokenSelect::Token1,
+        amount: Uint128::new(2000),
+        min_liquidity: Uint128::new(952),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &add_single_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::MinLiquidityError {
+            min_liquidity: Uint128::new(952),
+            liquidity_available: Uint128::new(951),
+        }),
+        err.downcast().unwrap()
+    );
+
+    let add_single_msg = ExecuteMsg::AddLiquiditySingle {
+        token: TokenSelect::Token1,
+        amount: Uint128::new(2000),
+        min_liquidity: Uint128::new(951),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_single_msg, &[])
+        .unwrap();
+    assert_eq!(
+        lp_token.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(9951)
+    );
+    assert_eq!(
+        token1.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(88000)
+    );
+    assert_eq!(
+        token2.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(90000)
+    );
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(12000));
+    assert_eq!(info.token2_reserve, Uint128::new(10000));
+    assert_eq!(info.lp_token_supply, Uint128::new(10951));
+
+    // 868 token2 are withdrawn and 1042 token1 are swapped for another 790 token2
+    let remove_single_msg = ExecuteMsg::RemoveLiquiditySingle {
+        amount: Uint128::new(951),
+        output_token: TokenSelect::Token2,
+        min_output: Uint128::new(1659),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &remove_single_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::SwapMinError {
+            min: Uint128::new(1659),
+            available: Uint128::new(1658),
+        }),
+        err.downcast().unwrap()
+    );
+
+    let remove_single_msg = ExecuteMsg::RemoveLiquiditySingle {
+        amount: Uint128::new(951),
+        output_token: TokenSelect::Token2,
+        min_output: Uint128::new(1658),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &remove_single_msg, &[])
+        .unwrap();
+    assert_eq!(
+        lp_token.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(9000)
+    );
+    assert_eq!(
+        token2.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(91658)
+    );
+    assert_eq!(
+        get_protocol_fees(&router, &amm).token1_amount,
+        Uint128::new(1)
+    );
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(11999));
+    assert_eq!(info.token2_reserve, Uint128::new(8342));
+    assert_eq!(info.lp_token_supply, Uint128::new(10000));
+
+    // Can't withdraw more than the sender's lp balance
+    let remove_single_msg = ExecuteMsg::RemoveLiquiditySingle {
+        amount: Uint128::new(9001),
+        output_token: TokenSelect::Token1,
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &remove_single_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::InsufficientLiquidityError {
+            requested: Uint128::new(9001),
+            available: Uint128::new(9000),
+        }),
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn test_circuit_breaker() {
+    let mut router = mock_app();
+
+    const NATIVE_TOKEN_DENOM: &str = "juno";
+
+    let owner = Addr::unchecked("owner");
+    let funds = coins(100_000, NATIVE_TOKEN_DENOM);
+    router.borrow_mut().init_modules(|router, _, storage| {
+        router.bank.init_balance(storage, &owner, funds).unwrap()
+    });
+
+    let cw20_token = create_cw20(
+        &mut router,
+        &owner,
+        "token".to_string(),
+        "CWTOKEN".to_string(),
+        Uint128::new(100_000),
+    );
+
+    let lp_fee_percent = Decimal::from_str("0.2").unwrap();
+    let protocol_fee_percent = Decimal::from_str("0.1").unwrap();
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Native(NATIVE_TOKEN_DENOM.into()),
+        Denom::Cw20(cw20_token.addr()),
+        lp_fee_percent,
+        protocol_fee_percent,
+        "fee_recipient".to_string(),
+    );
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(100_000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), cw20_token.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &coins(10000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+
+    // No limits by default
+    let limits: LimitsResponse = router
+        .wrap()
+        .query_wasm_smart(&amm, &QueryMsg::Limits {})
+        .unwrap();
+    assert_eq!(
+        limits,
+        LimitsResponse {
+            max_price_impact: None,
+            max_block_reserve_change: None,
+            auto_freeze: false,
+        }
+    );
+
+    let update_limits = |router: &mut App, limits: Limits| {
+        let msg = ExecuteMsg::UpdateConfig {
+            lp_fee_percent,
+            protocol_fee_percent,
+            protocol_fee_recipient: "fee_recipient".to_string(),
+            limits: Some(limits),
+        };
+        router
+            .execute_contract(owner.clone(), amm.clone(), &msg, &[])
+            .unwrap();
+    };
+    let swap_msg = |input_amount: u128| ExecuteMsg::Swap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(input_amount),
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+
+    // Price impact above the limit is rejected
+    update_limits(
+        &mut router,
+        Limits {
+            max_price_impact: Some(Decimal::from_str("0.05").unwrap()),
+            max_block_reserve_change: None,
+            auto_freeze: false,
+        },
+    );
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(1000),
+            &coins(1000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::MaxPriceImpactError {
+            max: Decimal::from_str("0.05").unwrap(),
+            price_impact: Decimal::from_ratio(997u128, 10997u128),
+        },
+        err.downcast().unwrap()
+    );
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(500),
+            &coins(500, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+
+    // Reserve changes add up within a block, the swap of 500 above counts too
+    update_limits(
+        &mut router,
+        Limits {
+            max_price_impact: None,
+            max_block_reserve_change: Some(Decimal::from_str("0.08").unwrap()),
+            auto_freeze: false,
+        },
+    );
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(400),
+            &coins(400, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::MaxBlockReserveChangeError {
+            max: Decimal::from_str("0.08").unwrap(),
+            reserve_change: Decimal::from_str("0.09").unwrap(),
+        },
+        err.downcast().unwrap()
+    );
+
+    // The next block starts over
+    router.update_block(|block| block.height += 1);
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(400),
+            &coins(400, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(10900));
+    assert_eq!(info.token2_reserve, Uint128::new(9178));
+
+    // With auto freeze the trade is not swapped, the pool freezes and the input is returned
+    update_limits(
+        &mut router,
+        Limits {
+            max_price_impact: Some(Decimal::from_str("0.05").unwrap()),
+            max_block_reserve_change: None,
+            auto_freeze: true,
+        },
+    );
+    let native_balance = bank_balance(&mut router, &owner, NATIVE_TOKEN_DENOM.to_string());
+    let res = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(1000),
+            &coins(1000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+    let freeze_event = res.events.iter().find(|e| e.ty == "wasm-freeze").unwrap();
+    assert!(freeze_event
+        .attributes
+        .iter()
+        .any(|a| a.key == "reason" && a.value.starts_with("Max price impact error")));
+    assert_eq!(
+        bank_balance(&mut router, &owner, NATIVE_TOKEN_DENOM.to_string()),
+        native_balance
+    );
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(10900));
+    assert_eq!(info.token2_reserve, Uint128::new(9178));
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(100),
+            &coins(100, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::SwapsPaused {}),
+        err.downcast().unwrap()
+    );
+
+    // Received cw20 tokens are sent back
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::FreezeDeposits { freeze: false },
+            &[],
+        )
+        .unwrap();
+    let cw20_balance = cw20_token.balance(&router, owner.clone()).unwrap();
+    let send_msg = Cw20ExecuteMsg::Send {
+        contract: amm.to_string(),
+        amount: Uint128::new(2000),
+        msg: to_binary(&ReceiveMsg::Swap {
+            min_output: Uint128::zero(),
+            expiration: None,
+        })
+        .unwrap(),
+    };
+    router
+        .execute_contract(owner.clone(), cw20_token.addr(), &send_msg, &[])
+        .unwrap();
+    assert_eq!(
+        cw20_token.balance(&router, owner.clone()).unwrap(),
+        cw20_balance
+    );
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(100),
+            &coins(100, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::SwapsPaused {}),
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn test_pause() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let guardian = Addr::unchecked("guardian");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(50000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(50000),
+    );
+
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        Decimal::from_str("0.3").unwrap(),
+        Decimal::zero(),
+        owner.to_string(),
+    );
+    let lp_token = Cw20Contract(Addr::unchecked(get_info(&router, &amm).lp_token_address));
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(50000),
+        expires: None,
+    };
+    for token in [&token1, &token2, &lp_token] {
+        router
+            .execute_contract(owner.clone(), token.addr(), &allowance_msg, &[])
+            .unwrap();
+    }
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(10000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(100),
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+    let remove_liquidity_msg = ExecuteMsg::RemoveLiquidity {
+        amount: Uint128::new(100),
+        min_token1: Uint128::zero(),
+        min_token2: Uint128::zero(),
+        expiration: None,
+    };
+    let pause_msg = |deposits, swaps, withdrawals| ExecuteMsg::UpdatePause {
+        deposits,
+        swaps,
+        withdrawals,
+    };
+    let get_pause = |router: &App| -> PauseResponse {
+        router
+            .wrap()
+            .query_wasm_smart(&amm, &QueryMsg::Pause {})
+            .unwrap()
+    };
+
+    // Nothing is paused and there is no guardian by default
+    assert_eq!(
+        get_pause(&router),
+        PauseResponse {
+            deposits: false,
+            swaps: false,
+            withdrawals: false,
+            guardian: None,
+        }
+    );
+
+    // Only the owner can pause before a guardian is set
+    let err = router
+        .execute_contract(
+            guardian.clone(),
+            amm.clone(),
+            &pause_msg(None, Some(true), None),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+
+    // Only the owner can set the guardian
+    let update_guardian_msg = ExecuteMsg::UpdateGuardian {
+        guardian: Some(guardian.to_string()),
+    };
+    let err = router
+        .execute_contract(guardian.clone(), amm.clone(), &update_guardian_msg, &[])
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+    router
+        .execute_contract(owner.clone(), amm.clone(), &update_guardian_msg, &[])
+        .unwrap();
+    assert_eq!(get_pause(&router).guardian, Some(guardian.to_string()));
+
+    // Pausing swaps leaves deposits and withdrawals open
+    let res = router
+        .execute_contract(
+            guardian.clone(),
+            amm.clone(),
+            &pause_msg(None, Some(true), None),
+            &[],
+        )
+        .unwrap();
+    let pause_event = res.events.iter().find(|e| e.ty == "wasm-pause").unwrap();
+    assert!(pause_event
+        .attributes
+        .iter()
+        .any(|a| a.key == "swaps" && a.value == "true"));
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::SwapsPaused {}),
+        err.downcast().unwrap()
+    );
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap();
+
+    // Single sided withdrawals swap, so they are paused with swaps
+    let remove_liquidity_single_msg = ExecuteMsg::RemoveLiquiditySingle {
+        amount: Uint128::new(100),
+        output_token: TokenSelect::Token1,
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &remove_liquidity_single_msg,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::SwapsPaused {}),
+        err.downcast().unwrap()
+    );
+
+    // The guardian can pause withdrawals and deposits but not unpause anything
+    router
+        .execute_contract(
+            guardian.clone(),
+            amm.clone(),
+            &pause_msg(Some(true), None, Some(true)),
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        get_pause(&router),
+        PauseResponse {
+            deposits: true,
+            swaps: true,
+            withdrawals: true,
+            guardian: Some(guardian.to_string()),
+        }
+    );
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::DepositsPaused {}),
+        err.downcast().unwrap()
+    );
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::WithdrawalsPaused {}),
+        err.downcast().unwrap()
+    );
+    let send_msg = Cw20ExecuteMsg::Send {
+        contract: amm.to_string(),
+        amount: Uint128::new(100),
+        msg: to_binary(&ReceiveMsg::RemoveLiquidity {
+            min_token1: Uint128::zero(),
+            min_token2: Uint128::zero(),
+            expiration: None,
+        })
+        .unwrap(),
+    };
+    let err = router
+        .execute_contract(owner.clone(), lp_token.addr(), &send_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::WithdrawalsPaused {}),
+        err.downcast().unwrap()
+    );
+    let err = router
+        .execute_contract(
+            guardian.clone(),
+            amm.clone(),
+            &pause_msg(None, None, Some(false)),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::UnauthorizedUnpause {},
+        err.downcast().unwrap()
+    );
+
+    // The owner unpauses withdrawals so LPs can exit while the rest stays paused
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &pause_msg(None, None, Some(false)),
+            &[],
+        )
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap();
+
+    // FreezeDeposits still toggles deposits and swaps together
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::FreezeDeposits { freeze: false },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        get_pause(&router),
+        PauseResponse {
+            deposits: false,
+            swaps: false,
+            withdrawals: false,
+            guardian: Some(guardian.to_string()),
+        }
+    );
+    router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap();
+
+    // Removing the guardian revokes its access
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::UpdateGuardian { guardian: None },
+            &[],
+        )
+        .unwrap();
+    let err = router
+        .execute_contract(
+            guardian.clone(),
+            amm.clone(),
+            &pause_msg(Some(true), None, None),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+}
+
+#[test]
+fn test_stable_swap() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(10_000_000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(10_000_000),
+    );
+
+    let product_amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        Decimal::from_str("0.3").unwrap(),
+        Decimal::zero(),
+        owner.to_string(),
+    );
+
+    let cw20_id = router.store_code(contract_cw20());
+    let amm_id = router.store_code(contract_amm());
+    let mut msg = InstantiateMsg {
+        token1_denom: Denom::Cw20(token1.addr()),
+        token2_denom: Denom::Cw20(token2.addr()),
+        owner: Some(owner.to_string()),
+        lp_fee_percent: Decimal::from_str("0.3").unwrap(),
+        protocol_fee_percent: Decimal::zero(),
+        protocol_fee_recipient: owner.to_string(),
+        lp_token_code_id: cw20_id,
+        amp: Some(0),
+        concentrated: None,
+    };
+    let err = router
+        .instantiate_contract(amm_id, owner.clone(), &msg, &[], "amm", None)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::InvalidAmp { amp: 0 }, err);
+    msg.amp = Some(100);
+    let stable_amm = router
+        .instantiate_contract(amm_id, owner.clone(), &msg, &[], "amm", None)
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(1_000_000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(1_000_000),
+        expiration: None,
+    };
+    for amm in [&product_amm, &stable_amm] {
+        let lp_token = Cw20Contract(Addr::unchecked(get_info(&router, amm).lp_token_address));
+        for token in [&token1, &token2, &lp_token] {
+            let msg = Cw20ExecuteMsg::IncreaseAllowance {
+                spender: amm.to_string(),
+                amount: Uint128::new(5_000_000),
+                expires: None,
+            };
+            router
+                .execute_contract(owner.clone(), token.addr(), &msg, &[])
+                .unwrap();
+        }
+        router
+            .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+            .unwrap();
+    }
+
+    // The stable pool quotes close to 1:1, with less slippage than the product pool
+    let simulate_msg = QueryMsg::SimulateSwap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(100_000),
+    };
+    let product_quote: SimulateSwapResponse = router
+        .wrap()
+        .query_wasm_smart(&product_amm, &simulate_msg)
+        .unwrap();
+    let stable_quote: SimulateSwapResponse = router
+        .wrap()
+        .query_wasm_smart(&stable_amm, &simulate_msg)
+        .unwrap();
+    assert_eq!(product_quote.output_amount, Uint128::new(90_661));
+    assert!(stable_quote.output_amount > Uint128::new(99_500));
+    assert!(stable_quote.output_amount < Uint128::new(99_700));
+    assert_eq!(stable_quote.spot_price, Decimal::one());
+    assert!(stable_quote.price_impact < product_quote.price_impact);
+
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(100_000),
+        min_output: stable_quote.output_amount,
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), stable_amm.clone(), &swap_msg, &[])
+        .unwrap();
+    let info = get_info(&router, &stable_amm);
+    assert_eq!(info.token1_reserve, Uint128::new(1_100_000));
+    assert_eq!(
+        info.token2_reserve,
+        Uint128::new(1_000_000) - stable_quote.output_amount
+    );
+
+    // The reverse quote buys at least the requested output
+    let reverse_quote: ReverseSimulateResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &stable_amm,
+            &QueryMsg::ReverseSimulate {
+                output_token: TokenSelect::Token1,
+                output_amount: Uint128::new(50_000),
+            },
+        )
+        .unwrap();
+    let forward_quote: SimulateSwapResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &stable_amm,
+            &QueryMsg::SimulateSwap {
+                input_token: TokenSelect::Token2,
+                input_amount: reverse_quote.input_amount,
+            },
+        )
+        .unwrap();
+    assert!(forward_quote.output_amount >= Uint128::new(50_000));
+    let swap_exact_output_msg = ExecuteMsg::SwapExactOutput {
+        output_token: TokenSelect::Token1,
+        output_amount: Uint128::new(50_000),
+        max_input: reverse_quote.input_amount,
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            stable_amm.clone(),
+            &swap_exact_output_msg,
+            &[],
+        )
+        .unwrap();
+
+    // Outputs beyond the reserve are rejected instead of overflowing
+    let err = router
+        .wrap()
+        .query_wasm_smart::<ReverseSimulateResponse>(
+            &stable_amm,
+            &QueryMsg::ReverseSimulate {
+                output_token: TokenSelect::Token1,
+                output_amount: Uint128::MAX,
+            },
+        )
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Insufficient liquidity for the requested output"));
+    let swap_exact_output_msg = ExecuteMsg::SwapExactOutput {
+        output_token: TokenSelect::Token1,
+        output_amount: Uint128::MAX,
+        max_input: Uint128::MAX,
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            stable_amm.clone(),
+            &swap_exact_output_msg,
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Std(StdError::generic_err(
+            "Insufficient liquidity for the requested output"
+        ))
+    );
+
+    // Near the peg a single sided deposit of 200k tokens is worth close to 10% of the pool
+    let add_single_msg = ExecuteMsg::AddLiquiditySingle {
+        token: TokenSelect::Token2,
+        amount: Uint128::new(200_000),
+        min_liquidity: Uint128::new(99_500),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), stable_amm.clone(), &add_single_msg, &[])
+        .unwrap();
+
+    let get_amp = |router: &App| -> AmpResponse {
+        router
+            .wrap()
+            .query_wasm_smart(&stable_amm, &QueryMsg::Amp {})
+            .unwrap()
+    };
+    let now = router.block_info().time.seconds();
+    assert_eq!(
+        get_amp(&router),
+        AmpResponse {
+            amp: 100,
+            initial_amp: 100,
+            target_amp: 100,
+            start_time: now,
+            end_time: now,
+        }
+    );
+    router
+        .wrap()
+        .query_wasm_smart::<AmpResponse>(&product_amm, &QueryMsg::Amp {})
+        .unwrap_err();
+
+    // Only the owner ramps stable pools, slowly and by a bounded factor
+    let ramp_msg = |target, end_time| ExecuteMsg::RampAmp { target, end_time };
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            product_amm.clone(),
+            &ramp_msg(200, now + 86_400),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::NotStableSwapPool {}, err);
+    let err = router
+        .execute_contract(
+            Addr::unchecked("anyone"),
+            stable_amm.clone(),
+            &ramp_msg(200, now + 86_400),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            stable_amm.clone(),
+            &ramp_msg(200, now + 60),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::AmpRampTooShort {
+            min_end_time: now + 86_400
+        },
+        err
+    );
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            stable_amm.clone(),
+            &ramp_msg(1_001, now + 86_400),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::AmpChangeTooLarge {
+            current: 100,
+            target: 1_001
+        },
+        err
+    );
+
+    router
+        .execute_contract(
+            owner.clone(),
+            stable_amm.clone(),
+            &ramp_msg(200, now + 2 * 86_400),
+            &[],
+        )
+        .unwrap();
+    router.update_block(|block| block.time = block.time.plus_seconds(86_400));
+    assert_eq!(get_amp(&router).amp, 150);
+
+    // Stopping keeps the amplification reached so far
+    router
+        .execute_contract(
+            owner.clone(),
+            stable_amm.clone(),
+            &ExecuteMsg::StopRampAmp {},
+            &[],
+        )
+        .unwrap();
+    router.update_block(|block| block.time = block.time.plus_seconds(86_400));
+    let amp = get_amp(&router);
+    assert_eq!(amp.amp, 150);
+    assert_eq!(amp.target_amp, 150);
+}
+
+// Flash swap borrower, the callback message picks what it does with the lent tokens
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum BorrowerMsg {
+    Repay {
+        pool: String,
+        denom: Denom,
+        amount: Uint128,
+    },
+    CallPool {
+        pool: String,
+        msg: Binary,
+    },
+}
+
+fn borrower_execute(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: BorrowerMsg,
+) -> StdResult<Response> {
+    let msg: CosmosMsg = match msg {
+        BorrowerMsg::Repay {
+            pool,
+            denom: Denom::Native(denom),
+            amount,
+        } => BankMsg::Send {
+            to_address: pool,
+            amount: coins(amount.u128(), denom),
+        }
+        .into(),
+        BorrowerMsg::Repay {
+            pool,
+            denom: Denom::Cw20(token),
+            amount,
+        } => WasmMsg::Execute {
+            contract_addr: token.into_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: pool,
+                amount,
+            })?,
+            funds: vec![],
+        }
+        .into(),
+        BorrowerMsg::CallPool { pool, msg } => WasmMsg::Execute {
+            contract_addr: pool,
+            msg,
+            funds: vec![],
+        }
+        .into(),
+    };
+    Ok(Response::new().add_message(msg))
+}
+
+fn borrower_instantiate(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: Empty,
+) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+fn borrower_query(_deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+    Err(StdError::generic_err("No queries"))
+}
+
+#[test]
+fn test_flash_swap() {
+    let mut router = mock_app();
+
+    const NATIVE_TOKEN_DENOM: &str = "juno";
+
+    let owner = Addr::unchecked("owner");
+    router.borrow_mut().init_modules(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &owner, coins(200_000, NATIVE_TOKEN_DENOM))
+            .unwrap()
+    });
+    let cw20_token = create_cw20(
+        &mut router,
+        &owner,
+        "token".to_string(),
+        "CWTOKEN".to_string(),
+        Uint128::new(200_000),
+    );
+
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Native(NATIVE_TOKEN_DENOM.into()),
+        Denom::Cw20(cw20_token.addr()),
+        Decimal::from_str("0.2").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        "fee_recipient".to_string(),
+    );
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(100_000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), cw20_token.addr(), &allowance_msg, &[])
+        .unwrap();
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(100_000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(100_000),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &coins(100_000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+
+    // The borrower holds enough to pay the fees
+    let borrower_id = router.store_code(Box::new(ContractWrapper::new(
+        borrower_execute,
+        borrower_instantiate,
+        borrower_query,
+    )));
+    let borrower = router
+        .instantiate_contract(borrower_id, owner.clone(), &Empty {}, &[], "borrower", None)
+        .unwrap();
+    router
+        .send_tokens(
+            owner.clone(),
+            borrower.clone(),
+            &coins(1_000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+    let transfer_msg = Cw20ExecuteMsg::Transfer {
+        recipient: borrower.to_string(),
+        amount: Uint128::new(1_000),
+    };
+    router
+        .execute_contract(owner.clone(), cw20_token.addr(), &transfer_msg, &[])
+        .unwrap();
+
+    let flash_swap_msg = |token, amount: u128, callback_msg: &BorrowerMsg| ExecuteMsg::FlashSwap {
+        token,
+        amount: Uint128::new(amount),
+        callback_msg: to_binary(callback_msg).unwrap(),
+    };
+    let repay_msg = |denom: Denom, amount: u128| BorrowerMsg::Repay {
+        pool: amm.to_string(),
+        denom,
+        amount: Uint128::new(amount),
+    };
+    let native_denom = Denom::Native(NATIVE_TOKEN_DENOM.into());
+
+    // 10000 lent for a 0.3% fee, of which 0.1% goes to the protocol
+    router
+        .execute_contract(
+            borrower.clone(),
+            amm.clone(),
+            &flash_swap_msg(
+                TokenSelect::Token1,
+                10_000,
+                &repay_msg(native_denom.clone(), 10_030),
+            ),
+            &[],
+        )
+        .unwrap();
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(100_020));
+    assert_eq!(info.token2_reserve, Uint128::new(100_000));
+    assert_eq!(
+        get_protocol_fees(&router, &amm).token1_amount,
+        Uint128::new(10)
+    );
+    assert_eq!(
+        bank_balance(&mut router, &borrower, NATIVE_TOKEN_DENOM.to_string()).amount,
+        Uint128::new(970)
+    );
+    assert_eq!(
+        bank_balance(&mut router, &amm, NATIVE_TOKEN_DENOM.to_string()).amount,
+        Uint128::new(100_030)
+    );
+
+    // Repaying less than the fee reverts the whole flash swap
+    let err = router
+        .execute_contract(
+            borrower.clone(),
+            amm.clone(),
+            &flash_swap_msg(
+                TokenSelect::Token2,
+                10_000,
+                &repay_msg(Denom::Cw20(cw20_token.addr()), 10_029),
+            ),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::FlashSwapNotRepaid {
+            required: Uint128::new(10_030),
+            repaid: Uint128::new(10_029),
+        },
+        err
+    );
+    assert_eq!(
+        cw20_token.balance(&router, borrower.clone()).unwrap(),
+        Uint128::new(1_000)
+    );
+    assert_eq!(get_info(&router, &amm), info);
+
+    // The pool can't be used while the lent tokens are out
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token2,
+        input_amount: Uint128::new(100),
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+    let call_pool_msg = BorrowerMsg::CallPool {
+        pool: amm.to_string(),
+        msg: to_binary(&swap_msg).unwrap(),
+    };
+    let err = router
+        .execute_contract(
+            borrower.clone(),
+            amm.clone(),
+            &flash_swap_msg(TokenSelect::Token1, 10_000, &call_pool_msg),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::FlashSwapInProgress {}, err);
+
+    // Only the pool completes flash swaps, and it can't lend its whole reserve
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::CompleteFlashSwap {},
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let err = router
+        .execute_contract(
+            borrower.clone(),
+            amm.clone(),
+            &flash_swap_msg(
+                TokenSelect::Token2,
+                100_000,
+                &repay_msg(Denom::Cw20(cw20_token.addr()), 100_300),
+            ),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::Pool(PoolError::InsufficientLiquidityError {
+            requested: Uint128::new(100_000),
+            available: Uint128::new(100_000),
+        }),
+        err
+    );
+}
+
+#[test]
+fn test_concentrated_liquidity() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let lp = Addr::unchecked("provider");
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(10_000_000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(10_000_000),
+    );
+
+    let cw20_id = router.store_code(contract_cw20());
+    let amm_id = router.store_code(contract_amm());
+    let mut msg = InstantiateMsg {
+        token1_denom: Denom::Cw20(token1.addr()),
+        token2_denom: Denom::Cw20(token2.addr()),
+        owner: Some(owner.to_string()),
+        lp_fee_percent: Decimal::from_str("0.3").unwrap(),
+        protocol_fee_percent: Decimal::zero(),
+        protocol_fee_recipient: owner.to_string(),
+        lp_token_code_id: cw20_id,
+        amp: Some(100),
+        concentrated: Some(ConcentratedParams {
+            tick_spacing: 10,
+            initial_tick: 0,
+        }),
+    };
+    let err = router
+        .instantiate_contract(amm_id, owner.clone(), &msg, &[], "amm", None)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::ConflictingPoolTypes {}, err);
+    msg.amp = None;
+    let amm = router
+        .instantiate_contract(amm_id, owner.clone(), &msg, &[], "amm", None)
+        .unwrap();
+
+    for user in [&owner, &lp] {
+        for token in [&token1, &token2] {
+            if user != &owner {
+                let msg = Cw20ExecuteMsg::Transfer {
+                    recipient: user.to_string(),
+                    amount: Uint128::new(1_000_000),
+                };
+                router
+                    .execute_contract(owner.clone(), token.addr(), &msg, &[])
+                    .unwrap();
+            }
+            let msg = Cw20ExecuteMsg::IncreaseAllowance {
+                spender: amm.to_string(),
+                amount: Uint128::new(5_000_000),
+                expires: None,
+            };
+            router
+                .execute_contract(user.clone(), token.addr(), &msg, &[])
+                .unwrap();
+        }
+    }
+
+    // Liquidity is only added through positions
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(1_000),
+        min_liquidity: Uint128::zero(),
+        token2_amount: Uint128::new(1_000),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::ConcentratedPoolUnsupported {}, err);
+
+    let create_position =
+        |lower_tick, upper_tick, token1_amount, token2_amount| ExecuteMsg::CreatePosition {
+            lower_tick,
+            upper_tick,
+            token1_amount: Uint128::new(token1_amount),
+            token2_amount: Uint128::new(token2_amount),
+            min_liquidity: Uint128::zero(),
+            expiration: None,
+        };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &create_position(-55, 60, 1, 1),
+            &[],
+        )
+        .unwrap_err();
+    // A wide position from the owner and a narrow one from the lp around the price, and one
+    // above the price which only holds token1
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &create_position(-600, 600, 1_000_000, 1_000_000),
+            &[],
+        )
+        .unwrap();
+    router
+        .execute_contract(
+            lp.clone(),
+            amm.clone(),
+            &create_position(-60, 60, 100_000, 100_000),
+            &[],
+        )
+        .unwrap();
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &create_position(600, 1200, 100_000, 100_000),
+            &[],
+        )
+        .unwrap();
+
+    let get_position = |router: &App, id: u64| -> PositionResponse {
+        router
+            .wrap()
+            .query_wasm_smart(&amm, &QueryMsg::Position { id })
+            .unwrap()
+    };
+    let get_ticks = |router: &App, start_after, limit| -> TicksResponse {
+        router
+            .wrap()
+            .query_wasm_smart(&amm, &QueryMsg::Ticks { start_after, limit })
+            .unwrap()
+    };
+    let wide = get_position(&router, 1);
+    let narrow = get_position(&router, 2);
+    let above = get_position(&router, 3);
+    assert_eq!(narrow.owner, lp.to_string());
+    assert_eq!(above.token2_amount, Uint128::zero());
+    assert!(above.token1_amount > Uint128::new(99_990));
+    // A tenth of the tokens in a tenth of the range gives the narrow position about the
+    // liquidity of the wide one
+    assert!(narrow.liquidity > wide.liquidity.multiply_ratio(95u128, 100u128));
+    let info = get_info(&router, &amm);
+    assert_eq!(
+        info.token1_reserve,
+        wide.token1_amount + narrow.token1_amount + above.token1_amount + Uint128::new(3)
+    );
+
+    let ticks = get_ticks(&router, None, None);
+    assert_eq!(
+        ticks.ticks.iter().map(|t| t.tick).collect::<Vec<_>>(),
+        vec![-600, -60, 60, 600, 1200]
+    );
+    assert_eq!(ticks.ticks[3].liquidity_lower, above.liquidity);
+    assert_eq!(ticks.ticks[3].liquidity_upper, wide.liquidity);
+    assert_eq!(ticks.current_tick, 0);
+    assert_eq!(ticks.liquidity, wide.liquidity + narrow.liquidity);
+    let ticks = get_ticks(&router, Some(-60), Some(2));
+    assert_eq!(
+        ticks.ticks.iter().map(|t| t.tick).collect::<Vec<_>>(),
+        vec![60, 600]
+    );
+
+    // Within the range of both positions the price barely moves
+    let swap_msg = |input_token, input_amount| ExecuteMsg::Swap {
+        input_token,
+        input_amount: Uint128::new(input_amount),
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+    let simulation: SimulateSwapResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm,
+            &QueryMsg::SimulateSwap {
+                input_token: TokenSelect::Token1,
+                input_amount: Uint128::new(50_000),
+            },
+        )
+        .unwrap();
+    assert_eq!(simulation.spot_price, Decimal::one());
+    assert_eq!(simulation.lp_fee_amount, Uint128::new(150));
+    assert!(simulation.output_amount > Uint128::new(49_750));
+    assert!(simulation.output_amount < Uint128::new(49_850));
+    let token2_balance = token2.balance(&router, owner.clone()).unwrap();
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(TokenSelect::Token1, 50_000),
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        token2.balance(&router, owner.clone()).unwrap(),
+        token2_balance + simulation.output_amount
+    );
+
+    // A larger swap leaves the narrow range, only the wide position is left in range
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(TokenSelect::Token1, 300_000),
+            &[],
+        )
+        .unwrap();
+    let ticks = get_ticks(&router, None, None);
+    assert!(ticks.current_tick < -60);
+    assert_eq!(ticks.liquidity, wide.liquidity);
+
+    // The lp earned fees only in token1 and only while in range
+    let narrow = get_position(&router, 2);
+    assert_eq!(narrow.tokens_owed2, Uint128::zero());
+    assert!(narrow.tokens_owed1 > Uint128::new(300));
+    assert_eq!(narrow.token2_amount, Uint128::zero());
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::WithdrawPosition {
+                id: 2,
+                liquidity: Uint128::zero(),
+                min_token1: Uint128::zero(),
+                min_token2: Uint128::zero(),
+                expiration: None,
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let token1_balance = token1.balance(&router, lp.clone()).unwrap();
+    router
+        .execute_contract(
+            lp.clone(),
+            amm.clone(),
+            &ExecuteMsg::WithdrawPosition {
+                id: 2,
+                liquidity: Uint128::zero(),
+                min_token1: Uint128::zero(),
+                min_token2: Uint128::zero(),
+                expiration: None,
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        token1.balance(&router, lp.clone()).unwrap(),
+        token1_balance + narrow.tokens_owed1
+    );
+    assert_eq!(get_position(&router, 2).tokens_owed1, Uint128::zero());
+
+    // Swapping back crosses into the narrow range again
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(TokenSelect::Token2, 400_000),
+            &[],
+        )
+        .unwrap();
+    let ticks = get_ticks(&router, None, None);
+    assert!(ticks.current_tick >= 0 && ticks.current_tick < 60);
+    assert_eq!(ticks.liquidity, wide.liquidity + narrow.liquidity);
+
+    // Withdrawing everything removes the positions, the pool keeps at least what it owes
+    for (user, id) in [(&lp, 2), (&owner, 1), (&owner, 3)] {
+        let position = get_position(&router, id);
+        router
+            .execute_contract(
+                user.clone(),
+                amm.clone(),
+                &ExecuteMsg::WithdrawPosition {
+                    id,
+                    liquidity: position.liquidity,
+                    min_token1: position.token1_amount + position.tokens_owed1,
+                    min_token2: position.token2_amount + position.tokens_owed2,
+                    expiration: None,
+                },
+                &[],
+            )
+            .unwrap();
+        router
+            .wrap()
+            .query_wasm_smart::<PositionResponse>(&amm, &QueryMsg::Position { id })
+            .unwrap_err();
+    }
+    let ticks = get_ticks(&router, None, None);
+    assert!(ticks.ticks.is_empty());
+    assert!(ticks.liquidity.is_zero());
+    let info = get_info(&router, &amm);
+    assert!(info.token1_reserve < Uint128::new(10));
+    assert!(info.token2_reserve < Uint128::new(10));
+    assert_eq!(
+        token1.balance(&router, amm.clone()).unwrap(),
+        info.token1_reserve
+    );
+    assert_eq!(
+        token2.balance(&router, amm.clone()).unwrap(),
+        info.token2_reserve
+    );
+
+    // Swaps fail without liquidity
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(TokenSelect::Token1, 1_000),
+            &[],
+        )
+        .unwrap_err();
 }
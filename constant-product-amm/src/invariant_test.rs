@@ -0,0 +1,330 @@
+#![cfg(test)]
+
+use std::borrow::BorrowMut;
+use std::str::FromStr;
+
+use cosmwasm_std::{coins, Addr, Coin, Decimal, Uint128, Uint256};
+use cw20::{Cw20Contract, Cw20ExecuteMsg, Denom};
+use cw_multi_test::{App, Executor};
+use proptest::prelude::*;
+
+use crate::integration_test::{create_amm, create_cw20, mock_app};
+use crate::msg::{ExecuteMsg, InfoResponse, ProtocolFeesResponse, QueryMsg, TokenSelect};
+
+const NATIVE_TOKEN_DENOM: &str = "juno";
+const USERS: usize = 3;
+const USER_BALANCE: u128 = 1_000_000;
+
+#[derive(Clone, Debug)]
+enum Action {
+    AddLiquidity {
+        user: usize,
+        token1_amount: u128,
+        token2_amount: u128,
+    },
+    // Burns percent of the user's lp tokens
+    RemoveLiquidity {
+        user: usize,
+        percent: u8,
+    },
+    Swap {
+        user: usize,
+        input_token: TokenSelect,
+        input_amount: u128,
+    },
+}
+
+fn action_strategy() -> impl Strategy<Value = Action> {
+    let token = prop_oneof![Just(TokenSelect::Token1), Just(TokenSelect::Token2)];
+    prop_oneof![
+        (0..USERS, 1..200_000u128, 1..200_000u128).prop_map(
+            |(user, token1_amount, token2_amount)| Action::AddLiquidity {
+                user,
+                token1_amount,
+                token2_amount,
+            }
+        ),
+        (0..USERS, 1..=100u8).prop_map(|(user, percent)| Action::RemoveLiquidity { user, percent }),
+        (0..USERS, token, 1..200_000u128).prop_map(|(user, input_token, input_amount)| {
+            Action::Swap {
+                user,
+                input_token,
+                input_amount,
+            }
+        }),
+    ]
+}
+
+struct Pool {
+    router: App,
+    amm: Addr,
+    lp_token: Cw20Contract,
+    token1: Denom,
+    token2: Denom,
+    users: Vec<Addr>,
+}
+
+impl Pool {
+    // A pool with liquidity from the first user, token1 is native when native_pair is set
+    fn new(native_pair: bool) -> Self {
+        let mut router = mock_app();
+        let owner = Addr::unchecked("owner");
+        let users: Vec<Addr> = (0..USERS)
+            .map(|i| Addr::unchecked(format!("user{}", i)))
+            .collect();
+
+        let mut cw20_tokens = vec![];
+        for (name, symbol) in [("token1", "TOKENONE"), ("token2", "TOKENTWO")] {
+            if name == "token1" && native_pair {
+                continue;
+            }
+            cw20_tokens.push(create_cw20(
+                &mut router,
+                &owner,
+                name.to_string(),
+                symbol.to_string(),
+                Uint128::new(USER_BALANCE * USERS as u128),
+            ));
+        }
+        let mut denoms: Vec<Denom> = cw20_tokens
+            .iter()
+            .map(|token| Denom::Cw20(token.addr()))
+            .collect();
+        if native_pair {
+            denoms.insert(0, Denom::Native(NATIVE_TOKEN_DENOM.into()));
+        }
+
+        let amm = create_amm(
+            &mut router,
+            &owner,
+            denoms[0].clone(),
+            denoms[1].clone(),
+            Decimal::from_str("0.2").unwrap(),
+            Decimal::from_str("0.1").unwrap(),
+            "fee_recipient".to_string(),
+        );
+        let info: InfoResponse = router
+            .wrap()
+            .query_wasm_smart(&amm, &QueryMsg::Info {})
+            .unwrap();
+        let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+
+        for user in &users {
+            if native_pair {
+                router.borrow_mut().init_modules(|router, _, storage| {
+                    router
+                        .bank
+                        .init_balance(storage, user, coins(USER_BALANCE, NATIVE_TOKEN_DENOM))
+                        .unwrap()
+                });
+            }
+            for token in &cw20_tokens {
+                let transfer_msg = Cw20ExecuteMsg::Transfer {
+                    recipient: user.to_string(),
+                    amount: Uint128::new(USER_BALANCE),
+                };
+                router
+                    .execute_contract(owner.clone(), token.addr(), &transfer_msg, &[])
+                    .unwrap();
+            }
+            let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+                spender: amm.to_string(),
+                amount: Uint128::new(u128::MAX),
+                expires: None,
+            };
+            for token in cw20_tokens.iter().chain([&lp_token]) {
+                router
+                    .execute_contract(user.clone(), token.addr(), &allowance_msg, &[])
+                    .unwrap();
+            }
+        }
+
+        let mut pool = Pool {
+            router,
+            amm,
+            lp_token,
+            token1: denoms[0].clone(),
+            token2: denoms[1].clone(),
+            users,
+        };
+        pool.execute(&Action::AddLiquidity {
+            user: 0,
+            token1_amount: 100_000,
+            token2_amount: 100_000,
+        })
+        .unwrap();
+        pool
+    }
+
+    fn info(&self) -> InfoResponse {
+        self.router
+            .wrap()
+            .query_wasm_smart(&self.amm, &QueryMsg::Info {})
+            .unwrap()
+    }
+
+    fn protocol_fees(&self) -> ProtocolFeesResponse {
+        self.router
+            .wrap()
+            .query_wasm_smart(&self.amm, &QueryMsg::ProtocolFees {})
+            .unwrap()
+    }
+
+    fn balance(&self, denom: &Denom, addr: &Addr) -> Uint128 {
+        match denom {
+            Denom::Native(denom) => {
+                self.router
+                    .wrap()
+                    .query_balance(addr.to_string(), denom)
+                    .unwrap()
+                    .amount
+            }
+            Denom::Cw20(token) => Cw20Contract(token.clone())
+                .balance(&self.router, addr)
+                .unwrap(),
+        }
+    }
+
+    fn native_funds(denom: &Denom, amount: u128) -> Vec<Coin> {
+        match denom {
+            Denom::Native(denom) => coins(amount, denom),
+            Denom::Cw20(_) => vec![],
+        }
+    }
+
+    // Errors are expected, e.g. for a user without enough tokens, panics are not
+    fn execute(&mut self, action: &Action) -> Result<(), String> {
+        let (user, msg, funds) = match action {
+            Action::AddLiquidity {
+                user,
+                token1_amount,
+                token2_amount,
+            } => (
+                *user,
+                ExecuteMsg::AddLiquidity {
+                    token1_amount: Uint128::new(*token1_amount),
+                    token2_amount: Uint128::new(*token2_amount),
+                    min_liquidity: Uint128::zero(),
+                    expiration: None,
+                },
+                Self::native_funds(&self.token1, *token1_amount),
+            ),
+            Action::RemoveLiquidity { user, percent } => {
+                let lp_balance = self
+                    .lp_token
+                    .balance(&self.router, self.users[*user].clone())
+                    .unwrap();
+                (
+                    *user,
+                    ExecuteMsg::RemoveLiquidity {
+                        amount: lp_balance.multiply_ratio(*percent as u128, 100u128),
+                        min_token1: Uint128::zero(),
+                        min_token2: Uint128::zero(),
+                        expiration: None,
+                    },
+                    vec![],
+                )
+            }
+            Action::Swap {
+                user,
+                input_token,
+                input_amount,
+            } => {
+                let input_denom = match input_token {
+                    TokenSelect::Token1 => &self.token1,
+                    TokenSelect::Token2 => &self.token2,
+                };
+                (
+                    *user,
+                    ExecuteMsg::Swap {
+                        input_token: input_token.clone(),
+                        input_amount: Uint128::new(*input_amount),
+                        min_output: Uint128::zero(),
+                        expiration: None,
+                    },
+                    Self::native_funds(input_denom, *input_amount),
+                )
+            }
+        };
+        self.router
+            .execute_contract(self.users[user].clone(), self.amm.clone(), &msg, &funds)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+// Runs the actions and checks the pool invariants after every step
+fn check_invariants(native_pair: bool, actions: Vec<Action>) -> Result<(), TestCaseError> {
+    let mut pool = Pool::new(native_pair);
+    for action in actions {
+        let before = pool.info();
+        let user = match &action {
+            Action::AddLiquidity { user, .. }
+            | Action::RemoveLiquidity { user, .. }
+            | Action::Swap { user, .. } => pool.users[*user].clone(),
+        };
+        let user_token1 = pool.balance(&pool.token1, &user);
+        let user_token2 = pool.balance(&pool.token2, &user);
+        let lp_balance = pool.lp_token.balance(&pool.router, user.clone()).unwrap();
+
+        let succeeded = pool.execute(&action).is_ok();
+        let after = pool.info();
+
+        // The pool holds exactly its reserves plus the uncollected protocol fees
+        let protocol_fees = pool.protocol_fees();
+        prop_assert_eq!(
+            pool.balance(&pool.token1, &pool.amm),
+            after.token1_reserve + protocol_fees.token1_amount
+        );
+        prop_assert_eq!(
+            pool.balance(&pool.token2, &pool.amm),
+            after.token2_reserve + protocol_fees.token2_amount
+        );
+
+        if !succeeded {
+            prop_assert_eq!(&before, &after);
+            continue;
+        }
+        match action {
+            // Fees stay in the reserves, so k can only grow
+            Action::Swap { .. } => {
+                prop_assert!(
+                    after.token1_reserve.full_mul(after.token2_reserve)
+                        >= before.token1_reserve.full_mul(before.token2_reserve)
+                );
+            }
+            // A withdrawal never pays more than the pro-rata share of the reserves
+            Action::RemoveLiquidity { .. } => {
+                let burned =
+                    lp_balance - pool.lp_token.balance(&pool.router, user.clone()).unwrap();
+                let received1 = pool.balance(&pool.token1, &user) - user_token1;
+                let received2 = pool.balance(&pool.token2, &user) - user_token2;
+                let share = |reserve: Uint128| {
+                    burned.full_mul(reserve) / Uint256::from(before.lp_token_supply)
+                };
+                prop_assert!(Uint256::from(received1) <= share(before.token1_reserve));
+                prop_assert!(Uint256::from(received2) <= share(before.token2_reserve));
+                prop_assert_eq!(after.lp_token_supply, before.lp_token_supply - burned);
+            }
+            Action::AddLiquidity { .. } => {
+                prop_assert!(after.lp_token_supply > before.lp_token_supply);
+            }
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    // Every case runs the contracts in cw-multi-test, keep the count low
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn test_cw20_pair_invariants(actions in prop::collection::vec(action_strategy(), 1..20)) {
+        check_invariants(false, actions)?;
+    }
+
+    #[test]
+    fn test_native_pair_invariants(actions in prop::collection::vec(action_strategy(), 1..20)) {
+        check_invariants(true, actions)?;
+    }
+}
@@ -1,5 +1,10 @@
+pub mod concentrated;
 pub mod contract;
 pub mod error;
+mod event_test;
 mod integration_test;
+mod invariant_test;
 pub mod msg;
+pub mod oracle;
+pub mod stableswap;
 pub mod state;
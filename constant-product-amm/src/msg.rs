@@ -1,9 +1,11 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Decimal, Uint128};
+use cosmwasm_std::{Binary, Decimal, Decimal256, Uint128, Uint256};
 
-use cw20::{Denom, Expiration};
+use cw20::{Cw20ReceiveMsg, Denom, Expiration};
+
+use crate::state::Limits;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
@@ -14,17 +16,31 @@ pub struct InstantiateMsg {
     // NOTE: Fees percents are out of 100 e.g., 1 = 1%
     pub protocol_fee_percent: Decimal,
     pub lp_fee_percent: Decimal,
+    pub lp_token_code_id: u64,
+    // Amplification of a stable swap pool, None for a constant product pool
+    pub amp: Option<u64>,
+    // Makes the pool a concentrated liquidity pool, can't be combined with amp
+    pub concentrated: Option<ConcentratedParams>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub enum TokenSelect {
-    Token1,
-    Token2,
+pub struct ConcentratedParams {
+    // Position bounds must be multiples of the tick spacing
+    pub tick_spacing: u32,
+    // The pool starts at the price 1.0001^initial_tick of token2 per token1
+    pub initial_tick: i32,
 }
 
+pub use pool_core::msg::{
+    FeeResponse, InfoResponse, OwnershipResponse, ProtocolFeesResponse,
+    Token1ForToken2PriceResponse, Token2ForToken1PriceResponse,
+};
+pub use pool_core::TokenSelect;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    // token2_amount is the maximum deposited, only the amount matching the pool ratio is taken
     AddLiquidity {
         token1_amount: Uint128,
         token2_amount: Uint128,
@@ -37,28 +53,144 @@ pub enum ExecuteMsg {
         min_token2: Uint128,
         expiration: Option<Expiration>,
     },
+    // Deposit a single token, part of it is swapped so both sides match the pool ratio
+    AddLiquiditySingle {
+        token: TokenSelect,
+        amount: Uint128,
+        min_liquidity: Uint128,
+        expiration: Option<Expiration>,
+    },
+    // Withdraw liquidity and swap the other side into output_token
+    RemoveLiquiditySingle {
+        amount: Uint128,
+        output_token: TokenSelect,
+        min_output: Uint128,
+        expiration: Option<Expiration>,
+    },
     Swap {
         input_token: TokenSelect,
         input_amount: Uint128,
         min_output: Uint128,
         expiration: Option<Expiration>,
     },
+    // Buy exactly output_amount of output_token, spending at most max_input of the other token
+    SwapExactOutput {
+        output_token: TokenSelect,
+        output_amount: Uint128,
+        max_input: Uint128,
+        expiration: Option<Expiration>,
+    },
+    // Swap in this pool and swap the output in output_amm_address
+    PassThroughSwap {
+        output_amm_address: String,
+        input_token: TokenSelect,
+        input_token_amount: Uint128,
+        output_min_token: Uint128,
+        expiration: Option<Expiration>,
+    },
+    SwapAndSendTo {
+        input_token: TokenSelect,
+        input_amount: Uint128,
+        recipient: String,
+        min_token: Uint128,
+        expiration: Option<Expiration>,
+    },
     UpdateConfig {
-        owner: Option<String>,
         lp_fee_percent: Decimal,
         protocol_fee_percent: Decimal,
         protocol_fee_recipient: String,
+        // None leaves the circuit breaker limits unchanged
+        limits: Option<Limits>,
+    },
+    // Send the accrued protocol fees to the protocol fee recipient
+    CollectProtocolFees {},
+    // Propose a new owner, who has to accept before the expiry
+    ProposeNewOwner {
+        owner: String,
+        expiry: Option<Expiration>,
     },
-    // Freeze adding new deposits
+    AcceptOwnership {},
+    CancelOwnershipProposal {},
+    // Remove the owner for good, the config can no longer be updated
+    RenounceOwnership {},
+    // Pause or unpause deposits and swaps together
     FreezeDeposits {
         freeze: bool,
     },
+    // None leaves a flag unchanged, the guardian can only pause
+    UpdatePause {
+        deposits: Option<bool>,
+        swaps: Option<bool>,
+        withdrawals: Option<bool>,
+    },
+    // None removes the guardian
+    UpdateGuardian {
+        guardian: Option<String>,
+    },
+    // Move the amplification of a stable swap pool linearly to target until end_time
+    RampAmp {
+        target: u64,
+        end_time: u64,
+    },
+    // Keep the current amplification of a stable swap pool, ending any ramp
+    StopRampAmp {},
+    // Lend amount of token to the sender and execute callback_msg on it, the sender has to
+    // transfer back the amount plus the swap fee before the callback returns
+    FlashSwap {
+        token: TokenSelect,
+        amount: Uint128,
+        callback_msg: Binary,
+    },
+    // Sent by the pool to itself after the flash swap callback to check the repayment
+    CompleteFlashSwap {},
+    // Provide liquidity to a concentrated liquidity pool in the price range
+    // [1.0001^lower_tick, 1.0001^upper_tick), the token amounts are the maximum deposited
+    CreatePosition {
+        lower_tick: i32,
+        upper_tick: i32,
+        token1_amount: Uint128,
+        token2_amount: Uint128,
+        min_liquidity: Uint128,
+        expiration: Option<Expiration>,
+    },
+    // Remove liquidity from a position and collect its fees, 0 only collects the fees
+    WithdrawPosition {
+        id: u64,
+        liquidity: Uint128,
+        min_token1: Uint128,
+        min_token2: Uint128,
+        expiration: Option<Expiration>,
+    },
+    Receive(Cw20ReceiveMsg),
+}
+
+// Hooks for cw20 tokens sent to the pool with Cw20ExecuteMsg::Send
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    // Swap the received token for the other pool token
+    Swap {
+        min_output: Uint128,
+        expiration: Option<Expiration>,
+    },
+    // The received token is one side of the deposit, the other side is pulled from the sender
+    AddLiquidity {
+        other_token_amount: Uint128,
+        min_liquidity: Uint128,
+        expiration: Option<Expiration>,
+    },
+    // Burn the received lp tokens and return the underlying tokens
+    RemoveLiquidity {
+        min_token1: Uint128,
+        min_token2: Uint128,
+        expiration: Option<Expiration>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    /// Implements CW20. Returns the current balance of the given address, 0 if unset.
+    /// Returns the lp token balance of the given address, 0 if unset.
     Balance {
         address: String,
     },
@@ -70,6 +202,37 @@ pub enum QueryMsg {
         token2_amount: Uint128,
     },
     Fee {},
+    // Output and fee breakdown of swapping input_amount of input_token
+    SimulateSwap {
+        input_token: TokenSelect,
+        input_amount: Uint128,
+    },
+    // Input required to receive output_amount of output_token
+    ReverseSimulate {
+        output_token: TokenSelect,
+        output_amount: Uint128,
+    },
+    // Time weighted average price of token in terms of the other token
+    Twap {
+        token: TokenSelect,
+        window_seconds: u64,
+    },
+    CumulativePrices {},
+    Ownership {},
+    ProtocolFees {},
+    Limits {},
+    Pause {},
+    // Amplification of a stable swap pool, fails for constant product pools
+    Amp {},
+    // Position of a concentrated liquidity pool
+    Position {
+        id: u64,
+    },
+    // Initialized ticks of a concentrated liquidity pool in ascending order
+    Ticks {
+        start_after: Option<i32>,
+        limit: Option<u32>,
+    },
 }
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct MigrateMsg {
@@ -78,30 +241,97 @@ pub struct MigrateMsg {
     pub protocol_fee_percent: Decimal,
     pub lp_fee_percent: Decimal,
     pub freeze_pool: bool,
+    // Only used by pools without an lp token, which instantiate it on migration
+    pub lp_token_code_id: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct InfoResponse {
-    pub token1_reserve: Uint128,
-    pub token1_denom: Denom,
-    pub token2_reserve: Uint128,
-    pub token2_denom: Denom,
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LimitsResponse {
+    pub max_price_impact: Option<Decimal>,
+    pub max_block_reserve_change: Option<Decimal>,
+    pub auto_freeze: bool,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
-pub struct FeeResponse {
-    pub owner: Option<String>,
-    pub lp_fee_percent: Decimal,
-    pub protocol_fee_percent: Decimal,
-    pub protocol_fee_recipient: String,
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PauseResponse {
+    pub deposits: bool,
+    pub swaps: bool,
+    pub withdrawals: bool,
+    pub guardian: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct Token1ForToken2PriceResponse {
-    pub token2_amount: Uint128,
+pub struct AmpResponse {
+    // Amplification at the current block time
+    pub amp: u64,
+    pub initial_amp: u64,
+    pub target_amp: u64,
+    pub start_time: u64,
+    pub end_time: u64,
 }
 
+// Token amounts are the ones withdrawn for the full liquidity at the current price, the owed
+// amounts are the fees collected by the next withdrawal
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct Token2ForToken1PriceResponse {
+pub struct PositionResponse {
+    pub id: u64,
+    pub owner: String,
+    pub lower_tick: i32,
+    pub upper_tick: i32,
+    pub liquidity: Uint128,
     pub token1_amount: Uint128,
+    pub token2_amount: Uint128,
+    pub tokens_owed1: Uint128,
+    pub tokens_owed2: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TickResponse {
+    pub tick: i32,
+    // Liquidity of the positions starting at the tick
+    pub liquidity_lower: Uint128,
+    // Liquidity of the positions ending at the tick
+    pub liquidity_upper: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TicksResponse {
+    pub ticks: Vec<TickResponse>,
+    pub current_tick: i32,
+    pub sqrt_price: Uint256,
+    // Liquidity in range at the current price
+    pub liquidity: Uint128,
+}
+
+// Fees are denominated in the input token, prices are output per input token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SimulateSwapResponse {
+    pub output_amount: Uint128,
+    pub lp_fee_amount: Uint128,
+    pub protocol_fee_amount: Uint128,
+    pub spot_price: Decimal,
+    pub price_impact: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ReverseSimulateResponse {
+    pub input_amount: Uint128,
+    pub lp_fee_amount: Uint128,
+    pub protocol_fee_amount: Uint128,
+    pub spot_price: Decimal,
+    pub price_impact: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TwapResponse {
+    pub price: Decimal256,
+    // Actual window used, at least the requested one
+    pub window_seconds: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CumulativePricesResponse {
+    pub price1_cumulative: Decimal256,
+    pub price2_cumulative: Decimal256,
+    pub last_updated: u64,
 }
@@ -0,0 +1,192 @@
+use cosmwasm_std::{
+    BlockInfo, Decimal256, Deps, Env, StdError, StdResult, Storage, Uint128, Uint256,
+};
+
+use crate::concentrated;
+use crate::msg::{CumulativePricesResponse, TokenSelect, TwapResponse};
+use crate::stableswap::{get_spot_price, load_amp};
+use crate::state::{
+    PriceCumulative, CONCENTRATED_POOL, PRICE_CUMULATIVE, PRICE_SNAPSHOTS, PRICE_SNAPSHOT_COUNT,
+    TOKEN1, TOKEN2,
+};
+
+// Size of the snapshot ring buffer, older snapshots are overwritten
+pub const MAX_PRICE_SNAPSHOTS: u64 = 288;
+// At most one snapshot is kept per interval, so the buffer covers 24 hours however many
+// trades the pool sees
+pub const PRICE_SNAPSHOT_INTERVAL: u64 = 300;
+
+// Adds the spot prices weighted by the seconds elapsed since the last update, the reserve
+// ratio for constant product pools, the curve price for stable swap pools and the pool price
+// for concentrated liquidity pools
+fn accumulate(
+    cumulative: &PriceCumulative,
+    token1_reserve: Uint128,
+    token2_reserve: Uint128,
+    amp: Option<u64>,
+    sqrt_price: Option<Uint256>,
+    now: u64,
+) -> StdResult<PriceCumulative> {
+    let elapsed = now.saturating_sub(cumulative.last_updated);
+    if elapsed == 0 || token1_reserve.is_zero() || token2_reserve.is_zero() {
+        return Ok(PriceCumulative {
+            last_updated: now,
+            ..cumulative.clone()
+        });
+    }
+
+    let elapsed = Uint256::from(elapsed);
+    let one = Uint256::from(10u128.pow(18));
+    let (price1, price2) = match (amp, sqrt_price) {
+        (None, None) => (
+            Decimal256::from_ratio(Uint256::from(token2_reserve) * elapsed, token1_reserve),
+            Decimal256::from_ratio(Uint256::from(token1_reserve) * elapsed, token2_reserve),
+        ),
+        (Some(amp), None) => {
+            let price1 = get_spot_price(amp, token1_reserve, token2_reserve)?.atomics();
+            let price2 = get_spot_price(amp, token2_reserve, token1_reserve)?.atomics();
+            (
+                Decimal256::from_ratio(Uint256::from(price1) * elapsed, one),
+                Decimal256::from_ratio(Uint256::from(price2) * elapsed, one),
+            )
+        }
+        (_, Some(sqrt_price)) => {
+            let price1 = concentrated::get_spot_price(sqrt_price, true)?.atomics();
+            let price2 = concentrated::get_spot_price(sqrt_price, false)?.atomics();
+            (
+                Decimal256::from_ratio(price1 * elapsed, one),
+                Decimal256::from_ratio(price2 * elapsed, one),
+            )
+        }
+    };
+    Ok(PriceCumulative {
+        price1_cumulative: cumulative.price1_cumulative + price1,
+        price2_cumulative: cumulative.price2_cumulative + price2,
+        last_updated: now,
+    })
+}
+
+fn save_snapshot(storage: &mut dyn Storage, snapshot: &PriceCumulative) -> StdResult<()> {
+    let count = PRICE_SNAPSHOT_COUNT.may_load(storage)?.unwrap_or_default();
+    if count > 0 {
+        let last = PRICE_SNAPSHOTS.load(storage, (count - 1) % MAX_PRICE_SNAPSHOTS)?;
+        if last.last_updated / PRICE_SNAPSHOT_INTERVAL
+            == snapshot.last_updated / PRICE_SNAPSHOT_INTERVAL
+        {
+            return Ok(());
+        }
+    }
+    PRICE_SNAPSHOTS.save(storage, count % MAX_PRICE_SNAPSHOTS, snapshot)?;
+    PRICE_SNAPSHOT_COUNT.save(storage, &(count + 1))
+}
+
+pub fn init_price_accumulator(storage: &mut dyn Storage, block: &BlockInfo) -> StdResult<()> {
+    let cumulative = PriceCumulative {
+        price1_cumulative: Decimal256::zero(),
+        price2_cumulative: Decimal256::zero(),
+        last_updated: block.time.seconds(),
+    };
+    PRICE_CUMULATIVE.save(storage, &cumulative)?;
+    save_snapshot(storage, &cumulative)
+}
+
+// Must be called before the reserves change, so the elapsed time is priced at the old reserves
+pub fn update_price_accumulator(storage: &mut dyn Storage, block: &BlockInfo) -> StdResult<()> {
+    let cumulative = match PRICE_CUMULATIVE.may_load(storage)? {
+        Some(cumulative) => cumulative,
+        // Pools migrated from a version without price accumulators start tracking now
+        None => return init_price_accumulator(storage, block),
+    };
+    let now = block.time.seconds();
+    if now <= cumulative.last_updated {
+        return Ok(());
+    }
+
+    let token1 = TOKEN1.load(storage)?;
+    let token2 = TOKEN2.load(storage)?;
+    let amp = load_amp(storage, block)?;
+    let sqrt_price = CONCENTRATED_POOL
+        .may_load(storage)?
+        .map(|pool| pool.sqrt_price);
+    let cumulative = accumulate(
+        &cumulative,
+        token1.reserve,
+        token2.reserve,
+        amp,
+        sqrt_price,
+        now,
+    )?;
+    PRICE_CUMULATIVE.save(storage, &cumulative)?;
+    save_snapshot(storage, &cumulative)
+}
+
+// Cumulative prices as they would be if the accumulator was updated in the current block
+fn current_cumulative(deps: Deps, env: &Env) -> StdResult<PriceCumulative> {
+    let cumulative = PRICE_CUMULATIVE.load(deps.storage)?;
+    let token1 = TOKEN1.load(deps.storage)?;
+    let token2 = TOKEN2.load(deps.storage)?;
+    accumulate(
+        &cumulative,
+        token1.reserve,
+        token2.reserve,
+        load_amp(deps.storage, &env.block)?,
+        CONCENTRATED_POOL
+            .may_load(deps.storage)?
+            .map(|pool| pool.sqrt_price),
+        env.block.time.seconds(),
+    )
+}
+
+pub fn query_cumulative_prices(deps: Deps, env: Env) -> StdResult<CumulativePricesResponse> {
+    let cumulative = current_cumulative(deps, &env)?;
+    Ok(CumulativePricesResponse {
+        price1_cumulative: cumulative.price1_cumulative,
+        price2_cumulative: cumulative.price2_cumulative,
+        last_updated: cumulative.last_updated,
+    })
+}
+
+pub fn query_twap(
+    deps: Deps,
+    env: Env,
+    token: TokenSelect,
+    window_seconds: u64,
+) -> StdResult<TwapResponse> {
+    if window_seconds == 0 {
+        return Err(StdError::generic_err("Twap window must be positive"));
+    }
+    let current = current_cumulative(deps, &env)?;
+    let target = current.last_updated.saturating_sub(window_seconds);
+
+    // Use the most recent snapshot that covers the whole window, snapshots are ordered by
+    // time so it is found with a binary search over the ring buffer
+    let count = PRICE_SNAPSHOT_COUNT
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let load = |index: u64| PRICE_SNAPSHOTS.load(deps.storage, index % MAX_PRICE_SNAPSHOTS);
+    let (mut low, mut high) = (count.saturating_sub(MAX_PRICE_SNAPSHOTS), count);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if load(mid)?.last_updated <= target {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    if low == count.saturating_sub(MAX_PRICE_SNAPSHOTS) {
+        return Err(StdError::generic_err(
+            "Not enough price history for the requested twap window",
+        ));
+    }
+    let start = load(low - 1)?;
+
+    let elapsed = Uint256::from(current.last_updated - start.last_updated);
+    let price = match token {
+        TokenSelect::Token1 => (current.price1_cumulative - start.price1_cumulative) / elapsed,
+        TokenSelect::Token2 => (current.price2_cumulative - start.price2_cumulative) / elapsed,
+    };
+    Ok(TwapResponse {
+        price,
+        window_seconds: current.last_updated - start.last_updated,
+    })
+}
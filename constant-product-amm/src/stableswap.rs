@@ -0,0 +1,358 @@
+use cosmwasm_std::{BlockInfo, Decimal, StdError, StdResult, Storage, Uint128, Uint256, Uint512};
+use pool_core::fee::{fee_decimal_to_uint128, FEE_SCALE_FACTOR};
+use pool_core::{PoolCurve, TokenSelect};
+
+use crate::state::{AmpConfig, AMP};
+
+// Bounds of the amplification coefficient
+pub const MIN_AMP: u64 = 1;
+pub const MAX_AMP: u64 = 1_000_000;
+// A ramp can change the amplification by at most this factor
+pub const MAX_AMP_CHANGE: u64 = 10;
+// Shortest ramp, so the curve can't be moved within a few blocks
+pub const MIN_RAMP_SECONDS: u64 = 86_400;
+
+const N_COINS: u8 = 2;
+const MAX_ITERATIONS: u8 = 255;
+
+// Amplification at the block time, linear between the start and the end of a ramp
+pub fn get_amp(config: &AmpConfig, block: &BlockInfo) -> u64 {
+    let now = block.time.seconds();
+    if now >= config.end_time || config.end_time <= config.start_time {
+        return config.target_amp;
+    }
+    let elapsed = now.saturating_sub(config.start_time) as u128;
+    let duration = (config.end_time - config.start_time) as u128;
+    let (initial, target) = (config.initial_amp as u128, config.target_amp as u128);
+    let amp = if target >= initial {
+        initial + (target - initial) * elapsed / duration
+    } else {
+        initial - (initial - target) * elapsed / duration
+    };
+    amp as u64
+}
+
+// None for constant product pools
+pub fn load_amp(storage: &dyn Storage, block: &BlockInfo) -> StdResult<Option<u64>> {
+    Ok(AMP.may_load(storage)?.map(|config| get_amp(&config, block)))
+}
+
+fn abs_diff(a: Uint256, b: Uint256) -> Uint256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+fn add(a: Uint256, b: Uint256) -> StdResult<Uint256> {
+    a.checked_add(b).map_err(StdError::overflow)
+}
+
+fn sub(a: Uint256, b: Uint256) -> StdResult<Uint256> {
+    a.checked_sub(b).map_err(StdError::overflow)
+}
+
+fn mul(a: Uint256, b: Uint256) -> StdResult<Uint256> {
+    a.checked_mul(b).map_err(StdError::overflow)
+}
+
+fn div(a: Uint256, b: Uint256) -> StdResult<Uint256> {
+    a.checked_div(b).map_err(StdError::divide_by_zero)
+}
+
+// Invariant D of A * n^n * (x + y) + D = A * n^n * D + D^(n + 1) / (n^n * x * y), found with
+// Newton's method starting from D = x + y
+pub fn compute_d(amp: u64, x: Uint128, y: Uint128) -> StdResult<Uint256> {
+    let n = Uint256::from(N_COINS);
+    let (x, y) = (Uint256::from(x), Uint256::from(y));
+    let sum = add(x, y)?;
+    if sum.is_zero() {
+        return Ok(Uint256::zero());
+    }
+    let ann = Uint256::from(amp * (N_COINS as u64).pow(N_COINS as u32));
+
+    let (mut d, mut d_prev) = (sum, Uint256::zero());
+    for _ in 0..MAX_ITERATIONS {
+        // D^(n + 1) / (n^n * x * y)
+        let d_p = div(mul(div(mul(d, d)?, mul(x, n)?)?, d)?, mul(y, n)?)?;
+        let d_prev_prev = std::mem::replace(&mut d_prev, d);
+        let numerator = mul(add(mul(ann, sum)?, mul(d_p, n)?)?, d)?;
+        let denominator = add(
+            mul(sub(ann, Uint256::from(1u8))?, d)?,
+            mul(d_p, add(n, Uint256::from(1u8))?)?,
+        )?;
+        d = div(numerator, denominator)?;
+        // Rounding can leave the iteration alternating around the root on imbalanced pools
+        if abs_diff(d, d_prev) <= Uint256::from(1u8) || d == d_prev_prev {
+            return Ok(d.min(d_prev));
+        }
+    }
+    Err(StdError::generic_err(
+        "StableSwap invariant did not converge",
+    ))
+}
+
+// Reserve y that keeps the invariant D when the other reserve is x, found with Newton's method
+// on y^2 + (b - D) * y = c
+pub fn compute_y(amp: u64, x: Uint256, d: Uint256) -> StdResult<Uint256> {
+    let n = Uint256::from(N_COINS);
+    let ann = Uint256::from(amp * (N_COINS as u64).pow(N_COINS as u32));
+    let c = div(mul(div(mul(d, d)?, mul(x, n)?)?, d)?, mul(ann, n)?)?;
+    let b = add(x, div(d, ann)?)?;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        let denominator = sub(add(mul(y, n)?, b)?, d)?;
+        y = div(add(mul(y, y)?, c)?, denominator)?;
+        if abs_diff(y, y_prev) <= Uint256::from(1u8) {
+            return Ok(y);
+        }
+    }
+    Err(StdError::generic_err("StableSwap reserve did not converge"))
+}
+
+// Output bought with input_amount, fees are taken from the input by the caller. Both reserves
+// come from the solver so its error cancels out, and the result is rounded down by one more
+// unit so the invariant never decreases
+pub fn get_output_amount(
+    amp: u64,
+    input_amount: Uint128,
+    input_reserve: Uint128,
+    output_reserve: Uint128,
+) -> StdResult<Uint128> {
+    let d = compute_d(amp, input_reserve, output_reserve)?;
+    let solved_output_reserve = compute_y(amp, Uint256::from(input_reserve), d)?;
+    let new_input_reserve = input_reserve
+        .checked_add(input_amount)
+        .map_err(StdError::overflow)?;
+    let new_output_reserve = compute_y(amp, Uint256::from(new_input_reserve), d)?;
+    let output_amount = solved_output_reserve
+        .saturating_sub(new_output_reserve)
+        .saturating_sub(Uint256::from(1u8));
+    Ok(output_amount.try_into()?)
+}
+
+// Inverse of get_output_amount, an input that buys at least output_amount
+pub fn get_input_amount(
+    amp: u64,
+    output_amount: Uint128,
+    input_reserve: Uint128,
+    output_reserve: Uint128,
+) -> StdResult<Uint128> {
+    // get_output_amount keeps one more unit in the pool
+    let new_output_reserve = output_reserve
+        .checked_sub(output_amount)
+        .and_then(|reserve| reserve.checked_sub(Uint128::new(1)))
+        .unwrap_or_default();
+    if new_output_reserve.is_zero() {
+        return Err(StdError::generic_err(
+            "Insufficient liquidity for the requested output",
+        ));
+    }
+    let d = compute_d(amp, input_reserve, output_reserve)?;
+    let solved_input_reserve = compute_y(amp, Uint256::from(output_reserve), d)?;
+    let new_input_reserve = compute_y(amp, Uint256::from(new_output_reserve), d)?;
+    let mut input_amount: Uint128 = add(new_input_reserve, Uint256::from(1u8))?
+        .saturating_sub(solved_input_reserve)
+        .try_into()?;
+
+    // On very imbalanced pools the solver error can exceed the margin, step up until the
+    // estimate buys enough
+    let mut step = Uint128::new(1);
+    for _ in 0..MAX_ITERATIONS {
+        if get_output_amount(amp, input_amount, input_reserve, output_reserve)? >= output_amount {
+            return Ok(input_amount);
+        }
+        input_amount = input_amount.checked_add(step).map_err(StdError::overflow)?;
+        step = step
+            .checked_mul(Uint128::new(2))
+            .map_err(StdError::overflow)?;
+    }
+    Err(StdError::generic_err("StableSwap input did not converge"))
+}
+
+// Stable swap pool curve at the amplification of the current block
+pub struct StableSwap {
+    pub amp: u64,
+}
+
+impl PoolCurve for StableSwap {
+    fn swap_output(
+        &self,
+        _input_token: &TokenSelect,
+        input_amount: Uint128,
+        input_reserve: Uint128,
+        output_reserve: Uint128,
+        fee_percent: Decimal,
+    ) -> StdResult<Uint128> {
+        if input_reserve.is_zero() || output_reserve.is_zero() {
+            return Err(StdError::generic_err("No liquidity"));
+        }
+        let fee_reduction_percent = FEE_SCALE_FACTOR - fee_decimal_to_uint128(fee_percent)?;
+        let input_amount_with_fee =
+            input_amount.multiply_ratio(fee_reduction_percent, FEE_SCALE_FACTOR);
+        get_output_amount(
+            self.amp,
+            input_amount_with_fee,
+            input_reserve,
+            output_reserve,
+        )
+    }
+
+    fn swap_input(
+        &self,
+        _input_token: &TokenSelect,
+        output_amount: Uint128,
+        input_reserve: Uint128,
+        output_reserve: Uint128,
+        fee_percent: Decimal,
+    ) -> StdResult<Uint128> {
+        if input_reserve.is_zero() || output_reserve.is_zero() {
+            return Err(StdError::generic_err("No liquidity"));
+        }
+        let input_amount_with_fee =
+            get_input_amount(self.amp, output_amount, input_reserve, output_reserve)?;
+        let fee_reduction_percent = FEE_SCALE_FACTOR - fee_decimal_to_uint128(fee_percent)?;
+        if fee_reduction_percent.is_zero() {
+            return Err(StdError::generic_err("No liquidity"));
+        }
+        // Round up so the fee taken from the input leaves at least input_amount_with_fee
+        Ok(input_amount_with_fee
+            .full_mul(FEE_SCALE_FACTOR)
+            .checked_add(Uint256::from(fee_reduction_percent - Uint128::new(1)))
+            .map_err(StdError::overflow)?
+            .checked_div(Uint256::from(fee_reduction_percent))
+            .map_err(StdError::divide_by_zero)?
+            .try_into()?)
+    }
+}
+
+// Marginal price of the input in output tokens, -dy/dx on the invariant curve:
+// (4 * A * n^n * x * y + D^3 / x) / (4 * A * n^n * x * y + D^3 / y)
+pub fn get_spot_price(
+    amp: u64,
+    input_reserve: Uint128,
+    output_reserve: Uint128,
+) -> StdResult<Decimal> {
+    if input_reserve.is_zero() || output_reserve.is_zero() {
+        return Err(StdError::generic_err("No liquidity"));
+    }
+    let d = compute_d(amp, input_reserve, output_reserve)?;
+    let (x, y) = (Uint256::from(input_reserve), Uint256::from(output_reserve));
+    let ann = Uint256::from(amp * (N_COINS as u64).pow(N_COINS as u32));
+    let product_term = mul(mul(mul(ann, Uint256::from(4u8))?, x)?, y)?;
+    let d_squared = mul(d, d)?;
+    let numerator = add(product_term, mul(div(d_squared, x)?, d)?)?;
+    let denominator = add(product_term, mul(div(d_squared, y)?, d)?)?;
+
+    let atomics: Uint256 = Uint256::from(10u128.pow(18))
+        .full_mul(numerator)
+        .checked_div(Uint512::from(denominator))
+        .map_err(StdError::divide_by_zero)?
+        .try_into()?;
+    let atomics: Uint128 = atomics.try_into()?;
+    Ok(Decimal::from_ratio(atomics, 10u128.pow(18)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::mock_env;
+    use cosmwasm_std::Timestamp;
+
+    #[test]
+    fn test_get_amp() {
+        let mut block = mock_env().block;
+        let config = AmpConfig {
+            initial_amp: 100,
+            target_amp: 200,
+            start_time: 1_000,
+            end_time: 2_000,
+        };
+        block.time = Timestamp::from_seconds(500);
+        assert_eq!(get_amp(&config, &block), 100);
+        block.time = Timestamp::from_seconds(1_250);
+        assert_eq!(get_amp(&config, &block), 125);
+        block.time = Timestamp::from_seconds(3_000);
+        assert_eq!(get_amp(&config, &block), 200);
+
+        let config = AmpConfig {
+            initial_amp: 200,
+            target_amp: 100,
+            start_time: 1_000,
+            end_time: 2_000,
+        };
+        block.time = Timestamp::from_seconds(1_500);
+        assert_eq!(get_amp(&config, &block), 150);
+    }
+
+    #[test]
+    fn test_balanced_pool_output() {
+        let reserve = Uint128::new(1_000_000_000);
+        // A high amplification trades close to 1:1
+        let output = get_output_amount(1_000, Uint128::new(10_000_000), reserve, reserve).unwrap();
+        assert!(output > Uint128::new(9_999_000) && output < Uint128::new(10_000_000));
+        // A low amplification slips more, but still less than constant product
+        let output = get_output_amount(1, Uint128::new(10_000_000), reserve, reserve).unwrap();
+        assert!(output > Uint128::new(9_900_990) && output < Uint128::new(9_999_000));
+
+        let price = get_spot_price(100, reserve, reserve).unwrap();
+        assert_eq!(price, Decimal::one());
+    }
+
+    #[test]
+    fn test_spot_price_overflow() {
+        // The invariant fits, but the numerator of the price doesn't
+        let x = Uint128::new(52_454_101_666_261_404_777_145_539_423_524_356_096);
+        let y = Uint128::new(85_070_591_730_234_615_865_843_651_857_942_052_863);
+        compute_d(1, x, y).unwrap();
+        let err = get_spot_price(1, x, y).unwrap_err();
+        assert!(matches!(err, StdError::Overflow { .. }));
+    }
+
+    #[test]
+    fn test_input_amount_buys_output() {
+        let pools = [
+            (1_000_000u128, 1_000_000u128),
+            (10_000_000, 1_000),
+            (1_000, 10_000_000),
+            (123_456_789, 987_654_321_000),
+        ];
+        for amp in [1u64, 10, 100, 1_000, 100_000] {
+            for (x, y) in pools {
+                for output in [1u128, 7, 100, 999, 5_000, 250_000] {
+                    if output + 1 >= y {
+                        continue;
+                    }
+                    let (x, y, output) = (Uint128::new(x), Uint128::new(y), Uint128::new(output));
+                    let input = get_input_amount(amp, output, x, y).unwrap();
+                    assert!(get_output_amount(amp, input, x, y).unwrap() >= output);
+                }
+            }
+        }
+        let err = get_input_amount(
+            100,
+            Uint128::new(999),
+            Uint128::new(1_000),
+            Uint128::new(1_000),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Insufficient liquidity for the requested output")
+        );
+        let err = get_input_amount(100, Uint128::MAX, Uint128::new(1_000), Uint128::new(1_000))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Insufficient liquidity for the requested output")
+        );
+
+        // Reserves overflowing the solver return an error instead of panicking
+        let err = get_output_amount(100, Uint128::MAX, Uint128::new(1_000), Uint128::new(1_000))
+            .unwrap_err();
+        assert!(matches!(err, StdError::Overflow { .. }));
+    }
+}
@@ -1,28 +1,129 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Decimal, Uint128};
-use cw20::Denom;
-use cw_storage_plus::Item;
+use cosmwasm_std::{Addr, Decimal, Decimal256, Uint128, Uint256};
+use cw_storage_plus::{Item, Map};
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct Token {
-    pub reserve: Uint128,
-    pub denom: Denom,
+use crate::msg::TokenSelect;
+
+pub use pool_core::state::{
+    Fees, OwnershipProposal, PauseFlags, ProtocolFees, Token, FEES, LP_TOKEN, OWNER,
+    OWNERSHIP_PROPOSAL, PAUSE_FLAGS, PROTOCOL_FEES, TOKEN1, TOKEN2,
+};
+
+// Amplification of stable swap pools, ramped linearly from initial_amp to target_amp between
+// start_time and end_time, constant product pools don't store it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AmpConfig {
+    pub initial_amp: u64,
+    pub target_amp: u64,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+pub const AMP: Item<AmpConfig> = Item::new("amp");
+
+// Concentrated liquidity pools only hold liquidity in the ranges of their positions. The price
+// is the amount of token2 per token1, stored as a square root in Q64.96 fixed point, and the
+// fee growths are the lp fees earned per unit of liquidity in Q128.128 fixed point.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ConcentratedPool {
+    pub tick_spacing: u32,
+    pub sqrt_price: Uint256,
+    // Largest tick at or below the current price
+    pub tick: i32,
+    // Liquidity of the positions whose range contains the current price
+    pub liquidity: Uint128,
+    pub fee_growth_global1: Uint256,
+    pub fee_growth_global2: Uint256,
+}
+
+pub const CONCENTRATED_POOL: Item<ConcentratedPool> = Item::new("concentrated_pool");
+
+// Liquidity of the positions starting and ending at a tick, and the fee growth on the other
+// side of the tick from the current price
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct TickInfo {
+    pub liquidity_lower: Uint128,
+    pub liquidity_upper: Uint128,
+    pub fee_growth_outside1: Uint256,
+    pub fee_growth_outside2: Uint256,
+}
+
+// Keyed by the tick with its sign bit flipped, so keys sort in tick order
+pub const TICKS: Map<u32, TickInfo> = Map::new("ticks");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Position {
+    pub owner: Addr,
+    pub lower_tick: i32,
+    pub upper_tick: i32,
+    pub liquidity: Uint128,
+    pub fee_growth_inside1_last: Uint256,
+    pub fee_growth_inside2_last: Uint256,
+    // Fees earned until the last update of the position and not collected yet
+    pub tokens_owed1: Uint128,
+    pub tokens_owed2: Uint128,
 }
 
-pub const TOKEN1: Item<Token> = Item::new("token1");
-pub const TOKEN2: Item<Token> = Item::new("token2");
+pub const POSITIONS: Map<u64, Position> = Map::new("positions");
+pub const POSITION_COUNT: Item<u64> = Item::new("position_count");
 
-pub const OWNER: Item<Option<Addr>> = Item::new("owner");
+// Flash swap waiting for its repayment check, the pool balance of the lent token is taken before
+// the tokens are sent
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct FlashSwap {
+    pub borrower: Addr,
+    pub token: TokenSelect,
+    pub amount: Uint128,
+    pub balance_before: Uint128,
+}
 
+pub const FLASH_SWAP: Item<FlashSwap> = Item::new("flash_swap");
+
+// Can pause the pool alongside the owner, but only the owner can unpause it
+pub const GUARDIAN: Item<Option<Addr>> = Item::new("guardian");
+
+// Circuit breaker limits, fractions of 1 e.g., 0.05 = 5%, None disables a limit
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct Limits {
+    // Maximum price impact of a single swap
+    pub max_price_impact: Option<Decimal>,
+    // Maximum amount either reserve can move through swaps within a block,
+    // relative to the reserve at the start of the block
+    pub max_block_reserve_change: Option<Decimal>,
+    // Freeze the pool when a trade hits a limit, the trade input is returned. Off unless the
+    // owner sets it, it lets any trader who hits a limit pause the pool
+    #[serde(default)]
+    pub auto_freeze: bool,
+}
+
+pub const LIMITS: Item<Limits> = Item::new("limits");
+
+// Reserves at the start of the block and how much swaps moved them since
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct Fees {
-    pub protocol_fee_recipient: Addr,
-    pub protocol_fee_percent: Decimal,
-    pub lp_fee_percent: Decimal,
+pub struct BlockReserveChange {
+    pub height: u64,
+    pub token1_reserve: Uint128,
+    pub token2_reserve: Uint128,
+    pub token1_change: Uint128,
+    pub token2_change: Uint128,
+}
+
+pub const BLOCK_RESERVE_CHANGE: Item<BlockReserveChange> = Item::new("block_reserve_change");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceCumulative {
+    // Sum of token2_reserve / token1_reserve weighted by seconds
+    pub price1_cumulative: Decimal256,
+    // Sum of token1_reserve / token2_reserve weighted by seconds
+    pub price2_cumulative: Decimal256,
+    pub last_updated: u64,
 }
 
-pub const FEES: Item<Fees> = Item::new("fees");
+pub const PRICE_CUMULATIVE: Item<PriceCumulative> = Item::new("price_cumulative");
 
-pub const FROZEN: Item<bool> = Item::new("frozen");
+// Ring buffer of past accumulator values, at most one per PRICE_SNAPSHOT_INTERVAL, keyed by
+// snapshot count modulo MAX_PRICE_SNAPSHOTS
+pub const PRICE_SNAPSHOTS: Map<u64, PriceCumulative> = Map::new("price_snapshots");
+pub const PRICE_SNAPSHOT_COUNT: Item<u64> = Item::new("price_snapshot_count");
@@ -4,8 +4,9 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use constant_sum_amm::msg::{
-    ExecuteMsg, InfoResponse, InstantiateMsg, QueryMsg, Token1ForToken2PriceResponse,
-    Token2ForToken1PriceResponse,
+    DepegProtectionResponse, ExecuteMsg, InfoResponse, InstantiateMsg, OwnershipResponse,
+    PriceResponse, PriceSourceQueryMsg, ProtocolFeesResponse, QueryMsg,
+    Token1ForToken2PriceResponse, Token2ForToken1PriceResponse,
 };
 use constant_sum_amm::state::Token;
 use cw20::BalanceResponse;
@@ -24,4 +25,9 @@ fn main() {
     export_schema(&schema_for!(InfoResponse), &out_dir);
     export_schema(&schema_for!(Token1ForToken2PriceResponse), &out_dir);
     export_schema(&schema_for!(Token2ForToken1PriceResponse), &out_dir);
+    export_schema(&schema_for!(OwnershipResponse), &out_dir);
+    export_schema(&schema_for!(ProtocolFeesResponse), &out_dir);
+    export_schema(&schema_for!(DepegProtectionResponse), &out_dir);
+    export_schema(&schema_for!(PriceSourceQueryMsg), &out_dir);
+    export_schema(&schema_for!(PriceResponse), &out_dir);
 }
@@ -1,77 +1,82 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, entry_point, to_binary, Addr, Binary, BlockInfo, CosmosMsg, Decimal, Deps, DepsMut,
-    Env, MessageInfo, Response, StdError, StdResult, Uint128, Uint256,
-    WasmMsg,
+    attr, to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+    Uint128,
 };
+use cw0::nonpayable;
 use cw2::set_contract_version;
-use cw20::Denom::Cw20;
-use cw20::{Cw20ExecuteMsg, Denom, Expiration};
-use cw20_base::contract::query_balance;
-use std::convert::TryInto;
-use std::str::FromStr;
+use cw20::Expiration;
+use pool_core::lp_token::{query_balance, save_lp_token};
+use pool_core::ownership::{
+    assert_owner, execute_accept_ownership, execute_cancel_ownership_proposal,
+    execute_propose_new_owner, execute_renounce_ownership, query_ownership,
+};
+use pool_core::pause::{assert_not_paused, execute_freeze_deposits, PauseAction};
+use pool_core::pool::{
+    check_expiration, commit_swap, execute_collect_protocol_fees, get_swap, get_swap_output,
+    instantiate_pool, provide_liquidity, query_fee, query_protocol_fees, update_fees,
+    withdraw_liquidity, PoolConfig,
+};
+use pool_core::state::get_swap_tokens;
+use pool_core::transfer::{get_transfer_to_msg, validate_input_amounts};
+use pool_core::ConstantSum;
 
 use crate::error::ContractError;
 use crate::msg::{
-    ExecuteMsg, FeeResponse, InfoResponse, InstantiateMsg, QueryMsg, Token1ForToken2PriceResponse,
+    DepegProtectionParams, DepegProtectionResponse, ExecuteMsg, InfoResponse, InstantiateMsg,
+    PriceResponse, PriceSourceQueryMsg, QueryMsg, Token1ForToken2PriceResponse,
     Token2ForToken1PriceResponse, TokenSelect,
 };
-use crate::state::{Fees, Token, FEES, FROZEN, OWNER, TOKEN1, TOKEN2};
+use crate::state::{DepegProtection, DEPEG_PROTECTION, RATE};
 
 // Version info for migration info
 pub const CONTRACT_NAME: &str = "crates.io:sum-amm";
 pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-const FEE_SCALE_FACTOR: Uint128 = Uint128::new(10_000);
-const MAX_FEE_PERCENT: &str = "1";
-const FEE_DECIMAL_PRECISION: Uint128 = Uint128::new(10u128.pow(20));
-
 // Note, you can use StdResult in some functions where you do not
 // make use of the custom errors
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
-    _env: Env,
+    mut deps: DepsMut,
+    env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    let token1 = Token {
-        reserve: Uint128::zero(),
-        denom: msg.token1_denom.clone(),
-    };
-    TOKEN1.save(deps.storage, &token1)?;
+    let response = instantiate_pool(
+        deps.branch(),
+        &env,
+        PoolConfig {
+            token1_denom: msg.token1_denom,
+            token2_denom: msg.token2_denom,
+            owner: msg.owner,
+            protocol_fee_recipient: msg.protocol_fee_recipient,
+            protocol_fee_percent: msg.protocol_fee_percent,
+            lp_fee_percent: msg.lp_fee_percent,
+            lp_token_code_id: msg.lp_token_code_id,
+        },
+    )?;
 
-    let token2 = Token {
-        denom: msg.token2_denom.clone(),
-        reserve: Uint128::zero(),
-    };
-    TOKEN2.save(deps.storage, &token2)?;
-
-    let owner = msg.owner.map(|h| deps.api.addr_validate(&h)).transpose()?;
-    OWNER.save(deps.storage, &owner)?;
-
-    let protocol_fee_recipient = deps.api.addr_validate(&msg.protocol_fee_recipient)?;
-    let total_fee_percent = msg.lp_fee_percent + msg.protocol_fee_percent;
-    let max_fee_percent = Decimal::from_str(MAX_FEE_PERCENT)?;
-    if total_fee_percent > max_fee_percent {
-        return Err(ContractError::FeesTooHigh {
-            max_fee_percent,
-            total_fee_percent,
-        });
-    }
+    let depeg_protection = msg
+        .depeg_protection
+        .map(|params| validate_depeg_protection(deps.as_ref(), params))
+        .transpose()?;
+    DEPEG_PROTECTION.save(deps.storage, &depeg_protection)?;
 
-    let fees = Fees {
-        lp_fee_percent: msg.lp_fee_percent,
-        protocol_fee_percent: msg.protocol_fee_percent,
-        protocol_fee_recipient,
-    };
-    FEES.save(deps.storage, &fees)?;
+    let rate = msg.rate.unwrap_or_else(Decimal::one);
+    if rate.is_zero() {
+        return Err(ContractError::InvalidRate {});
+    }
+    RATE.save(deps.storage, &rate)?;
 
-    // Depositing is not frozen by default
-    FROZEN.save(deps.storage, &false)?;
+    Ok(response)
+}
 
-    Ok(Response::new().add_attribute("key", "instantiate"))
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    Ok(save_lp_token(deps, msg)?)
 }
 
 // And declare a custom Error variant for the ones where you will want to make use of it
@@ -82,14 +87,25 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    // Only deposits and swaps take native funds, their handlers check them against the inputs
+    let payable = matches!(
+        msg,
+        ExecuteMsg::AddLiquidity { .. }
+            | ExecuteMsg::Swap { .. }
+            | ExecuteMsg::SwapAndSendTo { .. }
+    );
+    if !payable {
+        nonpayable(&info)?;
+    }
+
     match msg {
         ExecuteMsg::AddLiquidity {
             token1_amount,
             min_liquidity,
-            expiration, token2_amount } => {
-            if FROZEN.load(deps.storage)? {
-                return Err(ContractError::FrozenPool {});
-            }
+            expiration,
+            token2_amount,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Deposit)?;
             execute_add_liquidity(
                 deps,
                 &info,
@@ -105,7 +121,10 @@ pub fn execute(
             min_token1,
             min_token2,
             expiration,
-        } => execute_remove_liquidity(deps, info, env, amount, min_token1, min_token2, expiration),
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Withdrawal)?;
+            execute_remove_liquidity(deps, info, env, amount, min_token1, min_token2, expiration)
+        }
         ExecuteMsg::Swap {
             input_token,
             input_amount,
@@ -113,9 +132,7 @@ pub fn execute(
             expiration,
             ..
         } => {
-            if FROZEN.load(deps.storage)? {
-                return Err(ContractError::FrozenPool {});
-            }
+            assert_not_paused(deps.storage, PauseAction::Swap)?;
             execute_swap(
                 deps,
                 &info,
@@ -127,183 +144,207 @@ pub fn execute(
                 expiration,
             )
         }
+        ExecuteMsg::SwapAndSendTo {
+            input_token,
+            input_amount,
+            recipient,
+            min_token,
+            expiration,
+        } => {
+            assert_not_paused(deps.storage, PauseAction::Swap)?;
+            execute_swap(
+                deps,
+                &info,
+                input_amount,
+                env,
+                input_token,
+                recipient,
+                min_token,
+                expiration,
+            )
+        }
         ExecuteMsg::UpdateConfig {
-            owner,
             protocol_fee_recipient,
             lp_fee_percent,
             protocol_fee_percent,
         } => execute_update_config(
             deps,
             info,
-            owner,
             lp_fee_percent,
             protocol_fee_percent,
             protocol_fee_recipient,
         ),
-        ExecuteMsg::FreezeDeposits { freeze } => execute_freeze_deposits(deps, info.sender, freeze),
+        ExecuteMsg::CollectProtocolFees {} => Ok(execute_collect_protocol_fees(deps, info)?),
+        ExecuteMsg::ProposeNewOwner { owner, expiry } => {
+            Ok(execute_propose_new_owner(deps, env, info, owner, expiry)?)
+        }
+        ExecuteMsg::AcceptOwnership {} => Ok(execute_accept_ownership(deps, env, info)?),
+        ExecuteMsg::CancelOwnershipProposal {} => {
+            Ok(execute_cancel_ownership_proposal(deps, info)?)
+        }
+        ExecuteMsg::RenounceOwnership {} => Ok(execute_renounce_ownership(deps, info)?),
+        ExecuteMsg::FreezeDeposits { freeze } => {
+            Ok(execute_freeze_deposits(deps, info.sender, freeze)?)
+        }
+        ExecuteMsg::UpdateDepegProtection { depeg_protection } => {
+            execute_update_depeg_protection(deps, info, depeg_protection)
+        }
+        ExecuteMsg::UpdateRate { rate } => execute_update_rate(deps, info, rate),
     }
 }
 
-fn execute_freeze_deposits(
+pub fn execute_update_rate(
     deps: DepsMut,
-    sender: Addr,
-    freeze: bool,
+    info: MessageInfo,
+    rate: Decimal,
 ) -> Result<Response, ContractError> {
-    if let Some(owner) = OWNER.load(deps.storage)? {
-        if sender != owner {
-            return Err(ContractError::UnauthorizedPoolFreeze {});
-        }
-    } else {
-        return Err(ContractError::UnauthorizedPoolFreeze {});
+    assert_owner(deps.as_ref(), &info.sender)?;
+    if rate.is_zero() {
+        return Err(ContractError::InvalidRate {});
     }
 
-    FROZEN.save(deps.storage, &freeze)?;
-    Ok(Response::new().add_attribute("action", "freezing-contracts"))
+    RATE.save(deps.storage, &rate)?;
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_rate"),
+        attr("rate", rate.to_string()),
+    ]))
 }
 
-fn check_expiration(
-    expiration: &Option<Expiration>,
-    block: &BlockInfo,
-) -> Result<(), ContractError> {
-    match expiration {
-        Some(e) => {
-            if e.is_expired(block) {
-                return Err(ContractError::MsgExpirationError {});
-            }
-            Ok(())
-        }
-        None => Ok(()),
+fn validate_depeg_protection(
+    deps: Deps,
+    params: DepegProtectionParams,
+) -> Result<DepegProtection, ContractError> {
+    let max_outputs = [params.max_token1_output, params.max_token2_output];
+    let max_output_too_high = max_outputs
+        .iter()
+        .flatten()
+        .any(|max| *max > Decimal::one());
+    if params.price_band > Decimal::one() || max_output_too_high {
+        return Err(ContractError::InvalidDepegProtection {});
     }
-}
 
+    Ok(DepegProtection {
+        price_source: deps.api.addr_validate(&params.price_source)?,
+        price_band: params.price_band,
+        max_token1_output: params.max_token1_output,
+        max_token2_output: params.max_token2_output,
+    })
+}
 
-pub fn execute_add_liquidity(
+pub fn execute_update_depeg_protection(
     deps: DepsMut,
-    info: &MessageInfo,
-    env: Env,
-    min_liquidity: Uint128,
-    token1_amount: Uint128,
-    token2_amount: Uint128,
-    expiration: Option<Expiration>,
+    info: MessageInfo,
+    depeg_protection: Option<DepegProtectionParams>,
 ) -> Result<Response, ContractError> {
-    check_expiration(&expiration, &env.block)?;
+    assert_owner(deps.as_ref(), &info.sender)?;
 
-    let token1 = TOKEN1.load(deps.storage)?;
-    let token2 = TOKEN2.load(deps.storage)?;
+    let depeg_protection = depeg_protection
+        .map(|params| validate_depeg_protection(deps.as_ref(), params))
+        .transpose()?;
+    DEPEG_PROTECTION.save(deps.storage, &depeg_protection)?;
 
-    let liquidity_amount = token1_amount+token2_amount;
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_depeg_protection"),
+        attr(
+            "price_source",
+            depeg_protection.map_or_else(
+                || "none".to_string(),
+                |protection| protection.price_source.into_string(),
+            ),
+        ),
+    ]))
+}
 
-    if liquidity_amount < min_liquidity {
-        return Err(ContractError::MinLiquidityError {
-            min_liquidity,
-            liquidity_available: liquidity_amount,
-        });
-    }
+// Rejects swaps while the external price is off the peg and swaps buying too much of a reserve
+fn check_depeg_protection(
+    deps: Deps,
+    input_token: &TokenSelect,
+    output_amount: Uint128,
+    output_reserve: Uint128,
+) -> Result<(), ContractError> {
+    let protection = match DEPEG_PROTECTION.may_load(deps.storage)?.flatten() {
+        Some(protection) => protection,
+        None => return Ok(()),
+    };
 
-    // Generate cw20 transfer messages if necessary
-    let mut transfer_msgs: Vec<CosmosMsg> = vec![];
-    if let Cw20(addr) = token1.denom {
-        transfer_msgs.push(get_cw20_transfer_from_msg(
-            &info.sender,
-            &env.contract.address,
-            &addr,
-            token1_amount,
-        )?)
-    }
-    if let Cw20(addr) = token2.denom.clone() {
-        transfer_msgs.push(get_cw20_transfer_from_msg(
-            &info.sender,
-            &env.contract.address,
-            &addr,
-            token2_amount,
-        )?)
+    let max_output = match input_token {
+        TokenSelect::Token1 => protection.max_token2_output,
+        TokenSelect::Token2 => protection.max_token1_output,
+    };
+    if let Some(max_output) = max_output {
+        let max = max_output * output_reserve;
+        if output_amount > max {
+            return Err(ContractError::MaxOutputError {
+                max,
+                output: output_amount,
+            });
+        }
     }
 
+    // The band is relative to the rate the pool swaps at
+    let rate = RATE.load(deps.storage)?;
+    let resp: PriceResponse = deps
+        .querier
+        .query_wasm_smart(&protection.price_source, &PriceSourceQueryMsg::Price {})?;
+    let lower_bound = rate * (Decimal::one() - protection.price_band);
+    let upper_bound = rate * (Decimal::one() + protection.price_band);
+    if resp.price < lower_bound || resp.price > upper_bound {
+        return Err(ContractError::PriceOutOfBand {
+            price: resp.price,
+            rate,
+            price_band: protection.price_band,
+        });
+    }
 
-    TOKEN1.update(deps.storage, |mut token1| -> Result<_, ContractError> {
-        token1.reserve += token1_amount;
-        Ok(token1)
-    })?;
-    TOKEN2.update(deps.storage, |mut token2| -> Result<_, ContractError> {
-        token2.reserve += token2_amount;
-        Ok(token2)
-    })?;
-
-    Ok(Response::new()
-        .add_messages(transfer_msgs)
-        .add_attributes(vec![
-            attr("token1_amount", token1_amount),
-            attr("token2_amount", token2_amount),
-            attr("liquidity_received", liquidity_amount),
-        ]))
+    Ok(())
 }
 
-fn get_cw20_transfer_from_msg(
-    owner: &Addr,
-    recipient: &Addr,
-    token_addr: &Addr,
-    token_amount: Uint128,
-) -> StdResult<CosmosMsg> {
-    // create transfer cw20 msg
-    let transfer_cw20_msg = Cw20ExecuteMsg::TransferFrom {
-        owner: owner.into(),
-        recipient: recipient.into(),
-        amount: token_amount,
-    };
-    let exec_cw20_transfer = WasmMsg::Execute {
-        contract_addr: token_addr.into(),
-        msg: to_binary(&transfer_cw20_msg)?,
-        funds: vec![],
-    };
-    let cw20_transfer_cosmos_msg: CosmosMsg = exec_cw20_transfer.into();
-    Ok(cw20_transfer_cosmos_msg)
+pub fn execute_add_liquidity(
+    deps: DepsMut,
+    info: &MessageInfo,
+    env: Env,
+    min_liquidity: Uint128,
+    token1_amount: Uint128,
+    token2_amount: Uint128,
+    expiration: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let curve = load_curve(deps.as_ref())?;
+    let deposit = provide_liquidity(
+        deps,
+        info,
+        &env,
+        &curve,
+        token1_amount,
+        token2_amount,
+        min_liquidity,
+        expiration,
+        None,
+    )?;
+    Ok(deposit.response)
 }
-
 pub fn execute_update_config(
     deps: DepsMut,
     info: MessageInfo,
-    new_owner: Option<String>,
     lp_fee_percent: Decimal,
     protocol_fee_percent: Decimal,
     protocol_fee_recipient: String,
 ) -> Result<Response, ContractError> {
-    let owner = OWNER.load(deps.storage)?;
-    if Some(info.sender) != owner {
-        return Err(ContractError::Unauthorized {});
-    }
-
-    let new_owner_addr = new_owner
-        .as_ref()
-        .map(|h| deps.api.addr_validate(h))
-        .transpose()?;
-    OWNER.save(deps.storage, &new_owner_addr)?;
-
-    let total_fee_percent = lp_fee_percent + protocol_fee_percent;
-    let max_fee_percent = Decimal::from_str(MAX_FEE_PERCENT)?;
-    if total_fee_percent > max_fee_percent {
-        return Err(ContractError::FeesTooHigh {
-            max_fee_percent,
-            total_fee_percent,
-        });
-    }
-
-    let protocol_fee_recipient = deps.api.addr_validate(&protocol_fee_recipient)?;
-    let updated_fees = Fees {
-        protocol_fee_recipient: protocol_fee_recipient.clone(),
+    let update_config_event = update_fees(
+        deps,
+        &info.sender,
         lp_fee_percent,
         protocol_fee_percent,
-    };
-    FEES.save(deps.storage, &updated_fees)?;
+        protocol_fee_recipient.clone(),
+    )?;
 
-    let new_owner = new_owner.unwrap_or_default();
-    Ok(Response::new().add_attributes(vec![
-        attr("new_owner", new_owner),
-        attr("lp_fee_percent", lp_fee_percent.to_string()),
-        attr("protocol_fee_percent", protocol_fee_percent.to_string()),
-        attr("protocol_fee_recipient", protocol_fee_recipient.to_string()),
-    ]))
+    Ok(Response::new()
+        .add_event(update_config_event)
+        .add_attributes(vec![
+            attr("lp_fee_percent", lp_fee_percent.to_string()),
+            attr("protocol_fee_percent", protocol_fee_percent.to_string()),
+            attr("protocol_fee_recipient", protocol_fee_recipient),
+        ]))
 }
-
 pub fn execute_remove_liquidity(
     deps: DepsMut,
     info: MessageInfo,
@@ -313,155 +354,16 @@ pub fn execute_remove_liquidity(
     min_token2: Uint128,
     expiration: Option<Expiration>,
 ) -> Result<Response, ContractError> {
-    check_expiration(&expiration, &env.block)?;
-
-
-    let token1 = TOKEN1.load(deps.storage)?;
-    let token2 = TOKEN2.load(deps.storage)?;
-
-    let total_token_supply = token1.reserve+token2.reserve;
-
-    if amount > total_token_supply {
-        return Err(ContractError::InsufficientLiquidityError {
-            requested: amount,
-            available: total_token_supply,
-        });
-    }
-
-
-    let token1_amount = amount
-        .checked_mul(token1.reserve)
-        .map_err(StdError::overflow)?
-        .checked_div(total_token_supply)
-        .map_err(StdError::divide_by_zero)?;
-    if token1_amount < min_token1 {
-        return Err(ContractError::MinToken1Error {
-            requested: min_token1,
-            available: token1_amount,
-        });
-    }
-
-    let token2_amount = amount
-        .checked_mul(token2.reserve)
-        .map_err(StdError::overflow)?
-        .checked_div(total_token_supply)
-        .map_err(StdError::divide_by_zero)?;
-    if token2_amount < min_token2 {
-        return Err(ContractError::MinToken2Error {
-            requested: min_token2,
-            available: token2_amount,
-        });
-    }
-
-    TOKEN1.update(deps.storage, |mut token1| -> Result<_, ContractError> {
-        token1.reserve = token1
-            .reserve
-            .checked_sub(token1_amount)
-            .map_err(StdError::overflow)?;
-        Ok(token1)
-    })?;
-
-    TOKEN2.update(deps.storage, |mut token2| -> Result<_, ContractError> {
-        token2.reserve = token2
-            .reserve
-            .checked_sub(token2_amount)
-            .map_err(StdError::overflow)?;
-        Ok(token2)
-    })?;
-
-    let token1_transfer_msg = match token1.denom {
-        Denom::Cw20(addr) => get_cw20_transfer_to_msg(&info.sender, &addr, token1_amount)?,
-        Denom::Native(_denom) => {unimplemented!()},
-    };
-    let token2_transfer_msg = match token2.denom {
-        Denom::Cw20(addr) => get_cw20_transfer_to_msg(&info.sender, &addr, token2_amount)?,
-        Denom::Native(_denom) => {unimplemented!()},
-    };
-
-    Ok(Response::new()
-        .add_messages(vec![
-            token1_transfer_msg,
-            token2_transfer_msg,
-        ])
-        .add_attributes(vec![
-            attr("liquidity_burned", amount),
-            attr("token1_returned", token1_amount),
-            attr("token2_returned", token2_amount),
-        ]))
-}
-
-fn get_cw20_transfer_to_msg(
-    recipient: &Addr,
-    token_addr: &Addr,
-    token_amount: Uint128,
-) -> StdResult<CosmosMsg> {
-    // create transfer cw20 msg
-    let transfer_cw20_msg = Cw20ExecuteMsg::Transfer {
-        recipient: recipient.into(),
-        amount: token_amount,
-    };
-    let exec_cw20_transfer = WasmMsg::Execute {
-        contract_addr: token_addr.into(),
-        msg: to_binary(&transfer_cw20_msg)?,
-        funds: vec![],
-    };
-    let cw20_transfer_cosmos_msg: CosmosMsg = exec_cw20_transfer.into();
-    Ok(cw20_transfer_cosmos_msg)
-}
-
-
-fn get_fee_transfer_msg(
-    sender: &Addr,
-    recipient: &Addr,
-    fee_denom: &Denom,
-    amount: Uint128,
-) -> StdResult<CosmosMsg> {
-    match fee_denom {
-        Denom::Cw20(addr) => get_cw20_transfer_from_msg(sender, recipient, addr, amount),
-        Denom::Native(_denom) => {unimplemented!()},
-    }
-}
-
-fn fee_decimal_to_uint128(decimal: Decimal) -> StdResult<Uint128> {
-    let result: Uint128 = decimal
-        .atomics()
-        .checked_mul(FEE_SCALE_FACTOR)
-        .map_err(StdError::overflow)?;
-
-    Ok(result / FEE_DECIMAL_PRECISION)
-}
-
-fn get_input_price(
-    input_amount: Uint128,
-    input_reserve: Uint128,
-    output_reserve: Uint128,
-    _fee_percent: Decimal,
-) -> StdResult<Uint128> {
-    if input_reserve == Uint128::zero() || output_reserve == Uint128::zero() {
-        return Err(StdError::generic_err("No liquidity"));
-    };
-
-   let k= input_reserve.checked_add(output_reserve).map_err(StdError::overflow)?;
-   let new_input_reserve=input_reserve.checked_add(input_amount).map_err(StdError::overflow)?;
-   let new_output_reserve=k.checked_sub(new_input_reserve).map_err(StdError::overflow)?;
-   let output_amount=output_reserve.checked_sub(new_output_reserve).map_err(StdError::overflow)?;
-   let fee=output_amount.checked_mul(Uint128::new(3).checked_div(Uint128::new(100)).map_err(StdError::divide_by_zero)?).map_err(StdError::overflow)?;
-   let new_output_amount=output_amount-fee;
-   Ok(new_output_amount)
-   
+    let curve = load_curve(deps.as_ref())?;
+    let withdrawal = withdraw_liquidity(
+        deps, &info, &env, &curve, amount, min_token1, min_token2, expiration, false,
+    )?;
+    Ok(withdrawal.response)
 }
-
-fn get_protocol_fee_amount(input_amount: Uint128, fee_percent: Decimal) -> StdResult<Uint128> {
-    if fee_percent.is_zero() {
-        return Ok(Uint128::zero());
-    }
-
-    let fee_percent = fee_decimal_to_uint128(fee_percent)?;
-    Ok(input_amount
-        .full_mul(fee_percent)
-        .checked_div(Uint256::from(FEE_SCALE_FACTOR))
-        .map_err(StdError::divide_by_zero)?
-        .try_into()?)
+fn load_curve(deps: Deps) -> StdResult<ConstantSum> {
+    Ok(ConstantSum {
+        rate: RATE.load(deps.storage)?,
+    })
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -469,100 +371,46 @@ pub fn execute_swap(
     deps: DepsMut,
     info: &MessageInfo,
     input_amount: Uint128,
-    _env: Env,
+    env: Env,
     input_token_enum: TokenSelect,
     recipient: String,
     min_token: Uint128,
     expiration: Option<Expiration>,
 ) -> Result<Response, ContractError> {
-    check_expiration(&expiration, &_env.block)?;
-
-    let input_token_item = match input_token_enum {
-        TokenSelect::Token1 => TOKEN1,
-        TokenSelect::Token2 => TOKEN2,
-    };
-    let input_token = input_token_item.load(deps.storage)?;
-    let output_token_item = match input_token_enum {
-        TokenSelect::Token1 => TOKEN2,
-        TokenSelect::Token2 => TOKEN1,
-    };
-    let output_token = output_token_item.load(deps.storage)?;
+    check_expiration(&expiration, &env.block)?;
 
+    let (input_token, output_token) = get_swap_tokens(deps.storage, &input_token_enum)?;
+    validate_input_amounts(&info.funds, &[(input_amount, &input_token.denom)])?;
 
-    let fees = FEES.load(deps.storage)?;
-    let total_fee_percent = fees.lp_fee_percent + fees.protocol_fee_percent;
-    let token_bought = get_input_price(
+    let swap = get_swap(
+        deps.storage,
+        &load_curve(deps.as_ref())?,
+        input_token_enum.clone(),
         input_amount,
-        input_token.reserve,
+        min_token,
+    )?;
+    check_depeg_protection(
+        deps.as_ref(),
+        &input_token_enum,
+        swap.output_amount,
         output_token.reserve,
-        total_fee_percent,
     )?;
 
-    if min_token > token_bought {
-        return Err(ContractError::SwapMinError {
-            min: min_token,
-            available: token_bought,
-        });
-    }
-    // Calculate fees
-    let protocol_fee_amount = get_protocol_fee_amount(input_amount, fees.protocol_fee_percent)?;
-    let input_amount_minus_protocol_fee = input_amount - protocol_fee_amount;
-
-    let mut msgs = match input_token.denom.clone() {
-        Denom::Cw20(addr) => vec![get_cw20_transfer_from_msg(
-            &info.sender,
-            &_env.contract.address,
-            &addr,
-            input_amount_minus_protocol_fee,
-        )?],
-        Denom::Native(_) => vec![],
-    };
-
-    // Send protocol fee to protocol fee recipient
-    if !protocol_fee_amount.is_zero() {
-        msgs.push(get_fee_transfer_msg(
-            &info.sender,
-            &fees.protocol_fee_recipient,
-            &input_token.denom,
-            protocol_fee_amount,
-        )?)
-    }
+    let mut msgs = commit_swap(deps.storage, info, &env, &swap, false)?;
 
     let recipient = deps.api.addr_validate(&recipient)?;
     // Create transfer to message
-    msgs.push(match output_token.denom {
-        Denom::Cw20(addr) => get_cw20_transfer_to_msg(&recipient, &addr, token_bought)?,
-        Denom::Native(_denom) => {unimplemented!()},
-    });
-
-    input_token_item.update(
-        deps.storage,
-        |mut input_token| -> Result<_, ContractError> {
-            input_token.reserve = input_token
-                .reserve
-                .checked_add(input_amount_minus_protocol_fee)
-                .map_err(StdError::overflow)?;
-            Ok(input_token)
-        },
-    )?;
-
-    output_token_item.update(
-        deps.storage,
-        |mut output_token| -> Result<_, ContractError> {
-            output_token.reserve = output_token
-                .reserve
-                .checked_sub(token_bought)
-                .map_err(StdError::overflow)?;
-            Ok(output_token)
-        },
-    )?;
+    msgs.push(get_transfer_to_msg(
+        &recipient,
+        &output_token.denom,
+        swap.output_amount,
+    )?);
 
     Ok(Response::new().add_messages(msgs).add_attributes(vec![
         attr("native_sold", input_amount),
-        attr("token_bought", token_bought),
+        attr("token_bought", swap.output_amount),
     ]))
 }
-
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
@@ -575,37 +423,34 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             to_binary(&query_token2_for_token1_price(deps, token2_amount)?)
         }
         QueryMsg::Fee {} => to_binary(&query_fee(deps)?),
+        QueryMsg::Ownership {} => to_binary(&query_ownership(deps)?),
+        QueryMsg::ProtocolFees {} => to_binary(&query_protocol_fees(deps)?),
+        QueryMsg::DepegProtection {} => to_binary(&query_depeg_protection(deps)?),
     }
 }
 
 pub fn query_info(deps: Deps) -> StdResult<InfoResponse> {
-    let token1 = TOKEN1.load(deps.storage)?;
-    let token2 = TOKEN2.load(deps.storage)?;
-  
-    // TODO get total supply
+    let info = pool_core::pool::query_info(deps)?;
+
     Ok(InfoResponse {
-        token1_reserve: token1.reserve,
-        token1_denom: token1.denom,
-        token2_reserve: token2.reserve,
-        token2_denom: token2.denom,
-       
+        token1_reserve: info.token1_reserve,
+        token1_denom: info.token1_denom,
+        token2_reserve: info.token2_reserve,
+        token2_denom: info.token2_denom,
+        lp_token_supply: info.lp_token_supply,
+        lp_token_address: info.lp_token_address,
+        rate: RATE.load(deps.storage)?,
     })
 }
-
 pub fn query_token1_for_token2_price(
     deps: Deps,
     token1_amount: Uint128,
 ) -> StdResult<Token1ForToken2PriceResponse> {
-    let token1 = TOKEN1.load(deps.storage)?;
-    let token2 = TOKEN2.load(deps.storage)?;
-
-    let fees = FEES.load(deps.storage)?;
-    let total_fee_percent = fees.lp_fee_percent + fees.protocol_fee_percent;
-    let token2_amount = get_input_price(
+    let token2_amount = get_swap_output(
+        deps.storage,
+        &load_curve(deps)?,
+        &TokenSelect::Token1,
         token1_amount,
-        token1.reserve,
-        token2.reserve,
-        total_fee_percent,
     )?;
     Ok(Token1ForToken2PriceResponse { token2_amount })
 }
@@ -614,82 +459,24 @@ pub fn query_token2_for_token1_price(
     deps: Deps,
     token2_amount: Uint128,
 ) -> StdResult<Token2ForToken1PriceResponse> {
-    let token1 = TOKEN1.load(deps.storage)?;
-    let token2 = TOKEN2.load(deps.storage)?;
-
-    let fees = FEES.load(deps.storage)?;
-    let total_fee_percent = fees.lp_fee_percent + fees.protocol_fee_percent;
-    let token1_amount = get_input_price(
+    let token1_amount = get_swap_output(
+        deps.storage,
+        &load_curve(deps)?,
+        &TokenSelect::Token2,
         token2_amount,
-        token2.reserve,
-        token1.reserve,
-        total_fee_percent,
     )?;
     Ok(Token2ForToken1PriceResponse { token1_amount })
 }
 
-pub fn query_fee(deps: Deps) -> StdResult<FeeResponse> {
-    let fees = FEES.load(deps.storage)?;
-    let owner = OWNER.load(deps.storage)?.map(|o| o.into_string());
+pub fn query_depeg_protection(deps: Deps) -> StdResult<DepegProtectionResponse> {
+    let depeg_protection = DEPEG_PROTECTION.may_load(deps.storage)?.flatten();
 
-    Ok(FeeResponse {
-        owner,
-        lp_fee_percent: fees.lp_fee_percent,
-        protocol_fee_percent: fees.protocol_fee_percent,
-        protocol_fee_recipient: fees.protocol_fee_recipient.into_string(),
+    Ok(DepegProtectionResponse {
+        depeg_protection: depeg_protection.map(|protection| DepegProtectionParams {
+            price_source: protection.price_source.into_string(),
+            price_band: protection.price_band,
+            max_token1_output: protection.max_token1_output,
+            max_token2_output: protection.max_token2_output,
+        }),
     })
 }
-
-
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-
-    #[test]
-    fn test_get_input_price() {
-        let fee_percent = Decimal::from_str("0.03").unwrap();
-        // Base case
-        assert_eq!(
-            get_input_price(
-                Uint128::new(10),
-                Uint128::new(100),
-                Uint128::new(100),
-                fee_percent
-            )
-            .unwrap(),
-            Uint128::new(10)
-        );
-
-        // No input reserve error
-        let err = get_input_price(
-            Uint128::new(10),
-            Uint128::new(0),
-            Uint128::new(100),
-            fee_percent,
-        )
-        .unwrap_err();
-        assert_eq!(err, StdError::generic_err("No liquidity"));
-
-        // No output reserve error
-        let err = get_input_price(
-            Uint128::new(10),
-            Uint128::new(100),
-            Uint128::new(0),
-            fee_percent,
-        )
-        .unwrap_err();
-        assert_eq!(err, StdError::generic_err("No liquidity"));
-
-        // No reserve error
-        let err = get_input_price(
-            Uint128::new(10),
-            Uint128::new(0),
-            Uint128::new(0),
-            fee_percent,
-        )
-        .unwrap_err();
-        assert_eq!(err, StdError::generic_err("No liquidity"));
-    }
-}
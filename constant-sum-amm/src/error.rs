@@ -1,4 +1,6 @@
 use cosmwasm_std::{Decimal, StdError, Uint128};
+use cw0::PaymentError;
+use pool_core::PoolError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,6 +8,9 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Pool(PoolError),
+
     #[error("{0}")]
     Cw20Error(#[from] cw20_base::ContractError),
 
@@ -16,66 +21,36 @@ pub enum ContractError {
     Unauthorized {},
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
-    #[error("Min liquidity error: requested: {min_liquidity}, available: {liquidity_available}")]
-    MinLiquidityError {
-        min_liquidity: Uint128,
-        liquidity_available: Uint128,
-    },
-
-    #[error("Max token error: max_token: {max_token}, tokens_required: {tokens_required}")]
-    MaxTokenError {
-        max_token: Uint128,
-        tokens_required: Uint128,
-    },
-
-    #[error("Insufficient liquidity error: requested: {requested}, available: {available}")]
-    InsufficientLiquidityError {
-        requested: Uint128,
-        available: Uint128,
-    },
-
-    #[error("Min token1 error: requested: {requested}, available: {available}")]
-    MinToken1Error {
-        requested: Uint128,
-        available: Uint128,
-    },
-
-    #[error("Min token2 error: requested: {requested}, available: {available}")]
-    MinToken2Error {
-        requested: Uint128,
-        available: Uint128,
-    },
-
-    #[error("Incorrect native denom: provided: {provided}, required: {required}")]
-    IncorrectNativeDenom { provided: String, required: String },
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
 
-    #[error("Swap min error: min: {min}, available: {available}")]
-    SwapMinError { min: Uint128, available: Uint128 },
+    #[error("The output amm provided is invalid")]
+    InvalidOutputPool {},
 
-    #[error("MsgExpirationError")]
-    MsgExpirationError {},
+    #[error("Invalid depeg protection: price band and max outputs must be at most 1")]
+    InvalidDepegProtection {},
 
-    #[error("Total fee ({total_fee_percent}) percent is higher than max ({max_fee_percent})")]
-    FeesTooHigh {
-        max_fee_percent: Decimal,
-        total_fee_percent: Decimal,
+    #[error("Depeg protection: price {price} is more than {price_band} off the rate {rate}")]
+    PriceOutOfBand {
+        price: Decimal,
+        rate: Decimal,
+        price_band: Decimal,
     },
 
-    #[error("InsufficientFunds")]
-    InsufficientFunds {},
-
-    #[error("Uknown reply id: {id}")]
-    UnknownReplyId { id: u64 },
+    #[error("Invalid rate: the rate must be above 0")]
+    InvalidRate {},
 
-    #[error("Failed to instantiate lp token")]
-    InstantiateLpTokenError {},
-
-    #[error("The output amm provided is invalid")]
-    InvalidOutputPool {},
-
-    #[error("Unauthorized pool freeze - sender is not an owner or owner has not been set")]
-    UnauthorizedPoolFreeze {},
+    #[error("Depeg protection: output {output} is above the max output of {max}")]
+    MaxOutputError { max: Uint128, output: Uint128 },
+}
 
-    #[error("This pools is frozen - you can not deposit or swap tokens")]
-    FrozenPool {},
+impl From<PoolError> for ContractError {
+    fn from(err: PoolError) -> Self {
+        match err {
+            PoolError::Std(err) => Self::Std(err),
+            // Same error whether the pool or the contract rejects the sender
+            PoolError::Unauthorized {} => Self::Unauthorized {},
+            err => Self::Pool(err),
+        }
+    }
 }
@@ -4,11 +4,17 @@ use std::borrow::BorrowMut;
 
 use crate::error::ContractError;
 use cosmwasm_std::{coins, Addr, Coin, Decimal, Empty, Uint128};
+use cw0::PaymentError;
 use cw20::{Cw20Coin, Cw20Contract, Cw20ExecuteMsg, Denom};
 use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+use pool_core::PoolError;
 use std::str::FromStr;
 
-use crate::msg::{ExecuteMsg, FeeResponse, InfoResponse, InstantiateMsg, QueryMsg, TokenSelect};
+use crate::msg::{
+    DepegProtectionParams, DepegProtectionResponse, ExecuteMsg, FeeResponse, InfoResponse,
+    InstantiateMsg, PriceResponse, PriceSourceQueryMsg, ProtocolFeesResponse, QueryMsg,
+    Token1ForToken2PriceResponse, Token2ForToken1PriceResponse, TokenSelect,
+};
 
 fn mock_app() -> App {
     App::default()
@@ -19,7 +25,8 @@ pub fn contract_amm() -> Box<dyn Contract<Empty>> {
         crate::contract::execute,
         crate::contract::instantiate,
         crate::contract::query,
-    );
+    )
+    .with_reply(crate::contract::reply);
     Box::new(contract)
 }
 
@@ -32,6 +39,51 @@ pub fn contract_cw20() -> Box<dyn Contract<Empty>> {
     Box::new(contract)
 }
 
+// Price source stub for the depeg protection, anyone can set the price
+mod price_source {
+    use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+    use cw_storage_plus::Item;
+
+    use crate::msg::{PriceResponse, PriceSourceQueryMsg};
+
+    const PRICE: Item<PriceResponse> = Item::new("price");
+
+    pub fn instantiate(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: PriceResponse,
+    ) -> StdResult<Response> {
+        PRICE.save(deps.storage, &msg)?;
+        Ok(Response::new())
+    }
+
+    pub fn execute(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: PriceResponse,
+    ) -> StdResult<Response> {
+        PRICE.save(deps.storage, &msg)?;
+        Ok(Response::new())
+    }
+
+    pub fn query(deps: Deps, _env: Env, msg: PriceSourceQueryMsg) -> StdResult<Binary> {
+        match msg {
+            PriceSourceQueryMsg::Price {} => to_binary(&PRICE.load(deps.storage)?),
+        }
+    }
+}
+
+pub fn contract_price_source() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        price_source::execute,
+        price_source::instantiate,
+        price_source::query,
+    );
+    Box::new(contract)
+}
+
 fn get_info(router: &App, contract_addr: &Addr) -> InfoResponse {
     router
         .wrap()
@@ -66,6 +118,8 @@ fn create_amm(
         lp_fee_percent,
         protocol_fee_percent,
         protocol_fee_recipient,
+        depeg_protection: None,
+        rate: None,
     };
     router
         .instantiate_contract(amm_id, owner.clone(), &msg, &[], "amm", None)
@@ -162,6 +216,8 @@ fn test_instantiate() {
         lp_fee_percent,
         protocol_fee_percent,
         protocol_fee_recipient: owner.to_string(),
+        depeg_protection: None,
+        rate: None,
     };
     let err = router
         .instantiate_contract(amm_id, owner.clone(), &msg, &[], "amm", None)
@@ -169,15 +225,14 @@ fn test_instantiate() {
         .downcast()
         .unwrap();
     assert_eq!(
-        ContractError::FeesTooHigh {
+        ContractError::Pool(PoolError::FeesTooHigh {
             max_fee_percent: Decimal::from_str("1").unwrap(),
             total_fee_percent: Decimal::from_str("1.01").unwrap()
-        },
+        }),
         err
     );
 }
 
-
 #[test]
 fn update_config() {
     let mut router = mock_app();
@@ -213,7 +268,6 @@ fn update_config() {
     let lp_fee_percent = Decimal::from_str("0.15").unwrap();
     let protocol_fee_percent = Decimal::from_str("0.15").unwrap();
     let msg = ExecuteMsg::UpdateConfig {
-        owner: Some(owner.to_string()),
         protocol_fee_recipient: "new_fee_recpient".to_string(),
         lp_fee_percent,
         protocol_fee_percent,
@@ -232,7 +286,6 @@ fn update_config() {
     let lp_fee_percent = Decimal::from_str("1.01").unwrap();
     let protocol_fee_percent = Decimal::zero();
     let msg = ExecuteMsg::UpdateConfig {
-        owner: Some(owner.to_string()),
         protocol_fee_recipient: "new_fee_recpient".to_string(),
         lp_fee_percent,
         protocol_fee_percent,
@@ -243,10 +296,10 @@ fn update_config() {
         .downcast()
         .unwrap();
     assert_eq!(
-        ContractError::FeesTooHigh {
+        ContractError::Pool(PoolError::FeesTooHigh {
             max_fee_percent: Decimal::from_str("1").unwrap(),
             total_fee_percent: Decimal::from_str("1.01").unwrap()
-        },
+        }),
         err
     );
 
@@ -254,7 +307,6 @@ fn update_config() {
     let lp_fee_percent = Decimal::from_str("0.21").unwrap();
     let protocol_fee_percent = Decimal::from_str("0.09").unwrap();
     let msg = ExecuteMsg::UpdateConfig {
-        owner: Some(owner.to_string()),
         protocol_fee_recipient: owner.to_string(),
         lp_fee_percent,
         protocol_fee_percent,
@@ -271,9 +323,8 @@ fn update_config() {
         .unwrap();
     assert_eq!(ContractError::Unauthorized {}, err);
 
-    // Try updating owner and fee params
+    // Update the fee params, the owner only moves once the proposed owner accepts
     let msg = ExecuteMsg::UpdateConfig {
-        owner: Some("new_owner".to_string()),
         protocol_fee_recipient: owner.to_string(),
         lp_fee_percent,
         protocol_fee_percent,
@@ -282,6 +333,27 @@ fn update_config() {
         .execute_contract(owner.clone(), amm_addr.clone(), &msg, &[])
         .unwrap();
 
+    let new_owner = Addr::unchecked("new_owner");
+    let msg = ExecuteMsg::ProposeNewOwner {
+        owner: new_owner.to_string(),
+        expiry: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm_addr.clone(), &msg, &[])
+        .unwrap();
+    assert_eq!(
+        get_fee(&router, &amm_addr).owner.unwrap(),
+        owner.to_string()
+    );
+    router
+        .execute_contract(
+            new_owner,
+            amm_addr.clone(),
+            &ExecuteMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap();
+
     let fee = get_fee(&router, &amm_addr);
     assert_eq!(fee.protocol_fee_recipient, owner.to_string());
     assert_eq!(fee.protocol_fee_percent, protocol_fee_percent);
@@ -331,7 +403,7 @@ fn test_pass_through_swap() {
     // Add initial liquidity to both pools
     let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
         spender: amm.to_string(),
-        amount: Uint128::new(100),
+        amount: Uint128::new(1000),
         expires: None,
     };
     let _res = router
@@ -342,9 +414,9 @@ fn test_pass_through_swap() {
         .unwrap();
 
     let add_liquidity_msg = ExecuteMsg::AddLiquidity {
-        token1_amount: Uint128::new(100),
-        min_liquidity: Uint128::new(100),
-        token2_amount: Uint128::new(100),
+        token1_amount: Uint128::new(1000),
+        min_liquidity: Uint128::new(1000),
+        token2_amount: Uint128::new(1000),
         expiration: None,
     };
     router
@@ -381,10 +453,11 @@ fn test_pass_through_swap() {
 
     // ensure balances updated
     let token1_balance = token1.balance(&router, owner.clone()).unwrap();
-    assert_eq!(token1_balance, Uint128::new(4890));
+    assert_eq!(token1_balance, Uint128::new(3990));
 
+    // 10 in, 9.997 out, rounded down in favour of the pool
     let token2_balance = token2.balance(&router, owner.clone()).unwrap();
-    assert_eq!(token2_balance, Uint128::new(4910));
+    assert_eq!(token2_balance, Uint128::new(4009));
 
     let amm_native_balance = bank_balance(&mut router, &amm, NATIVE_TOKEN_DENOM.to_string());
     assert_eq!(amm_native_balance.amount, Uint128::zero());
@@ -397,5 +470,823 @@ fn test_pass_through_swap() {
     println!("{} {}", token1_balance, token2_balance);
     assert_eq!(info_amm.token2_reserve, token2_balance);
     assert_eq!(info_amm.token1_reserve, token1_balance);
+}
+
+#[test]
+fn test_lp_earns_fees() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+    let provider = Addr::unchecked("provider");
+    let trader = Addr::unchecked("trader");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(21_000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(21_000),
+    );
+
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        Decimal::from_str("0.3").unwrap(),
+        Decimal::zero(),
+        owner.to_string(),
+    );
+    let info = get_info(&router, &amm);
+    let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+
+    for token in [&token1, &token2] {
+        for recipient in [&provider, &trader] {
+            let transfer_msg = Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount: Uint128::new(10_000),
+            };
+            router
+                .execute_contract(owner.clone(), token.addr(), &transfer_msg, &[])
+                .unwrap();
+        }
+        let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+            spender: amm.to_string(),
+            amount: Uint128::new(10_000),
+            expires: None,
+        };
+        for sender in [&owner, &provider, &trader] {
+            router
+                .execute_contract(sender.clone(), token.addr(), &allowance_msg, &[])
+                .unwrap();
+        }
+    }
+
+    // The owner seeds the pool, the first deposit locks the minimum liquidity
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(1_000),
+        token2_amount: Uint128::new(1_000),
+        min_liquidity: Uint128::new(1_000),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10_000),
+        token2_amount: Uint128::new(10_000),
+        min_liquidity: Uint128::new(20_000),
+        expiration: None,
+    };
+    router
+        .execute_contract(provider.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+    let shares = lp_token.balance(&router, provider.clone()).unwrap();
+    assert_eq!(shares, Uint128::new(20_000));
+    assert_eq!(
+        get_info(&router, &amm).lp_token_supply,
+        Uint128::new(22_000)
+    );
+
+    // Swap back and forth, each swap leaves 0.3% of the input in the pool
+    for input_token in [TokenSelect::Token1, TokenSelect::Token2] {
+        let swap_msg = ExecuteMsg::Swap {
+            input_token,
+            input_amount: Uint128::new(5_000),
+            min_output: Uint128::new(4_985),
+            expiration: None,
+        };
+        router
+            .execute_contract(trader.clone(), amm.clone(), &swap_msg, &[])
+            .unwrap();
+    }
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(11_015));
+    assert_eq!(info.token2_reserve, Uint128::new(11_015));
+    assert_eq!(
+        token1.balance(&router, trader.clone()).unwrap(),
+        Uint128::new(9_985)
+    );
+    assert_eq!(
+        token2.balance(&router, trader.clone()).unwrap(),
+        Uint128::new(9_985)
+    );
+
+    // Withdrawing more shares than were minted fails
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: shares,
+        expires: None,
+    };
+    router
+        .execute_contract(provider.clone(), lp_token.addr(), &allowance_msg, &[])
+        .unwrap();
+    let remove_liquidity_msg = |amount: Uint128| ExecuteMsg::RemoveLiquidity {
+        amount,
+        min_token1: Uint128::new(10_001),
+        min_token2: Uint128::new(10_001),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(
+            provider.clone(),
+            amm.clone(),
+            &remove_liquidity_msg(shares + Uint128::new(1)),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::Pool(PoolError::InsufficientLiquidityError {
+            requested: shares + Uint128::new(1),
+            available: shares,
+        }),
+        err
+    );
+
+    // The provider withdraws exactly its shares and gets more than it deposited
+    router
+        .execute_contract(
+            provider.clone(),
+            amm.clone(),
+            &remove_liquidity_msg(shares),
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        lp_token.balance(&router, provider.clone()).unwrap(),
+        Uint128::zero()
+    );
+    let token1_balance = token1.balance(&router, provider.clone()).unwrap();
+    let token2_balance = token2.balance(&router, provider.clone()).unwrap();
+    assert_eq!(token1_balance, Uint128::new(10_013));
+    assert_eq!(token2_balance, Uint128::new(10_013));
+    assert!(token1_balance + token2_balance > Uint128::new(20_000));
+}
+
+#[test]
+fn test_depeg_protection() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(100_000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(100_000),
+    );
+
+    let price_source_id = router.store_code(contract_price_source());
+    let set_price = |price: &str| PriceResponse {
+        price: Decimal::from_str(price).unwrap(),
+    };
+    let price_source = router
+        .instantiate_contract(
+            price_source_id,
+            owner.clone(),
+            &set_price("1"),
+            &[],
+            "price_source",
+            None,
+        )
+        .unwrap();
+    let resp: PriceResponse = router
+        .wrap()
+        .query_wasm_smart(&price_source, &PriceSourceQueryMsg::Price {})
+        .unwrap();
+    assert_eq!(resp, set_price("1"));
+
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Cw20(token1.addr()),
+        Denom::Cw20(token2.addr()),
+        Decimal::zero(),
+        Decimal::zero(),
+        owner.to_string(),
+    );
+
+    for token in [&token1, &token2] {
+        let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+            spender: amm.to_string(),
+            amount: Uint128::new(100_000),
+            expires: None,
+        };
+        router
+            .execute_contract(owner.clone(), token.addr(), &allowance_msg, &[])
+            .unwrap();
+    }
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10_000),
+        token2_amount: Uint128::new(10_000),
+        min_liquidity: Uint128::zero(),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+
+    // Swaps into token2 can buy at most 10% of the token2 reserve
+    let depeg_protection = DepegProtectionParams {
+        price_source: price_source.to_string(),
+        price_band: Decimal::from_str("0.02").unwrap(),
+        max_token1_output: None,
+        max_token2_output: Some(Decimal::from_str("0.1").unwrap()),
+    };
+    let update_msg = ExecuteMsg::UpdateDepegProtection {
+        depeg_protection: Some(depeg_protection.clone()),
+    };
+    let err = router
+        .execute_contract(Addr::unchecked("anyone"), amm.clone(), &update_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    router
+        .execute_contract(owner.clone(), amm.clone(), &update_msg, &[])
+        .unwrap();
+    let resp: DepegProtectionResponse = router
+        .wrap()
+        .query_wasm_smart(&amm, &QueryMsg::DepegProtection {})
+        .unwrap();
+    assert_eq!(resp.depeg_protection, Some(depeg_protection.clone()));
+
+    let swap_msg = |input_token: TokenSelect, input_amount: u128| ExecuteMsg::Swap {
+        input_token,
+        input_amount: Uint128::new(input_amount),
+        min_output: Uint128::zero(),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(TokenSelect::Token1, 1_000),
+            &[],
+        )
+        .unwrap();
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(TokenSelect::Token1, 1_000),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::MaxOutputError {
+            max: Uint128::new(900),
+            output: Uint128::new(1_000)
+        },
+        err
+    );
+    // The other direction is not limited
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(TokenSelect::Token2, 5_000),
+            &[],
+        )
+        .unwrap();
+
+    // token1 depegs, draining the token2 reserve is blocked in both directions
+    router
+        .execute_contract(owner.clone(), price_source.clone(), &set_price("0.97"), &[])
+        .unwrap();
+    for input_token in [TokenSelect::Token1, TokenSelect::Token2] {
+        let err = router
+            .execute_contract(owner.clone(), amm.clone(), &swap_msg(input_token, 10), &[])
+            .unwrap_err()
+            .downcast()
+            .unwrap();
+        assert_eq!(
+            ContractError::PriceOutOfBand {
+                price: Decimal::from_str("0.97").unwrap(),
+                rate: Decimal::one(),
+                price_band: Decimal::from_str("0.02").unwrap()
+            },
+            err
+        );
+    }
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(6_000));
+    assert_eq!(info.token2_reserve, Uint128::new(14_000));
+
+    // The band is inclusive
+    router
+        .execute_contract(owner.clone(), price_source.clone(), &set_price("1.02"), &[])
+        .unwrap();
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(TokenSelect::Token1, 10),
+            &[],
+        )
+        .unwrap();
+
+    // Bands wider than the price itself are rejected
+    let update_msg = ExecuteMsg::UpdateDepegProtection {
+        depeg_protection: Some(DepegProtectionParams {
+            price_band: Decimal::from_str("1.5").unwrap(),
+            ..depeg_protection
+        }),
+    };
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &update_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::InvalidDepegProtection {}, err);
+
+    // Without the protection the depegged pool can be swapped again
+    let update_msg = ExecuteMsg::UpdateDepegProtection {
+        depeg_protection: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &update_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), price_source, &set_price("0.5"), &[])
+        .unwrap();
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg(TokenSelect::Token1, 10),
+            &[],
+        )
+        .unwrap();
+    let resp: DepegProtectionResponse = router
+        .wrap()
+        .query_wasm_smart(&amm, &QueryMsg::DepegProtection {})
+        .unwrap();
+    assert_eq!(resp.depeg_protection, None);
+}
+
+#[test]
+fn test_rate() {
+    let mut router = mock_app();
+
+    let owner = Addr::unchecked("owner");
+
+    let token1 = create_cw20(
+        &mut router,
+        &owner,
+        "token1".to_string(),
+        "TOKENONE".to_string(),
+        Uint128::new(100_000),
+    );
+    let token2 = create_cw20(
+        &mut router,
+        &owner,
+        "token2".to_string(),
+        "TOKENTWO".to_string(),
+        Uint128::new(100_000),
+    );
+
+    // token1 is a wrapped token2 worth 1.05 token2
+    let cw20_id = router.store_code(contract_cw20());
+    let amm_id = router.store_code(contract_amm());
+    let mut msg = InstantiateMsg {
+        token1_denom: Denom::Cw20(token1.addr()),
+        token2_denom: Denom::Cw20(token2.addr()),
+        lp_token_code_id: cw20_id,
+        owner: Some(owner.to_string()),
+        lp_fee_percent: Decimal::zero(),
+        protocol_fee_percent: Decimal::zero(),
+        protocol_fee_recipient: owner.to_string(),
+        depeg_protection: None,
+        rate: Some(Decimal::zero()),
+    };
+    let err = router
+        .instantiate_contract(amm_id, owner.clone(), &msg, &[], "amm", None)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::InvalidRate {}, err);
+    msg.rate = Some(Decimal::from_str("1.05").unwrap());
+    let amm = router
+        .instantiate_contract(amm_id, owner.clone(), &msg, &[], "amm", None)
+        .unwrap();
+
+    for token in [&token1, &token2] {
+        let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+            spender: amm.to_string(),
+            amount: Uint128::new(100_000),
+            expires: None,
+        };
+        router
+            .execute_contract(owner.clone(), token.addr(), &allowance_msg, &[])
+            .unwrap();
+    }
+
+    // Liquidity is valued in token2
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(10_000),
+        token2_amount: Uint128::new(10_500),
+        min_liquidity: Uint128::new(20_000),
+        expiration: None,
+    };
+    let res = router
+        .execute_contract(owner.clone(), amm.clone(), &add_liquidity_msg, &[])
+        .unwrap();
+    let event = res.events.iter().find(|e| e.ty == "wasm").unwrap();
+    assert!(event
+        .attributes
+        .iter()
+        .any(|a| a.key == "liquidity_received" && a.value == "20000"));
+
+    let resp: Token1ForToken2PriceResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm,
+            &QueryMsg::Token1ForToken2Price {
+                token1_amount: Uint128::new(1_000),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.token2_amount, Uint128::new(1_050));
+    let resp: Token2ForToken1PriceResponse = router
+        .wrap()
+        .query_wasm_smart(
+            &amm,
+            &QueryMsg::Token2ForToken1Price {
+                token2_amount: Uint128::new(1_050),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.token1_amount, Uint128::new(1_000));
+
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(1_000),
+        min_output: Uint128::new(1_050),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap();
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(11_000));
+    assert_eq!(info.token2_reserve, Uint128::new(9_450));
+    assert_eq!(info.rate, Decimal::from_str("1.05").unwrap());
+
+    // Only the owner can change the rate
+    let update_msg = ExecuteMsg::UpdateRate {
+        rate: Decimal::from_str("1.1").unwrap(),
+    };
+    let err = router
+        .execute_contract(Addr::unchecked("anyone"), amm.clone(), &update_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::Unauthorized {}, err);
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::UpdateRate {
+                rate: Decimal::zero(),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(ContractError::InvalidRate {}, err);
+    router
+        .execute_contract(owner.clone(), amm.clone(), &update_msg, &[])
+        .unwrap();
+
+    // Half of the 21_000 supply withdraws half of each reserve whatever the rate
+    let info = get_info(&router, &amm);
+    assert_eq!(info.lp_token_supply, Uint128::new(21_000));
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(10_500),
+        expires: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            Addr::unchecked(info.lp_token_address),
+            &allowance_msg,
+            &[],
+        )
+        .unwrap();
+    let remove_liquidity_msg = ExecuteMsg::RemoveLiquidity {
+        amount: Uint128::new(10_500),
+        min_token1: Uint128::new(5_500),
+        min_token2: Uint128::new(4_725),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap();
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(5_500));
+    assert_eq!(info.token2_reserve, Uint128::new(4_725));
+
+    // The depeg protection band is around the rate
+    let price_source_id = router.store_code(contract_price_source());
+    let price_source = router
+        .instantiate_contract(
+            price_source_id,
+            owner.clone(),
+            &PriceResponse {
+                price: Decimal::from_str("1.12").unwrap(),
+            },
+            &[],
+            "price_source",
+            None,
+        )
+        .unwrap();
+    let update_msg = ExecuteMsg::UpdateDepegProtection {
+        depeg_protection: Some(DepegProtectionParams {
+            price_source: price_source.to_string(),
+            price_band: Decimal::from_str("0.02").unwrap(),
+            max_token1_output: None,
+            max_token2_output: None,
+        }),
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &update_msg, &[])
+        .unwrap();
+    router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap();
+    let price = PriceResponse {
+        price: Decimal::one(),
+    };
+    router
+        .execute_contract(owner.clone(), price_source.clone(), &price, &[])
+        .unwrap();
+    let err = router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        ContractError::PriceOutOfBand {
+            price: Decimal::one(),
+            rate: Decimal::from_str("1.1").unwrap(),
+            price_band: Decimal::from_str("0.02").unwrap()
+        },
+        err
+    );
+
+    // The band edges are rate * (1 - price_band) = 1.078 and rate * (1 + price_band) = 1.122
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(100),
+        min_output: Uint128::new(110),
+        expiration: None,
+    };
+    for (price, in_band) in [
+        ("1.078", true),
+        ("1.122", true),
+        ("1.0779", false),
+        ("1.1221", false),
+    ] {
+        let price = Decimal::from_str(price).unwrap();
+        router
+            .execute_contract(
+                owner.clone(),
+                price_source.clone(),
+                &PriceResponse { price },
+                &[],
+            )
+            .unwrap();
+        let res = router.execute_contract(owner.clone(), amm.clone(), &swap_msg, &[]);
+        if in_band {
+            res.unwrap();
+        } else {
+            assert_eq!(
+                ContractError::PriceOutOfBand {
+                    price,
+                    rate: Decimal::from_str("1.1").unwrap(),
+                    price_band: Decimal::from_str("0.02").unwrap()
+                },
+                res.unwrap_err().downcast().unwrap()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_native_cw20_pool() {
+    let mut router = mock_app();
+
+    const NATIVE_TOKEN_DENOM: &str = "juno";
+    const IBC_TOKEN_DENOM: &str = "atom";
+
+    let owner = Addr::unchecked("owner");
+    let fee_recipient = Addr::unchecked("fee_recipient");
+    let funds = vec![
+        Coin::new(5000, NATIVE_TOKEN_DENOM),
+        Coin::new(5000, IBC_TOKEN_DENOM),
+    ];
+    router.borrow_mut().init_modules(|router, _, storage| {
+        router.bank.init_balance(storage, &owner, funds).unwrap()
+    });
+
+    let cw20_token = create_cw20(
+        &mut router,
+        &owner,
+        "token".to_string(),
+        "CWTOKEN".to_string(),
+        Uint128::new(5000),
+    );
+
+    let amm = create_amm(
+        &mut router,
+        &owner,
+        Denom::Native(NATIVE_TOKEN_DENOM.into()),
+        Denom::Cw20(cw20_token.addr()),
+        Decimal::from_str("0.2").unwrap(),
+        Decimal::from_str("0.1").unwrap(),
+        fee_recipient.to_string(),
+    );
+
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(5000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), cw20_token.addr(), &allowance_msg, &[])
+        .unwrap();
+
+    let add_liquidity_msg = ExecuteMsg::AddLiquidity {
+        token1_amount: Uint128::new(2000),
+        token2_amount: Uint128::new(2000),
+        min_liquidity: Uint128::new(3000),
+        expiration: None,
+    };
+    // The native funds must match the deposit
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &coins(1000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::InsufficientFunds {}),
+        err.downcast().unwrap()
+    );
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &[
+                Coin::new(2000, NATIVE_TOKEN_DENOM),
+                Coin::new(10, IBC_TOKEN_DENOM),
+            ],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Pool(PoolError::UnexpectedFunds {
+            denom: IBC_TOKEN_DENOM.to_string(),
+        }),
+        err.downcast().unwrap()
+    );
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &add_liquidity_msg,
+            &coins(2000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+
+    // Native input, the protocol fee stays in the pool until its recipient collects it
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token1,
+        input_amount: Uint128::new(1000),
+        min_output: Uint128::new(997),
+        expiration: None,
+    };
+    router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &swap_msg,
+            &coins(1000, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap();
+    let info = get_info(&router, &amm);
+    assert_eq!(info.token1_reserve, Uint128::new(2999));
+    assert_eq!(info.token2_reserve, Uint128::new(1003));
+    let protocol_fees: ProtocolFeesResponse = router
+        .wrap()
+        .query_wasm_smart(&amm, &QueryMsg::ProtocolFees {})
+        .unwrap();
+    assert_eq!(protocol_fees.token1_amount, Uint128::new(1));
+    assert_eq!(protocol_fees.token2_amount, Uint128::zero());
+
+    // Only the protocol fee recipient can collect
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &ExecuteMsg::CollectProtocolFees {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::Unauthorized {}, err.downcast().unwrap());
+    router
+        .execute_contract(
+            fee_recipient.clone(),
+            amm.clone(),
+            &ExecuteMsg::CollectProtocolFees {},
+            &[],
+        )
+        .unwrap();
+    let fee_balance = bank_balance(&mut router, &fee_recipient, NATIVE_TOKEN_DENOM.to_string());
+    assert_eq!(fee_balance.amount, Uint128::new(1));
+
+    // Native output
+    let swap_msg = ExecuteMsg::Swap {
+        input_token: TokenSelect::Token2,
+        input_amount: Uint128::new(500),
+        min_output: Uint128::new(498),
+        expiration: None,
+    };
+    router
+        .execute_contract(owner.clone(), amm.clone(), &swap_msg, &[])
+        .unwrap();
+    let owner_balance = bank_balance(&mut router, &owner, NATIVE_TOKEN_DENOM.to_string());
+    assert_eq!(owner_balance.amount, Uint128::new(2498));
+
+    // Withdrawals don't take funds
+    let info = get_info(&router, &amm);
+    let lp_token = Cw20Contract(Addr::unchecked(info.lp_token_address));
+    let allowance_msg = Cw20ExecuteMsg::IncreaseAllowance {
+        spender: amm.to_string(),
+        amount: Uint128::new(3000),
+        expires: None,
+    };
+    router
+        .execute_contract(owner.clone(), lp_token.addr(), &allowance_msg, &[])
+        .unwrap();
+    let remove_liquidity_msg = ExecuteMsg::RemoveLiquidity {
+        amount: Uint128::new(3000),
+        min_token1: Uint128::new(1875),
+        min_token2: Uint128::new(1127),
+        expiration: None,
+    };
+    let err = router
+        .execute_contract(
+            owner.clone(),
+            amm.clone(),
+            &remove_liquidity_msg,
+            &coins(10, NATIVE_TOKEN_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Payment(PaymentError::NonPayable {}),
+        err.downcast().unwrap()
+    );
+    router
+        .execute_contract(owner.clone(), amm.clone(), &remove_liquidity_msg, &[])
+        .unwrap();
+    let owner_balance = bank_balance(&mut router, &owner, NATIVE_TOKEN_DENOM.to_string());
+    assert_eq!(owner_balance.amount, Uint128::new(4373));
+    assert_eq!(
+        cw20_token.balance(&router, owner.clone()).unwrap(),
+        Uint128::new(4624)
+    );
 
+    // The locked minimum liquidity keeps its share of the reserves in the pool
+    let info = get_info(&router, &amm);
+    assert_eq!(info.lp_token_supply, Uint128::new(1000));
+    let amm_balance = bank_balance(&mut router, &amm, NATIVE_TOKEN_DENOM.to_string());
+    assert_eq!(amm_balance.amount, info.token1_reserve);
+    assert_eq!(info.token1_reserve, Uint128::new(626));
+    assert_eq!(info.token2_reserve, Uint128::new(376));
 }
@@ -15,14 +15,29 @@ pub struct InstantiateMsg {
     // NOTE: Fees percents are out of 100 e.g., 1 = 1%
     pub protocol_fee_percent: Decimal,
     pub lp_fee_percent: Decimal,
+    // None leaves the pool without depeg protection
+    pub depeg_protection: Option<DepegProtectionParams>,
+    // Price of token1 in token2 the pool swaps at, None is 1:1
+    pub rate: Option<Decimal>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub enum TokenSelect {
-    Token1,
-    Token2,
+pub struct DepegProtectionParams {
+    // Contract answering PriceSourceQueryMsg::Price with the price of token1 in token2
+    pub price_source: String,
+    // Swaps are rejected while the price is outside rate * (1 ± price_band)
+    pub price_band: Decimal,
+    // Maximum output of a single swap relative to the output reserve, e.g., 0.1 = 10%
+    pub max_token1_output: Option<Decimal>,
+    pub max_token2_output: Option<Decimal>,
 }
 
+pub use pool_core::msg::{
+    FeeResponse, OwnershipResponse, ProtocolFeesResponse, Token1ForToken2PriceResponse,
+    Token2ForToken1PriceResponse,
+};
+pub use pool_core::TokenSelect;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
@@ -44,22 +59,48 @@ pub enum ExecuteMsg {
         min_output: Uint128,
         expiration: Option<Expiration>,
     },
+    // Target of a pass through swap from another pool
+    SwapAndSendTo {
+        input_token: TokenSelect,
+        input_amount: Uint128,
+        recipient: String,
+        min_token: Uint128,
+        expiration: Option<Expiration>,
+    },
     UpdateConfig {
-        owner: Option<String>,
         lp_fee_percent: Decimal,
         protocol_fee_percent: Decimal,
         protocol_fee_recipient: String,
     },
-    // Freeze adding new deposits
+    // Send the accrued protocol fees to the protocol fee recipient
+    CollectProtocolFees {},
+    // Propose a new owner, who has to accept before the expiry
+    ProposeNewOwner {
+        owner: String,
+        expiry: Option<Expiration>,
+    },
+    AcceptOwnership {},
+    CancelOwnershipProposal {},
+    // Remove the owner for good, the config can no longer be updated
+    RenounceOwnership {},
+    // Pause or unpause deposits and swaps together
     FreezeDeposits {
         freeze: bool,
     },
+    // None removes the depeg protection
+    UpdateDepegProtection {
+        depeg_protection: Option<DepegProtectionParams>,
+    },
+    // Change the price of token1 in token2 the pool swaps at
+    UpdateRate {
+        rate: Decimal,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    /// Implements CW20. Returns the current balance of the given address, 0 if unset.
+    /// Returns the lp token balance of the given address, 0 if unset.
     Balance {
         address: String,
     },
@@ -71,6 +112,22 @@ pub enum QueryMsg {
         token2_amount: Uint128,
     },
     Fee {},
+    Ownership {},
+    ProtocolFees {},
+    DepegProtection {},
+}
+
+// Query a depeg protection price source has to implement
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSourceQueryMsg {
+    Price {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PriceResponse {
+    // Price of token1 in token2
+    pub price: Decimal,
 }
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct MigrateMsg {
@@ -87,22 +144,12 @@ pub struct InfoResponse {
     pub token1_denom: Denom,
     pub token2_reserve: Uint128,
     pub token2_denom: Denom,
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
-pub struct FeeResponse {
-    pub owner: Option<String>,
-    pub lp_fee_percent: Decimal,
-    pub protocol_fee_percent: Decimal,
-    pub protocol_fee_recipient: String,
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct Token1ForToken2PriceResponse {
-    pub token2_amount: Uint128,
+    pub lp_token_supply: Uint128,
+    pub lp_token_address: String,
+    pub rate: Decimal,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct Token2ForToken1PriceResponse {
-    pub token1_amount: Uint128,
+pub struct DepegProtectionResponse {
+    pub depeg_protection: Option<DepegProtectionParams>,
 }
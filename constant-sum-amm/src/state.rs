@@ -1,29 +1,27 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Decimal, Uint128};
-use cw20::Denom;
+use cosmwasm_std::{Addr, Decimal};
 use cw_storage_plus::Item;
 
+pub use pool_core::state::{
+    Fees, OwnershipProposal, PauseFlags, ProtocolFees, Token, FEES, LP_TOKEN, OWNER,
+    OWNERSHIP_PROPOSAL, PAUSE_FLAGS, PROTOCOL_FEES, TOKEN1, TOKEN2,
+};
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct Token {
-    pub reserve: Uint128,
-    pub denom: Denom,
-}
-
-pub const TOKEN1: Item<Token> = Item::new("token1");
-pub const TOKEN2: Item<Token> = Item::new("token2");
-
-pub const OWNER: Item<Option<Addr>> = Item::new("owner");
+// Price of token1 in token2 that swaps and liquidity are valued at, 1 for a 1:1 pool
+pub const RATE: Item<Decimal> = Item::new("rate");
 
+// Guards the 1:1 peg, fractions of 1 e.g., 0.02 = 2%
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct Fees {
-    pub protocol_fee_recipient: Addr,
-    pub protocol_fee_percent: Decimal,
-    pub lp_fee_percent: Decimal,
+pub struct DepegProtection {
+    // Contract answering PriceSourceQueryMsg::Price with the price of token1 in token2
+    pub price_source: Addr,
+    // Swaps are rejected while the price is outside rate * (1 ± price_band)
+    pub price_band: Decimal,
+    // Maximum output of a single swap relative to the output reserve, None disables a limit
+    pub max_token1_output: Option<Decimal>,
+    pub max_token2_output: Option<Decimal>,
 }
 
-pub const FEES: Item<Fees> = Item::new("fees");
-
-pub const FROZEN: Item<bool> = Item::new("frozen");
+pub const DEPEG_PROTECTION: Item<Option<DepegProtection>> = Item::new("depeg_protection");
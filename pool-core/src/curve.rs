@@ -0,0 +1,607 @@
+use cosmwasm_std::{Decimal, Fraction, Isqrt, StdError, StdResult, Uint128, Uint256, Uint512};
+use std::convert::TryInto;
+
+use crate::fee::{fee_decimal_to_uint128, FEE_SCALE_FACTOR};
+use crate::msg::TokenSelect;
+
+// Pricing and liquidity accounting of a two token pool. Reserves of a swap are given in the
+// direction of the swap, input_token is the token sold. Fee percents are out of 100 and taken
+// from the input.
+pub trait PoolCurve {
+    // Output of selling input_amount, rounded down
+    fn swap_output(
+        &self,
+        input_token: &TokenSelect,
+        input_amount: Uint128,
+        input_reserve: Uint128,
+        output_reserve: Uint128,
+        fee_percent: Decimal,
+    ) -> StdResult<Uint128>;
+
+    // Inverse of swap_output, the smallest input that buys at least output_amount
+    fn swap_input(
+        &self,
+        input_token: &TokenSelect,
+        output_amount: Uint128,
+        input_reserve: Uint128,
+        output_reserve: Uint128,
+        fee_percent: Decimal,
+    ) -> StdResult<Uint128>;
+
+    // Liquidity minted for a deposit, pro-rata to the reserves by default. The first deposit
+    // into an empty pool mints sqrt(token1_amount * token2_amount).
+    fn mint_liquidity(
+        &self,
+        token1_amount: Uint128,
+        token2_amount: Uint128,
+        token1_reserve: Uint128,
+        token2_reserve: Uint128,
+        liquidity_supply: Uint128,
+    ) -> StdResult<Uint128> {
+        if liquidity_supply.is_zero() {
+            return Ok(token1_amount.full_mul(token2_amount).isqrt().try_into()?);
+        }
+
+        let token1_liquidity = token1_amount
+            .full_mul(liquidity_supply)
+            .checked_div(Uint256::from(token1_reserve))
+            .map_err(StdError::divide_by_zero)?;
+        let token2_liquidity = token2_amount
+            .full_mul(liquidity_supply)
+            .checked_div(Uint256::from(token2_reserve))
+            .map_err(StdError::divide_by_zero)?;
+        Ok(std::cmp::min(token1_liquidity, token2_liquidity).try_into()?)
+    }
+
+    // Token2 taken by a deposit of token1_amount, max_token2 is what the provider offers and
+    // the caller rejects a larger amount. Pro-rata to the reserves by default, rounded up so
+    // deposits never dilute the pool ratio, the first deposit sets the ratio and takes it all.
+    fn deposit_token2(
+        &self,
+        token1_amount: Uint128,
+        max_token2: Uint128,
+        token1_reserve: Uint128,
+        token2_reserve: Uint128,
+        liquidity_supply: Uint128,
+    ) -> StdResult<Uint128> {
+        if liquidity_supply.is_zero() {
+            return Ok(max_token2);
+        }
+
+        Ok(token1_amount
+            .full_mul(token2_reserve)
+            .checked_add(Uint256::from(token1_reserve))
+            .map_err(StdError::overflow)?
+            .checked_sub(Uint256::from(1u8))
+            .map_err(StdError::overflow)?
+            .checked_div(Uint256::from(token1_reserve))
+            .map_err(StdError::divide_by_zero)?
+            .try_into()?)
+    }
+
+    // Token1 and token2 returned for burning amount of liquidity, pro-rata to the reserves
+    fn burn_liquidity(
+        &self,
+        amount: Uint128,
+        token1_reserve: Uint128,
+        token2_reserve: Uint128,
+        liquidity_supply: Uint128,
+    ) -> StdResult<(Uint128, Uint128)> {
+        let share = |reserve: Uint128| -> StdResult<Uint128> {
+            Ok(amount
+                .full_mul(reserve)
+                .checked_div(Uint256::from(liquidity_supply))
+                .map_err(StdError::divide_by_zero)?
+                .try_into()?)
+        };
+        Ok((share(token1_reserve)?, share(token2_reserve)?))
+    }
+}
+
+// x * y = k
+pub struct ConstantProduct;
+
+impl PoolCurve for ConstantProduct {
+    fn swap_output(
+        &self,
+        _input_token: &TokenSelect,
+        input_amount: Uint128,
+        input_reserve: Uint128,
+        output_reserve: Uint128,
+        fee_percent: Decimal,
+    ) -> StdResult<Uint128> {
+        if input_reserve == Uint128::zero() || output_reserve == Uint128::zero() {
+            return Err(StdError::generic_err("No liquidity"));
+        };
+
+        let fee_percent = fee_decimal_to_uint128(fee_percent)?;
+        let fee_reduction_percent = FEE_SCALE_FACTOR - fee_percent;
+        let input_amount_with_fee = Uint512::from(input_amount.full_mul(fee_reduction_percent));
+        let numerator = input_amount_with_fee
+            .checked_mul(Uint512::from(output_reserve))
+            .map_err(StdError::overflow)?;
+        let denominator = Uint512::from(input_reserve)
+            .checked_mul(Uint512::from(FEE_SCALE_FACTOR))
+            .map_err(StdError::overflow)?
+            .checked_add(input_amount_with_fee)
+            .map_err(StdError::overflow)?;
+
+        Ok(numerator
+            .checked_div(denominator)
+            .map_err(StdError::divide_by_zero)?
+            .try_into()?)
+    }
+
+    fn swap_input(
+        &self,
+        _input_token: &TokenSelect,
+        output_amount: Uint128,
+        input_reserve: Uint128,
+        output_reserve: Uint128,
+        fee_percent: Decimal,
+    ) -> StdResult<Uint128> {
+        if input_reserve == Uint128::zero() || output_reserve == Uint128::zero() {
+            return Err(StdError::generic_err("No liquidity"));
+        };
+        if output_amount >= output_reserve {
+            return Err(StdError::generic_err(
+                "Insufficient liquidity for the requested output",
+            ));
+        }
+
+        let fee_percent = fee_decimal_to_uint128(fee_percent)?;
+        let fee_reduction_percent = FEE_SCALE_FACTOR - fee_percent;
+        let numerator = Uint512::from(input_reserve.full_mul(output_amount))
+            .checked_mul(Uint512::from(FEE_SCALE_FACTOR))
+            .map_err(StdError::overflow)?;
+        let denominator =
+            Uint512::from((output_reserve - output_amount).full_mul(fee_reduction_percent));
+        if denominator.is_zero() {
+            return Err(StdError::generic_err("No liquidity"));
+        }
+
+        // Round up so the computed input always buys the requested output
+        Ok(numerator
+            .checked_add(denominator)
+            .map_err(StdError::overflow)?
+            .checked_sub(Uint512::from(1u8))
+            .map_err(StdError::overflow)?
+            .checked_div(denominator)
+            .map_err(StdError::divide_by_zero)?
+            .try_into()?)
+    }
+}
+
+// x * rate + y = k, token1 is worth rate token2
+pub struct ConstantSum {
+    pub rate: Decimal,
+}
+
+impl ConstantSum {
+    // Value of the token amounts in token2
+    pub fn liquidity_value(
+        &self,
+        token1_amount: Uint128,
+        token2_amount: Uint128,
+    ) -> StdResult<Uint128> {
+        let token1_value: Uint128 = token1_amount
+            .full_mul(self.rate.numerator())
+            .checked_div(Uint256::from(self.rate.denominator()))
+            .map_err(StdError::divide_by_zero)?
+            .try_into()?;
+
+        token1_value
+            .checked_add(token2_amount)
+            .map_err(StdError::overflow)
+    }
+
+    // Output tokens per input token as a fraction
+    fn exchange_rate(&self, input_token: &TokenSelect) -> (Uint128, Uint128) {
+        match input_token {
+            TokenSelect::Token1 => (self.rate.numerator(), self.rate.denominator()),
+            TokenSelect::Token2 => (self.rate.denominator(), self.rate.numerator()),
+        }
+    }
+}
+
+impl PoolCurve for ConstantSum {
+    fn swap_output(
+        &self,
+        input_token: &TokenSelect,
+        input_amount: Uint128,
+        input_reserve: Uint128,
+        output_reserve: Uint128,
+        fee_percent: Decimal,
+    ) -> StdResult<Uint128> {
+        if input_reserve == Uint128::zero() || output_reserve == Uint128::zero() {
+            return Err(StdError::generic_err("No liquidity"));
+        };
+
+        let (rate_numerator, rate_denominator) = self.exchange_rate(input_token);
+        let fee_percent = fee_decimal_to_uint128(fee_percent)?;
+        let fee_reduction_percent = FEE_SCALE_FACTOR - fee_percent;
+        let output_amount: Uint128 = input_amount
+            .full_mul(fee_reduction_percent)
+            .checked_mul(Uint256::from(rate_numerator))
+            .map_err(StdError::overflow)?
+            .checked_div(FEE_SCALE_FACTOR.full_mul(rate_denominator))
+            .map_err(StdError::divide_by_zero)?
+            .try_into()?;
+        if output_amount > output_reserve {
+            return Err(StdError::generic_err("Not enough liquidity"));
+        }
+
+        Ok(output_amount)
+    }
+
+    fn swap_input(
+        &self,
+        input_token: &TokenSelect,
+        output_amount: Uint128,
+        input_reserve: Uint128,
+        output_reserve: Uint128,
+        fee_percent: Decimal,
+    ) -> StdResult<Uint128> {
+        if input_reserve == Uint128::zero() || output_reserve == Uint128::zero() {
+            return Err(StdError::generic_err("No liquidity"));
+        };
+        if output_amount > output_reserve {
+            return Err(StdError::generic_err("Not enough liquidity"));
+        }
+
+        let (rate_numerator, rate_denominator) = self.exchange_rate(input_token);
+        let fee_percent = fee_decimal_to_uint128(fee_percent)?;
+        let fee_reduction_percent = FEE_SCALE_FACTOR - fee_percent;
+        let numerator = Uint512::from(output_amount.full_mul(FEE_SCALE_FACTOR))
+            .checked_mul(Uint512::from(rate_denominator))
+            .map_err(StdError::overflow)?;
+        let denominator = Uint512::from(fee_reduction_percent.full_mul(rate_numerator));
+        if denominator.is_zero() {
+            return Err(StdError::generic_err("No liquidity"));
+        }
+
+        // Round up so the computed input always buys the requested output
+        Ok(numerator
+            .checked_add(denominator)
+            .map_err(StdError::overflow)?
+            .checked_sub(Uint512::from(1u8))
+            .map_err(StdError::overflow)?
+            .checked_div(denominator)
+            .map_err(StdError::divide_by_zero)?
+            .try_into()?)
+    }
+
+    // Deposits in any ratio mint their value, so all of token2 is taken
+    fn deposit_token2(
+        &self,
+        _token1_amount: Uint128,
+        max_token2: Uint128,
+        _token1_reserve: Uint128,
+        _token2_reserve: Uint128,
+        _liquidity_supply: Uint128,
+    ) -> StdResult<Uint128> {
+        Ok(max_token2)
+    }
+
+    // Liquidity is the share of the deposit in the value of the reserves, the first deposit
+    // mints its value
+    fn mint_liquidity(
+        &self,
+        token1_amount: Uint128,
+        token2_amount: Uint128,
+        token1_reserve: Uint128,
+        token2_reserve: Uint128,
+        liquidity_supply: Uint128,
+    ) -> StdResult<Uint128> {
+        let deposit_value = self.liquidity_value(token1_amount, token2_amount)?;
+        if liquidity_supply.is_zero() {
+            return Ok(deposit_value);
+        }
+
+        let reserves_value = self.liquidity_value(token1_reserve, token2_reserve)?;
+        Ok(deposit_value
+            .full_mul(liquidity_supply)
+            .checked_div(Uint256::from(reserves_value))
+            .map_err(StdError::divide_by_zero)?
+            .try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_constant_product_swap_output() {
+        let fee_percent = Decimal::from_str("0.03").unwrap();
+        let swap_output = |input_amount: u128, input_reserve: u128, output_reserve: u128| {
+            ConstantProduct.swap_output(
+                &TokenSelect::Token1,
+                Uint128::new(input_amount),
+                Uint128::new(input_reserve),
+                Uint128::new(output_reserve),
+                fee_percent,
+            )
+        };
+        // Base case
+        assert_eq!(swap_output(10, 100, 100).unwrap(), Uint128::new(9));
+
+        // No input reserve error
+        let err = swap_output(10, 0, 100).unwrap_err();
+        assert_eq!(err, StdError::generic_err("No liquidity"));
+
+        // No output reserve error
+        let err = swap_output(10, 100, 0).unwrap_err();
+        assert_eq!(err, StdError::generic_err("No liquidity"));
+
+        // No reserve error
+        let err = swap_output(10, 0, 0).unwrap_err();
+        assert_eq!(err, StdError::generic_err("No liquidity"));
+    }
+
+    #[test]
+    fn test_constant_product_swap_input() {
+        let fee_percent = Decimal::from_str("0.3").unwrap();
+        let swap_input = |output_amount: u128, input_reserve: u128, output_reserve: u128| {
+            ConstantProduct.swap_input(
+                &TokenSelect::Token1,
+                Uint128::new(output_amount),
+                Uint128::new(input_reserve),
+                Uint128::new(output_reserve),
+                fee_percent,
+            )
+        };
+        // Base case
+        assert_eq!(swap_input(9, 100, 100).unwrap(), Uint128::new(10));
+
+        // The input is the smallest amount buying the output
+        for output in 1..1000u128 {
+            let input = swap_input(output, 12345, 6789).unwrap();
+            let bought = |input_amount: Uint128| {
+                ConstantProduct
+                    .swap_output(
+                        &TokenSelect::Token1,
+                        input_amount,
+                        Uint128::new(12345),
+                        Uint128::new(6789),
+                        fee_percent,
+                    )
+                    .unwrap()
+            };
+            assert!(bought(input) >= Uint128::new(output));
+            assert!(bought(input - Uint128::new(1)) < Uint128::new(output));
+        }
+
+        // Output above the reserve error
+        let err = swap_input(100, 100, 100).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Insufficient liquidity for the requested output")
+        );
+
+        // No reserve error
+        let err = swap_input(10, 0, 100).unwrap_err();
+        assert_eq!(err, StdError::generic_err("No liquidity"));
+    }
+
+    #[test]
+    fn test_constant_product_liquidity() {
+        // The first deposit mints sqrt(token1_amount * token2_amount)
+        let liquidity = ConstantProduct
+            .mint_liquidity(
+                Uint128::new(400),
+                Uint128::new(900),
+                Uint128::zero(),
+                Uint128::zero(),
+                Uint128::zero(),
+            )
+            .unwrap();
+        assert_eq!(liquidity, Uint128::new(600));
+
+        // Later deposits mint the smaller of the two shares
+        let liquidity = ConstantProduct
+            .mint_liquidity(
+                Uint128::new(100),
+                Uint128::new(300),
+                Uint128::new(400),
+                Uint128::new(900),
+                Uint128::new(600),
+            )
+            .unwrap();
+        assert_eq!(liquidity, Uint128::new(150));
+
+        // Deposits take token2 at the pool ratio rounded up, the first one takes all of it
+        let deposit_token2 = |token1_amount: u128, liquidity_supply: u128| {
+            ConstantProduct
+                .deposit_token2(
+                    Uint128::new(token1_amount),
+                    Uint128::new(1_000),
+                    Uint128::new(400),
+                    Uint128::new(900),
+                    Uint128::new(liquidity_supply),
+                )
+                .unwrap()
+        };
+        assert_eq!(deposit_token2(100, 0), Uint128::new(1_000));
+        assert_eq!(deposit_token2(100, 600), Uint128::new(225));
+        assert_eq!(deposit_token2(101, 600), Uint128::new(228));
+
+        let (token1_amount, token2_amount) = ConstantProduct
+            .burn_liquidity(
+                Uint128::new(150),
+                Uint128::new(500),
+                Uint128::new(1200),
+                Uint128::new(750),
+            )
+            .unwrap();
+        assert_eq!(token1_amount, Uint128::new(100));
+        assert_eq!(token2_amount, Uint128::new(240));
+    }
+
+    #[test]
+    fn test_constant_sum_swap_output() {
+        let fee_percent = Decimal::from_str("0.03").unwrap();
+        let swap_output = |rate: &str, input_token: TokenSelect, input_amount: u128| {
+            ConstantSum {
+                rate: Decimal::from_str(rate).unwrap(),
+            }
+            .swap_output(
+                &input_token,
+                Uint128::new(input_amount),
+                Uint128::new(100_000),
+                Uint128::new(100_000),
+                fee_percent,
+            )
+        };
+        // Base case
+        assert_eq!(
+            swap_output("1", TokenSelect::Token1, 10_000).unwrap(),
+            Uint128::new(9_997)
+        );
+
+        // The fee rounds in favour of the pool
+        assert_eq!(
+            swap_output("1", TokenSelect::Token1, 10).unwrap(),
+            Uint128::new(9)
+        );
+
+        // token1 is worth 1.05 token2
+        assert_eq!(
+            swap_output("1.05", TokenSelect::Token1, 10_000).unwrap(),
+            Uint128::new(10_496)
+        );
+        assert_eq!(
+            swap_output("1.05", TokenSelect::Token2, 10_500).unwrap(),
+            Uint128::new(9_997)
+        );
+
+        // Output larger than the output reserve
+        let err = swap_output("1", TokenSelect::Token1, 200_000).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Not enough liquidity"));
+
+        // No reserve error
+        let err = ConstantSum {
+            rate: Decimal::one(),
+        }
+        .swap_output(
+            &TokenSelect::Token1,
+            Uint128::new(10),
+            Uint128::new(0),
+            Uint128::new(100),
+            fee_percent,
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("No liquidity"));
+    }
+
+    #[test]
+    fn test_constant_sum_swap_input() {
+        let curve = ConstantSum {
+            rate: Decimal::from_str("1.05").unwrap(),
+        };
+        let fee_percent = Decimal::from_str("0.3").unwrap();
+        let reserve = Uint128::new(100_000);
+
+        // The input is the smallest amount buying the output
+        for input_token in [TokenSelect::Token1, TokenSelect::Token2] {
+            for output in 1..1000u128 {
+                let output = Uint128::new(output);
+                let input = curve
+                    .swap_input(&input_token, output, reserve, reserve, fee_percent)
+                    .unwrap();
+                let bought = |input_amount: Uint128| {
+                    curve
+                        .swap_output(&input_token, input_amount, reserve, reserve, fee_percent)
+                        .unwrap()
+                };
+                assert!(bought(input) >= output);
+                assert!(bought(input - Uint128::new(1)) < output);
+            }
+        }
+
+        // Output above the reserve error
+        let err = curve
+            .swap_input(
+                &TokenSelect::Token1,
+                Uint128::new(100_001),
+                reserve,
+                reserve,
+                fee_percent,
+            )
+            .unwrap_err();
+        assert_eq!(err, StdError::generic_err("Not enough liquidity"));
+    }
+
+    #[test]
+    fn test_constant_sum_liquidity() {
+        let curve = ConstantSum {
+            rate: Decimal::from_str("1.05").unwrap(),
+        };
+        assert_eq!(
+            curve
+                .liquidity_value(Uint128::new(1_000), Uint128::new(500))
+                .unwrap(),
+            Uint128::new(1_550)
+        );
+
+        // The first deposit mints its value
+        let liquidity = curve
+            .mint_liquidity(
+                Uint128::new(1_000),
+                Uint128::new(500),
+                Uint128::zero(),
+                Uint128::zero(),
+                Uint128::zero(),
+            )
+            .unwrap();
+        assert_eq!(liquidity, Uint128::new(1_550));
+
+        // Later deposits mint their share of the reserves, which are worth 10_350 here
+        let liquidity = curve
+            .mint_liquidity(
+                Uint128::new(1_000),
+                Uint128::new(500),
+                Uint128::new(7_000),
+                Uint128::new(3_000),
+                Uint128::new(10_350),
+            )
+            .unwrap();
+        assert_eq!(liquidity, Uint128::new(1_550));
+
+        // Once fees raised the value of the reserves, the same deposit mints fewer shares
+        let liquidity = curve
+            .mint_liquidity(
+                Uint128::new(1_000),
+                Uint128::new(500),
+                Uint128::new(7_000),
+                Uint128::new(3_000),
+                Uint128::new(9_000),
+            )
+            .unwrap();
+        assert_eq!(liquidity, Uint128::new(1_347));
+
+        // Deposits in any ratio take all of token2
+        let token2_amount = curve
+            .deposit_token2(
+                Uint128::new(1_000),
+                Uint128::new(5_000),
+                Uint128::new(7_000),
+                Uint128::new(3_000),
+                Uint128::new(10_350),
+            )
+            .unwrap();
+        assert_eq!(token2_amount, Uint128::new(5_000));
+
+        let (token1_amount, token2_amount) = curve
+            .burn_liquidity(
+                Uint128::new(1_035),
+                Uint128::new(7_000),
+                Uint128::new(3_000),
+                Uint128::new(10_350),
+            )
+            .unwrap();
+        assert_eq!(token1_amount, Uint128::new(700));
+        assert_eq!(token2_amount, Uint128::new(300));
+    }
+}
@@ -0,0 +1,87 @@
+use cosmwasm_std::{Decimal, StdError, Uint128};
+use thiserror::Error;
+
+// Errors of the shared pool flow, each AMM wraps them in its own ContractError
+#[derive(Error, Debug, PartialEq)]
+pub enum PoolError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Incorrect native denom: provided: {provided}, required: {required}")]
+    IncorrectNativeDenom { provided: String, required: String },
+
+    #[error("InsufficientFunds")]
+    InsufficientFunds {},
+
+    #[error("Unexpected funds: {denom} is not an input of this message")]
+    UnexpectedFunds { denom: String },
+
+    #[error("Max token error: max_token: {max_token}, tokens_required: {tokens_required}")]
+    MaxTokenError {
+        max_token: Uint128,
+        tokens_required: Uint128,
+    },
+
+    #[error("Min liquidity error: requested: {min_liquidity}, available: {liquidity_available}")]
+    MinLiquidityError {
+        min_liquidity: Uint128,
+        liquidity_available: Uint128,
+    },
+
+    #[error("Insufficient liquidity error: requested: {requested}, available: {available}")]
+    InsufficientLiquidityError {
+        requested: Uint128,
+        available: Uint128,
+    },
+
+    #[error("Min token1 error: requested: {requested}, available: {available}")]
+    MinToken1Error {
+        requested: Uint128,
+        available: Uint128,
+    },
+
+    #[error("Min token2 error: requested: {requested}, available: {available}")]
+    MinToken2Error {
+        requested: Uint128,
+        available: Uint128,
+    },
+
+    #[error("Swap min error: min: {min}, available: {available}")]
+    SwapMinError { min: Uint128, available: Uint128 },
+
+    #[error("MsgExpirationError")]
+    MsgExpirationError {},
+
+    #[error("Total fee ({total_fee_percent}) percent is higher than max ({max_fee_percent})")]
+    FeesTooHigh {
+        max_fee_percent: Decimal,
+        total_fee_percent: Decimal,
+    },
+
+    #[error("Uknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("Failed to instantiate lp token")]
+    InstantiateLpTokenError {},
+
+    #[error("No ownership proposal")]
+    NoOwnershipProposal {},
+
+    #[error("Ownership proposal expired")]
+    OwnershipProposalExpired {},
+
+    #[error("Unauthorized pool freeze - sender is not an owner or owner has not been set")]
+    UnauthorizedPoolFreeze {},
+
+    #[error("Deposits are paused")]
+    DepositsPaused {},
+
+    #[error("Swaps are paused")]
+    SwapsPaused {},
+
+    #[error("Withdrawals are paused")]
+    WithdrawalsPaused {},
+}
@@ -0,0 +1,59 @@
+use cosmwasm_std::{Decimal, StdError, StdResult, Uint128, Uint256};
+use std::convert::TryInto;
+
+// Fees are out of 100 e.g., 0.3 = 0.3%, FEE_SCALE_FACTOR is 100%
+pub const FEE_SCALE_FACTOR: Uint128 = Uint128::new(10_000);
+pub const MAX_FEE_PERCENT: &str = "1";
+const FEE_DECIMAL_PRECISION: Uint128 = Uint128::new(10u128.pow(20));
+
+// Fee percent in units of FEE_SCALE_FACTOR, precision below 0.01% is dropped
+pub fn fee_decimal_to_uint128(decimal: Decimal) -> StdResult<Uint128> {
+    let result: Uint128 = decimal
+        .atomics()
+        .checked_mul(FEE_SCALE_FACTOR)
+        .map_err(StdError::overflow)?;
+
+    Ok(result / FEE_DECIMAL_PRECISION)
+}
+
+// Fee taken from amount, rounded down
+pub fn get_fee_amount(amount: Uint128, fee_percent: Decimal) -> StdResult<Uint128> {
+    if fee_percent.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    let fee_percent = fee_decimal_to_uint128(fee_percent)?;
+    Ok(amount
+        .full_mul(fee_percent)
+        .checked_div(Uint256::from(FEE_SCALE_FACTOR))
+        .map_err(StdError::divide_by_zero)?
+        .try_into()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_get_fee_amount() {
+        let fee_percent = Decimal::from_str("0.3").unwrap();
+        assert_eq!(
+            fee_decimal_to_uint128(fee_percent).unwrap(),
+            Uint128::new(30)
+        );
+        assert_eq!(
+            get_fee_amount(Uint128::new(10_000), fee_percent).unwrap(),
+            Uint128::new(30)
+        );
+        // Rounded down
+        assert_eq!(
+            get_fee_amount(Uint128::new(333), fee_percent).unwrap(),
+            Uint128::zero()
+        );
+        assert_eq!(
+            get_fee_amount(Uint128::new(10_000), Decimal::zero()).unwrap(),
+            Uint128::zero()
+        );
+    }
+}
@@ -0,0 +1,14 @@
+pub mod curve;
+pub mod error;
+pub mod fee;
+pub mod lp_token;
+pub mod msg;
+pub mod ownership;
+pub mod pause;
+pub mod pool;
+pub mod state;
+pub mod transfer;
+
+pub use crate::curve::{ConstantProduct, ConstantSum, PoolCurve};
+pub use crate::error::PoolError;
+pub use crate::msg::TokenSelect;
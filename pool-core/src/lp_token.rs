@@ -0,0 +1,166 @@
+use cosmwasm_std::{
+    to_binary, Addr, CosmosMsg, Deps, DepsMut, Reply, Response, StdResult, SubMsg, Uint128, WasmMsg,
+};
+use cw0::parse_reply_instantiate_data;
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, MinterResponse, TokenInfoResponse};
+
+use crate::error::PoolError;
+use crate::state::LP_TOKEN;
+
+pub const INSTANTIATE_LP_TOKEN_REPLY_ID: u64 = 0;
+
+// Locked in the pool by the first deposit, so the supply never returns to zero
+pub const MINIMUM_LIQUIDITY: Uint128 = Uint128::new(1_000);
+
+// The pool is the only minter of its own lp token
+pub fn get_instantiate_lp_token_msg(code_id: u64, pool: &Addr) -> StdResult<SubMsg> {
+    let instantiate_lp_token_msg = WasmMsg::Instantiate {
+        code_id,
+        funds: vec![],
+        admin: None,
+        label: "lp_token".to_string(),
+        msg: to_binary(&cw20_base::msg::InstantiateMsg {
+            name: "CosmWasm_Liquidity_Token".into(),
+            symbol: "cwlp".into(),
+            decimals: 6,
+            initial_balances: vec![],
+            mint: Some(MinterResponse {
+                minter: pool.into(),
+                cap: None,
+            }),
+            marketing: None,
+        })?,
+    };
+    Ok(SubMsg::reply_on_success(
+        instantiate_lp_token_msg,
+        INSTANTIATE_LP_TOKEN_REPLY_ID,
+    ))
+}
+
+pub fn save_lp_token(deps: DepsMut, msg: Reply) -> Result<Response, PoolError> {
+    if msg.id != INSTANTIATE_LP_TOKEN_REPLY_ID {
+        return Err(PoolError::UnknownReplyId { id: msg.id });
+    };
+    let res = parse_reply_instantiate_data(msg);
+    match res {
+        Ok(res) => {
+            // Validate contract address
+            let cw20_addr = deps.api.addr_validate(&res.contract_address)?;
+
+            // Save lp token
+            LP_TOKEN.save(deps.storage, &cw20_addr)?;
+
+            Ok(Response::new().add_attribute("lp_token_address", cw20_addr))
+        }
+        Err(_) => Err(PoolError::InstantiateLpTokenError {}),
+    }
+}
+
+// Liquidity minted for a deposit, the first one locks MINIMUM_LIQUIDITY in the pool
+pub fn get_liquidity_amount(
+    liquidity: Uint128,
+    liquidity_supply: Uint128,
+    min_liquidity: Uint128,
+) -> Result<Uint128, PoolError> {
+    let liquidity_amount = if !liquidity_supply.is_zero() {
+        liquidity
+    } else if liquidity > MINIMUM_LIQUIDITY {
+        liquidity - MINIMUM_LIQUIDITY
+    } else {
+        return Err(PoolError::MinLiquidityError {
+            min_liquidity: MINIMUM_LIQUIDITY,
+            liquidity_available: liquidity,
+        });
+    };
+    if liquidity_amount < min_liquidity {
+        return Err(PoolError::MinLiquidityError {
+            min_liquidity,
+            liquidity_available: liquidity_amount,
+        });
+    }
+    Ok(liquidity_amount)
+}
+
+// Mints liquidity_amount to recipient, and MINIMUM_LIQUIDITY to the pool on the first deposit
+pub fn get_mint_msgs(
+    pool: &Addr,
+    recipient: &Addr,
+    liquidity_amount: Uint128,
+    liquidity_supply: Uint128,
+    lp_token_address: &Addr,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut mint_msgs = vec![];
+    if liquidity_supply.is_zero() {
+        mint_msgs.push(mint_lp_tokens(pool, MINIMUM_LIQUIDITY, lp_token_address)?);
+    }
+    mint_msgs.push(mint_lp_tokens(
+        recipient,
+        liquidity_amount,
+        lp_token_address,
+    )?);
+    Ok(mint_msgs)
+}
+
+pub fn get_lp_token_supply(deps: Deps, lp_token_addr: &Addr) -> StdResult<Uint128> {
+    let resp: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(lp_token_addr, &Cw20QueryMsg::TokenInfo {})?;
+    Ok(resp.total_supply)
+}
+
+pub fn mint_lp_tokens(
+    recipient: &Addr,
+    liquidity_amount: Uint128,
+    lp_token_address: &Addr,
+) -> StdResult<CosmosMsg> {
+    let mint_msg = Cw20ExecuteMsg::Mint {
+        recipient: recipient.into(),
+        amount: liquidity_amount,
+    };
+    Ok(WasmMsg::Execute {
+        contract_addr: lp_token_address.to_string(),
+        msg: to_binary(&mint_msg)?,
+        funds: vec![],
+    }
+    .into())
+}
+
+pub fn get_token_balance(deps: Deps, contract: &Addr, addr: &Addr) -> StdResult<Uint128> {
+    let resp: BalanceResponse = deps.querier.query_wasm_smart(
+        contract,
+        &Cw20QueryMsg::Balance {
+            address: addr.to_string(),
+        },
+    )?;
+    Ok(resp.balance)
+}
+
+pub fn get_burn_msg(contract: &Addr, owner: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    let msg = Cw20ExecuteMsg::BurnFrom {
+        owner: owner.to_string(),
+        amount,
+    };
+    Ok(WasmMsg::Execute {
+        contract_addr: contract.to_string(),
+        msg: to_binary(&msg)?,
+        funds: vec![],
+    }
+    .into())
+}
+
+pub fn get_burn_received_msg(contract: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    let msg = Cw20ExecuteMsg::Burn { amount };
+    Ok(WasmMsg::Execute {
+        contract_addr: contract.to_string(),
+        msg: to_binary(&msg)?,
+        funds: vec![],
+    }
+    .into())
+}
+
+pub fn query_balance(deps: Deps, address: String) -> StdResult<BalanceResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let lp_token_address = LP_TOKEN.load(deps.storage)?;
+    let balance = get_token_balance(deps, &lp_token_address, &address)?;
+    Ok(BalanceResponse { balance })
+}
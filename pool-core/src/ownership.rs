@@ -0,0 +1,106 @@
+use cosmwasm_std::{attr, Addr, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cw20::Expiration;
+
+use crate::error::PoolError;
+use crate::msg::OwnershipResponse;
+use crate::state::{OwnershipProposal, OWNER, OWNERSHIP_PROPOSAL};
+
+pub fn assert_owner(deps: Deps, sender: &Addr) -> Result<(), PoolError> {
+    match OWNER.load(deps.storage)? {
+        Some(owner) if owner == *sender => Ok(()),
+        _ => Err(PoolError::Unauthorized {}),
+    }
+}
+
+pub fn execute_propose_new_owner(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_owner: String,
+    expiry: Option<Expiration>,
+) -> Result<Response, PoolError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+
+    let new_owner = deps.api.addr_validate(&new_owner)?;
+    let expiry = expiry.unwrap_or_default();
+    if expiry.is_expired(&env.block) {
+        return Err(PoolError::OwnershipProposalExpired {});
+    }
+    OWNERSHIP_PROPOSAL.save(
+        deps.storage,
+        &OwnershipProposal {
+            owner: new_owner.clone(),
+            expiry,
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "propose_new_owner"),
+        attr("pending_owner", new_owner),
+        attr("expiry", expiry.to_string()),
+    ]))
+}
+
+pub fn execute_accept_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, PoolError> {
+    let proposal = OWNERSHIP_PROPOSAL
+        .may_load(deps.storage)?
+        .ok_or(PoolError::NoOwnershipProposal {})?;
+    if info.sender != proposal.owner {
+        return Err(PoolError::Unauthorized {});
+    }
+    if proposal.expiry.is_expired(&env.block) {
+        return Err(PoolError::OwnershipProposalExpired {});
+    }
+
+    let previous_owner = OWNER.load(deps.storage)?;
+    OWNER.save(deps.storage, &Some(proposal.owner.clone()))?;
+    OWNERSHIP_PROPOSAL.remove(deps.storage);
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "accept_ownership"),
+        attr(
+            "previous_owner",
+            previous_owner.map(|o| o.into_string()).unwrap_or_default(),
+        ),
+        attr("new_owner", proposal.owner),
+    ]))
+}
+
+pub fn execute_cancel_ownership_proposal(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, PoolError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    if OWNERSHIP_PROPOSAL.may_load(deps.storage)?.is_none() {
+        return Err(PoolError::NoOwnershipProposal {});
+    }
+    OWNERSHIP_PROPOSAL.remove(deps.storage);
+
+    Ok(Response::new().add_attribute("action", "cancel_ownership_proposal"))
+}
+
+pub fn execute_renounce_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, PoolError> {
+    assert_owner(deps.as_ref(), &info.sender)?;
+    OWNER.save(deps.storage, &None)?;
+    OWNERSHIP_PROPOSAL.remove(deps.storage);
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "renounce_ownership"),
+        attr("previous_owner", info.sender),
+    ]))
+}
+
+pub fn query_ownership(deps: Deps) -> StdResult<OwnershipResponse> {
+    let owner = OWNER.load(deps.storage)?.map(|o| o.into_string());
+    let proposal = OWNERSHIP_PROPOSAL.may_load(deps.storage)?;
+
+    Ok(OwnershipResponse {
+        owner,
+        pending_owner: proposal.as_ref().map(|p| p.owner.to_string()),
+        pending_expiry: proposal.map(|p| p.expiry),
+    })
+}
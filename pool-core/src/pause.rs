@@ -0,0 +1,50 @@
+use cosmwasm_std::{Addr, DepsMut, Event, Response, StdResult, Storage};
+
+use crate::error::PoolError;
+use crate::state::{PauseFlags, OWNER, PAUSE_FLAGS};
+
+pub enum PauseAction {
+    Deposit,
+    Swap,
+    Withdrawal,
+}
+
+pub fn assert_not_paused(storage: &dyn Storage, action: PauseAction) -> Result<(), PoolError> {
+    let flags = PAUSE_FLAGS.load(storage)?;
+    match action {
+        PauseAction::Deposit if flags.deposits => Err(PoolError::DepositsPaused {}),
+        PauseAction::Swap if flags.swaps => Err(PoolError::SwapsPaused {}),
+        PauseAction::Withdrawal if flags.withdrawals => Err(PoolError::WithdrawalsPaused {}),
+        _ => Ok(()),
+    }
+}
+
+// Pauses or unpauses deposits and swaps together, withdrawals keep their flag
+pub fn execute_freeze_deposits(
+    deps: DepsMut,
+    sender: Addr,
+    freeze: bool,
+) -> Result<Response, PoolError> {
+    if let Some(owner) = OWNER.load(deps.storage)? {
+        if sender != owner {
+            return Err(PoolError::UnauthorizedPoolFreeze {});
+        }
+    } else {
+        return Err(PoolError::UnauthorizedPoolFreeze {});
+    }
+
+    PAUSE_FLAGS.update(deps.storage, |flags| -> StdResult<_> {
+        Ok(PauseFlags {
+            deposits: freeze,
+            swaps: freeze,
+            ..flags
+        })
+    })?;
+
+    let freeze_event = Event::new("freeze")
+        .add_attribute("sender", sender)
+        .add_attribute("frozen", freeze.to_string());
+    Ok(Response::new()
+        .add_event(freeze_event)
+        .add_attribute("action", "freezing-contracts"))
+}
@@ -0,0 +1,538 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{
+    attr, Addr, BlockInfo, CosmosMsg, Decimal, Deps, DepsMut, Env, Event, MessageInfo, Response,
+    StdError, StdResult, Storage, Uint128,
+};
+use cw20::{Denom, Expiration};
+
+use crate::curve::PoolCurve;
+use crate::error::PoolError;
+use crate::fee::{get_fee_amount, MAX_FEE_PERCENT};
+use crate::lp_token::{
+    get_burn_msg, get_burn_received_msg, get_instantiate_lp_token_msg, get_liquidity_amount,
+    get_lp_token_supply, get_mint_msgs, get_token_balance, MINIMUM_LIQUIDITY,
+};
+use crate::msg::{FeeResponse, InfoResponse, ProtocolFeesResponse, TokenSelect};
+use crate::ownership::assert_owner;
+use crate::state::{
+    add_reserves, get_swap_tokens, remove_reserves, update_swap_reserves, Fees, PauseFlags,
+    ProtocolFees, Token, FEES, LP_TOKEN, OWNER, PAUSE_FLAGS, PROTOCOL_FEES, TOKEN1, TOKEN2,
+};
+use crate::transfer::{
+    get_bank_transfer_to_msg, get_cw20_transfer_from_msg, get_cw20_transfer_to_msg,
+    get_transfer_to_msg, validate_input_amounts,
+};
+
+// Config every pool starts with, taken from its InstantiateMsg
+pub struct PoolConfig {
+    pub token1_denom: Denom,
+    pub token2_denom: Denom,
+    pub owner: Option<String>,
+    pub protocol_fee_recipient: String,
+    pub protocol_fee_percent: Decimal,
+    pub lp_fee_percent: Decimal,
+    pub lp_token_code_id: u64,
+}
+
+// Saves the tokens, owner and fees of a new pool, nothing is paused and no protocol fee has
+// accrued. The response instantiates the lp token, whose reply goes to save_lp_token.
+pub fn instantiate_pool(
+    deps: DepsMut,
+    env: &Env,
+    config: PoolConfig,
+) -> Result<Response, PoolError> {
+    let token1 = Token {
+        reserve: Uint128::zero(),
+        denom: config.token1_denom,
+    };
+    TOKEN1.save(deps.storage, &token1)?;
+
+    let token2 = Token {
+        denom: config.token2_denom,
+        reserve: Uint128::zero(),
+    };
+    TOKEN2.save(deps.storage, &token2)?;
+
+    let owner = config
+        .owner
+        .map(|h| deps.api.addr_validate(&h))
+        .transpose()?;
+    OWNER.save(deps.storage, &owner)?;
+
+    let protocol_fee_recipient = deps.api.addr_validate(&config.protocol_fee_recipient)?;
+    validate_fee_percent(config.lp_fee_percent, config.protocol_fee_percent)?;
+
+    let fees = Fees {
+        lp_fee_percent: config.lp_fee_percent,
+        protocol_fee_percent: config.protocol_fee_percent,
+        protocol_fee_recipient,
+    };
+    FEES.save(deps.storage, &fees)?;
+
+    // Nothing is paused by default
+    PAUSE_FLAGS.save(deps.storage, &PauseFlags::default())?;
+    PROTOCOL_FEES.save(deps.storage, &ProtocolFees::default())?;
+
+    let reply_msg = get_instantiate_lp_token_msg(config.lp_token_code_id, &env.contract.address)?;
+
+    Ok(Response::new()
+        .add_submessage(reply_msg)
+        .add_attribute("key", "instantiate"))
+}
+
+pub fn check_expiration(
+    expiration: &Option<Expiration>,
+    block: &BlockInfo,
+) -> Result<(), PoolError> {
+    match expiration {
+        Some(e) => {
+            if e.is_expired(block) {
+                return Err(PoolError::MsgExpirationError {});
+            }
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+pub fn validate_fee_percent(
+    lp_fee_percent: Decimal,
+    protocol_fee_percent: Decimal,
+) -> Result<(), PoolError> {
+    let total_fee_percent = lp_fee_percent + protocol_fee_percent;
+    let max_fee_percent = Decimal::from_str(MAX_FEE_PERCENT)?;
+    if total_fee_percent > max_fee_percent {
+        return Err(PoolError::FeesTooHigh {
+            max_fee_percent,
+            total_fee_percent,
+        });
+    }
+    Ok(())
+}
+
+// Burns amount of liquidity out of liquidity_supply, takes the returned tokens from the
+// reserves and returns them, the caller sends them to the provider
+pub fn remove_liquidity(
+    storage: &mut dyn Storage,
+    curve: &dyn PoolCurve,
+    amount: Uint128,
+    liquidity_supply: Uint128,
+    min_token1: Uint128,
+    min_token2: Uint128,
+) -> Result<(Uint128, Uint128), PoolError> {
+    let token1 = TOKEN1.load(storage)?;
+    let token2 = TOKEN2.load(storage)?;
+
+    let (token1_amount, token2_amount) =
+        curve.burn_liquidity(amount, token1.reserve, token2.reserve, liquidity_supply)?;
+    if token1_amount < min_token1 {
+        return Err(PoolError::MinToken1Error {
+            requested: min_token1,
+            available: token1_amount,
+        });
+    }
+    if token2_amount < min_token2 {
+        return Err(PoolError::MinToken2Error {
+            requested: min_token2,
+            available: token2_amount,
+        });
+    }
+
+    remove_reserves(storage, token1_amount, token2_amount)?;
+    Ok((token1_amount, token2_amount))
+}
+
+// Output of selling input_amount at the current reserves and fees
+pub fn get_swap_output(
+    storage: &dyn Storage,
+    curve: &dyn PoolCurve,
+    input_token: &TokenSelect,
+    input_amount: Uint128,
+) -> StdResult<Uint128> {
+    let (input, output) = get_swap_tokens(storage, input_token)?;
+    let fees = FEES.load(storage)?;
+    curve.swap_output(
+        input_token,
+        input_amount,
+        input.reserve,
+        output.reserve,
+        fees.lp_fee_percent + fees.protocol_fee_percent,
+    )
+}
+
+pub fn check_swap_min(min_token: Uint128, token_bought: Uint128) -> Result<(), PoolError> {
+    if min_token > token_bought {
+        return Err(PoolError::SwapMinError {
+            min: min_token,
+            available: token_bought,
+        });
+    }
+    Ok(())
+}
+
+pub fn query_fee(deps: Deps) -> StdResult<FeeResponse> {
+    let fees = FEES.load(deps.storage)?;
+    let owner = OWNER.load(deps.storage)?.map(|o| o.into_string());
+
+    Ok(FeeResponse {
+        owner,
+        lp_fee_percent: fees.lp_fee_percent,
+        protocol_fee_percent: fees.protocol_fee_percent,
+        protocol_fee_recipient: fees.protocol_fee_recipient.into_string(),
+    })
+}
+
+// Tokens moved by a deposit or withdrawal and the lp token supply after it, the response moves
+// the tokens and mints or burns the lp tokens
+pub struct LiquidityChange {
+    pub response: Response,
+    pub token1_amount: Uint128,
+    pub token2_amount: Uint128,
+    pub liquidity_amount: Uint128,
+    pub lp_token_supply: Uint128,
+}
+
+// Deposits token1_amount and the part of max_token2 the curve takes, the rest of a native or
+// received token2 is refunded. Received cw20 tokens are already held by the pool.
+#[allow(clippy::too_many_arguments)]
+pub fn provide_liquidity(
+    deps: DepsMut,
+    info: &MessageInfo,
+    env: &Env,
+    curve: &dyn PoolCurve,
+    token1_amount: Uint128,
+    max_token2: Uint128,
+    min_liquidity: Uint128,
+    expiration: Option<Expiration>,
+    received_token: Option<TokenSelect>,
+) -> Result<LiquidityChange, PoolError> {
+    check_expiration(&expiration, &env.block)?;
+
+    let token1 = TOKEN1.load(deps.storage)?;
+    let token2 = TOKEN2.load(deps.storage)?;
+
+    validate_input_amounts(
+        &info.funds,
+        &[(token1_amount, &token1.denom), (max_token2, &token2.denom)],
+    )?;
+
+    let lp_token_addr = LP_TOKEN.load(deps.storage)?;
+    let lp_token_supply = get_lp_token_supply(deps.as_ref(), &lp_token_addr)?;
+
+    let token2_amount = curve.deposit_token2(
+        token1_amount,
+        max_token2,
+        token1.reserve,
+        token2.reserve,
+        lp_token_supply,
+    )?;
+    if token2_amount > max_token2 {
+        return Err(PoolError::MaxTokenError {
+            max_token: max_token2,
+            tokens_required: token2_amount,
+        });
+    }
+    let liquidity = curve.mint_liquidity(
+        token1_amount,
+        token2_amount,
+        token1.reserve,
+        token2.reserve,
+        lp_token_supply,
+    )?;
+    let liquidity_amount = get_liquidity_amount(liquidity, lp_token_supply, min_liquidity)?;
+
+    // Generate cw20 transfer messages if necessary
+    let mut transfer_msgs: Vec<CosmosMsg> = vec![];
+    if let Denom::Cw20(addr) = token1.denom {
+        if received_token != Some(TokenSelect::Token1) {
+            transfer_msgs.push(get_cw20_transfer_from_msg(
+                &info.sender,
+                &env.contract.address,
+                &addr,
+                token1_amount,
+            )?)
+        }
+    }
+    let refund_amount = max_token2 - token2_amount;
+    match token2.denom {
+        // Refund received token2 sent above what the deposit takes
+        Denom::Cw20(addr) if received_token == Some(TokenSelect::Token2) => {
+            if !refund_amount.is_zero() {
+                transfer_msgs.push(get_cw20_transfer_to_msg(
+                    &info.sender,
+                    &addr,
+                    refund_amount,
+                )?)
+            }
+        }
+        Denom::Cw20(addr) => transfer_msgs.push(get_cw20_transfer_from_msg(
+            &info.sender,
+            &env.contract.address,
+            &addr,
+            token2_amount,
+        )?),
+        // Refund native token2 sent above what the deposit takes
+        Denom::Native(denom) => {
+            if !refund_amount.is_zero() {
+                transfer_msgs.push(get_bank_transfer_to_msg(
+                    &info.sender,
+                    &denom,
+                    refund_amount,
+                ))
+            }
+        }
+    }
+
+    add_reserves(deps.storage, token1_amount, token2_amount)?;
+
+    let mint_msgs = get_mint_msgs(
+        &env.contract.address,
+        &info.sender,
+        liquidity_amount,
+        lp_token_supply,
+        &lp_token_addr,
+    )?;
+    let minted_supply = if lp_token_supply.is_zero() {
+        liquidity_amount + MINIMUM_LIQUIDITY
+    } else {
+        lp_token_supply + liquidity_amount
+    };
+
+    let response = Response::new()
+        .add_messages(transfer_msgs)
+        .add_messages(mint_msgs)
+        .add_attributes(vec![
+            attr("token1_amount", token1_amount),
+            attr("token2_amount", token2_amount),
+            attr("liquidity_received", liquidity_amount),
+        ]);
+    Ok(LiquidityChange {
+        response,
+        token1_amount,
+        token2_amount,
+        liquidity_amount,
+        lp_token_supply: minted_supply,
+    })
+}
+
+// Burns amount of the sender's lp tokens for their share of the reserves. Lp tokens sent back
+// through the cw20 hook are already held by the pool.
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_liquidity(
+    deps: DepsMut,
+    info: &MessageInfo,
+    env: &Env,
+    curve: &dyn PoolCurve,
+    amount: Uint128,
+    min_token1: Uint128,
+    min_token2: Uint128,
+    expiration: Option<Expiration>,
+    lp_received: bool,
+) -> Result<LiquidityChange, PoolError> {
+    check_expiration(&expiration, &env.block)?;
+
+    let lp_token_addr = LP_TOKEN.load(deps.storage)?;
+    let token1 = TOKEN1.load(deps.storage)?;
+    let token2 = TOKEN2.load(deps.storage)?;
+    let total_token_supply = get_lp_token_supply(deps.as_ref(), &lp_token_addr)?;
+
+    if !lp_received {
+        let balance = get_token_balance(deps.as_ref(), &lp_token_addr, &info.sender)?;
+        if amount > balance {
+            return Err(PoolError::InsufficientLiquidityError {
+                requested: amount,
+                available: balance,
+            });
+        }
+    }
+
+    let (token1_amount, token2_amount) = remove_liquidity(
+        deps.storage,
+        curve,
+        amount,
+        total_token_supply,
+        min_token1,
+        min_token2,
+    )?;
+
+    let token1_transfer_msg = get_transfer_to_msg(&info.sender, &token1.denom, token1_amount)?;
+    let token2_transfer_msg = get_transfer_to_msg(&info.sender, &token2.denom, token2_amount)?;
+    let lp_token_burn_msg = if lp_received {
+        get_burn_received_msg(&lp_token_addr, amount)?
+    } else {
+        get_burn_msg(&lp_token_addr, &info.sender, amount)?
+    };
+
+    let response = Response::new()
+        .add_messages(vec![
+            token1_transfer_msg,
+            token2_transfer_msg,
+            lp_token_burn_msg,
+        ])
+        .add_attributes(vec![
+            attr("liquidity_burned", amount),
+            attr("token1_returned", token1_amount),
+            attr("token2_returned", token2_amount),
+        ]);
+    Ok(LiquidityChange {
+        response,
+        token1_amount,
+        token2_amount,
+        liquidity_amount: amount,
+        lp_token_supply: total_token_supply - amount,
+    })
+}
+
+// Swap selling input_amount of input_token, the protocol fee is taken from the input and the
+// rest of it goes to the reserves
+pub struct Swap {
+    pub input_token: TokenSelect,
+    pub input_amount: Uint128,
+    pub output_amount: Uint128,
+    pub protocol_fee_amount: Uint128,
+}
+
+// Swap of input_amount at the current reserves and fees, rejected below min_output
+pub fn get_swap(
+    storage: &dyn Storage,
+    curve: &dyn PoolCurve,
+    input_token: TokenSelect,
+    input_amount: Uint128,
+    min_output: Uint128,
+) -> Result<Swap, PoolError> {
+    let output_amount = get_swap_output(storage, curve, &input_token, input_amount)?;
+    check_swap_min(min_output, output_amount)?;
+
+    let fees = FEES.load(storage)?;
+    Ok(Swap {
+        input_token,
+        input_amount,
+        output_amount,
+        protocol_fee_amount: get_fee_amount(input_amount, fees.protocol_fee_percent)?,
+    })
+}
+
+// Accrues the protocol fee and moves the rest of the input and the output through the
+// reserves. The returned messages pull a cw20 input from the sender unless the pool received
+// it, the caller sends the output.
+pub fn commit_swap(
+    storage: &mut dyn Storage,
+    info: &MessageInfo,
+    env: &Env,
+    swap: &Swap,
+    input_received: bool,
+) -> Result<Vec<CosmosMsg>, PoolError> {
+    let (input_token, _) = get_swap_tokens(storage, &swap.input_token)?;
+
+    // The protocol fee stays in the pool until the recipient collects it
+    PROTOCOL_FEES.update(storage, |mut protocol_fees| -> StdResult<_> {
+        let accrued = match swap.input_token {
+            TokenSelect::Token1 => &mut protocol_fees.token1_amount,
+            TokenSelect::Token2 => &mut protocol_fees.token2_amount,
+        };
+        *accrued = accrued
+            .checked_add(swap.protocol_fee_amount)
+            .map_err(StdError::overflow)?;
+        Ok(protocol_fees)
+    })?;
+
+    update_swap_reserves(
+        storage,
+        &swap.input_token,
+        swap.input_amount - swap.protocol_fee_amount,
+        swap.output_amount,
+    )?;
+
+    Ok(match input_token.denom {
+        Denom::Cw20(addr) if !input_received => vec![get_cw20_transfer_from_msg(
+            &info.sender,
+            &env.contract.address,
+            &addr,
+            swap.input_amount,
+        )?],
+        _ => vec![],
+    })
+}
+
+pub fn execute_collect_protocol_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, PoolError> {
+    let fees = FEES.load(deps.storage)?;
+    if info.sender != fees.protocol_fee_recipient {
+        return Err(PoolError::Unauthorized {});
+    }
+
+    let protocol_fees = PROTOCOL_FEES.load(deps.storage)?;
+    let mut msgs = vec![];
+    for (token, amount) in [
+        (TOKEN1.load(deps.storage)?, protocol_fees.token1_amount),
+        (TOKEN2.load(deps.storage)?, protocol_fees.token2_amount),
+    ] {
+        if amount.is_zero() {
+            continue;
+        }
+        msgs.push(get_transfer_to_msg(&info.sender, &token.denom, amount)?);
+    }
+    PROTOCOL_FEES.save(deps.storage, &ProtocolFees::default())?;
+
+    Ok(Response::new().add_messages(msgs).add_attributes(vec![
+        attr("action", "collect_protocol_fees"),
+        attr("token1_amount", protocol_fees.token1_amount),
+        attr("token2_amount", protocol_fees.token2_amount),
+    ]))
+}
+
+// Owner only, the returned event carries the new fees and the caller adds its own config to it
+pub fn update_fees(
+    deps: DepsMut,
+    sender: &Addr,
+    lp_fee_percent: Decimal,
+    protocol_fee_percent: Decimal,
+    protocol_fee_recipient: String,
+) -> Result<Event, PoolError> {
+    assert_owner(deps.as_ref(), sender)?;
+
+    validate_fee_percent(lp_fee_percent, protocol_fee_percent)?;
+
+    let protocol_fee_recipient = deps.api.addr_validate(&protocol_fee_recipient)?;
+    let updated_fees = Fees {
+        protocol_fee_recipient: protocol_fee_recipient.clone(),
+        lp_fee_percent,
+        protocol_fee_percent,
+    };
+    FEES.save(deps.storage, &updated_fees)?;
+
+    Ok(Event::new("update_config")
+        .add_attribute("sender", sender)
+        .add_attribute("lp_fee_percent", lp_fee_percent.to_string())
+        .add_attribute("protocol_fee_percent", protocol_fee_percent.to_string())
+        .add_attribute("protocol_fee_recipient", protocol_fee_recipient))
+}
+
+pub fn query_info(deps: Deps) -> StdResult<InfoResponse> {
+    let token1 = TOKEN1.load(deps.storage)?;
+    let token2 = TOKEN2.load(deps.storage)?;
+    let lp_token_address = LP_TOKEN.load(deps.storage)?;
+
+    Ok(InfoResponse {
+        token1_reserve: token1.reserve,
+        token1_denom: token1.denom,
+        token2_reserve: token2.reserve,
+        token2_denom: token2.denom,
+        lp_token_supply: get_lp_token_supply(deps, &lp_token_address)?,
+        lp_token_address: lp_token_address.into_string(),
+    })
+}
+
+pub fn query_protocol_fees(deps: Deps) -> StdResult<ProtocolFeesResponse> {
+    let protocol_fees = PROTOCOL_FEES.load(deps.storage)?;
+    let fees = FEES.load(deps.storage)?;
+
+    Ok(ProtocolFeesResponse {
+        token1_amount: protocol_fees.token1_amount,
+        token2_amount: protocol_fees.token2_amount,
+        protocol_fee_recipient: fees.protocol_fee_recipient.into_string(),
+    })
+}
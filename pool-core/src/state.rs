@@ -0,0 +1,124 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Addr, Decimal, StdError, StdResult, Storage, Uint128};
+use cw20::{Denom, Expiration};
+use cw_storage_plus::Item;
+
+use crate::msg::TokenSelect;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Token {
+    pub reserve: Uint128,
+    pub denom: Denom,
+}
+
+pub const TOKEN1: Item<Token> = Item::new("token1");
+pub const TOKEN2: Item<Token> = Item::new("token2");
+
+pub const LP_TOKEN: Item<Addr> = Item::new("lp_token");
+
+pub const OWNER: Item<Option<Addr>> = Item::new("owner");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Fees {
+    pub protocol_fee_recipient: Addr,
+    pub protocol_fee_percent: Decimal,
+    pub lp_fee_percent: Decimal,
+}
+
+pub const FEES: Item<Fees> = Item::new("fees");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnershipProposal {
+    pub owner: Addr,
+    pub expiry: Expiration,
+}
+
+// Pending owner, ownership only moves once the proposed owner accepts it
+pub const OWNERSHIP_PROPOSAL: Item<OwnershipProposal> = Item::new("ownership_proposal");
+
+// Protocol fees taken from swaps, held by the pool until the recipient collects them
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct ProtocolFees {
+    pub token1_amount: Uint128,
+    pub token2_amount: Uint128,
+}
+
+pub const PROTOCOL_FEES: Item<ProtocolFees> = Item::new("protocol_fees");
+
+// Each flag pauses one kind of operation, operations that swap also check the swaps flag
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct PauseFlags {
+    pub deposits: bool,
+    pub swaps: bool,
+    pub withdrawals: bool,
+}
+
+pub const PAUSE_FLAGS: Item<PauseFlags> = Item::new("pause_flags");
+
+// Input and output token of a swap selling input_token
+pub fn get_swap_tokens(
+    storage: &dyn Storage,
+    input_token: &TokenSelect,
+) -> StdResult<(Token, Token)> {
+    let token1 = TOKEN1.load(storage)?;
+    let token2 = TOKEN2.load(storage)?;
+    Ok(match input_token {
+        TokenSelect::Token1 => (token1, token2),
+        TokenSelect::Token2 => (token2, token1),
+    })
+}
+
+pub fn add_reserves(
+    storage: &mut dyn Storage,
+    token1_amount: Uint128,
+    token2_amount: Uint128,
+) -> StdResult<()> {
+    for (item, amount) in [(TOKEN1, token1_amount), (TOKEN2, token2_amount)] {
+        item.update(storage, |mut token| -> StdResult<_> {
+            token.reserve = token
+                .reserve
+                .checked_add(amount)
+                .map_err(StdError::overflow)?;
+            Ok(token)
+        })?;
+    }
+    Ok(())
+}
+
+pub fn remove_reserves(
+    storage: &mut dyn Storage,
+    token1_amount: Uint128,
+    token2_amount: Uint128,
+) -> StdResult<()> {
+    for (item, amount) in [(TOKEN1, token1_amount), (TOKEN2, token2_amount)] {
+        item.update(storage, |mut token| -> StdResult<_> {
+            token.reserve = token
+                .reserve
+                .checked_sub(amount)
+                .map_err(StdError::overflow)?;
+            Ok(token)
+        })?;
+    }
+    Ok(())
+}
+
+// Adds the input that stays in the pool to its reserve and takes the output from the other one
+pub fn update_swap_reserves(
+    storage: &mut dyn Storage,
+    input_token: &TokenSelect,
+    input_amount: Uint128,
+    output_amount: Uint128,
+) -> StdResult<()> {
+    match input_token {
+        TokenSelect::Token1 => {
+            add_reserves(storage, input_amount, Uint128::zero())?;
+            remove_reserves(storage, Uint128::zero(), output_amount)
+        }
+        TokenSelect::Token2 => {
+            add_reserves(storage, Uint128::zero(), input_amount)?;
+            remove_reserves(storage, output_amount, Uint128::zero())
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use cosmwasm_std::{coins, to_binary, Addr, BankMsg, Coin, CosmosMsg, StdResult, Uint128, WasmMsg};
+use cw20::{Cw20ExecuteMsg, Denom};
+
+use crate::error::PoolError;
+
+pub fn get_cw20_transfer_from_msg(
+    owner: &Addr,
+    recipient: &Addr,
+    token_addr: &Addr,
+    token_amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    // create transfer cw20 msg
+    let transfer_cw20_msg = Cw20ExecuteMsg::TransferFrom {
+        owner: owner.into(),
+        recipient: recipient.into(),
+        amount: token_amount,
+    };
+    let exec_cw20_transfer = WasmMsg::Execute {
+        contract_addr: token_addr.into(),
+        msg: to_binary(&transfer_cw20_msg)?,
+        funds: vec![],
+    };
+    let cw20_transfer_cosmos_msg: CosmosMsg = exec_cw20_transfer.into();
+    Ok(cw20_transfer_cosmos_msg)
+}
+
+pub fn get_cw20_transfer_to_msg(
+    recipient: &Addr,
+    token_addr: &Addr,
+    token_amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    // create transfer cw20 msg
+    let transfer_cw20_msg = Cw20ExecuteMsg::Transfer {
+        recipient: recipient.into(),
+        amount: token_amount,
+    };
+    let exec_cw20_transfer = WasmMsg::Execute {
+        contract_addr: token_addr.into(),
+        msg: to_binary(&transfer_cw20_msg)?,
+        funds: vec![],
+    };
+    let cw20_transfer_cosmos_msg: CosmosMsg = exec_cw20_transfer.into();
+    Ok(cw20_transfer_cosmos_msg)
+}
+
+pub fn get_bank_transfer_to_msg(
+    recipient: &Addr,
+    denom: &str,
+    native_amount: Uint128,
+) -> CosmosMsg {
+    let transfer_bank_msg = BankMsg::Send {
+        to_address: recipient.into(),
+        amount: coins(native_amount.u128(), denom),
+    };
+
+    let transfer_bank_cosmos_msg: CosmosMsg = transfer_bank_msg.into();
+    transfer_bank_cosmos_msg
+}
+
+// Sends amount of a token held by the pool to recipient, whatever its denom
+pub fn get_transfer_to_msg(
+    recipient: &Addr,
+    denom: &Denom,
+    amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    match denom {
+        Denom::Cw20(addr) => get_cw20_transfer_to_msg(recipient, addr, amount),
+        Denom::Native(denom) => Ok(get_bank_transfer_to_msg(recipient, denom, amount)),
+    }
+}
+
+fn get_amount_for_denom(coins: &[Coin], denom: &str) -> Uint128 {
+    coins
+        .iter()
+        .filter(|c| c.denom == denom)
+        .map(|c| c.amount)
+        .sum()
+}
+
+// Native funds must match the native inputs exactly, any other coin would be stuck in the pool
+pub fn validate_input_amounts(
+    actual_funds: &[Coin],
+    inputs: &[(Uint128, &Denom)],
+) -> Result<(), PoolError> {
+    for (given_amount, given_denom) in inputs {
+        let denom = match given_denom {
+            Denom::Cw20(_) => continue,
+            Denom::Native(denom) => denom,
+        };
+        let actual_amount = get_amount_for_denom(actual_funds, denom);
+        if actual_amount.is_zero() && !given_amount.is_zero() {
+            if let Some(coin) = actual_funds.iter().find(|c| !c.amount.is_zero()) {
+                return Err(PoolError::IncorrectNativeDenom {
+                    provided: coin.denom.clone(),
+                    required: denom.clone(),
+                });
+            }
+        }
+        if actual_amount != *given_amount {
+            return Err(PoolError::InsufficientFunds {});
+        }
+    }
+
+    for coin in actual_funds.iter().filter(|c| !c.amount.is_zero()) {
+        let expected = inputs.iter().any(|(amount, denom)| {
+            !amount.is_zero() && matches!(denom, Denom::Native(d) if *d == coin.denom)
+        });
+        if !expected {
+            return Err(PoolError::UnexpectedFunds {
+                denom: coin.denom.clone(),
+            });
+        }
+    }
+    Ok(())
+}
@@ -1,13 +1,13 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::WasmMsg::Execute;
 use cosmwasm_std::{
-    to_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError,
-    StdResult, Uint64,
+    to_binary, Addr, Api, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Order,
+    Response, StdError, StdResult, Uint128, Uint64, WasmMsg,
 };
 use cw2::set_contract_version;
 use cw_storage_plus::Bound;
 use cw_utils::{Duration, Scheduled};
+use std::collections::BTreeMap;
 use std::ops::Add;
 
 use crate::error::ContractError;
@@ -76,8 +76,7 @@ pub fn execute(
 ) -> Result<Response, ContractError> {
     match msg {
         ExecuteMsg::Schedule {
-            target_address,
-            data,
+            msgs,
             title,
             description,
             execution_time,
@@ -86,8 +85,7 @@ pub fn execute(
             deps,
             _env,
             info,
-            target_address,
-            data,
+            msgs,
             title,
             description,
             execution_time,
@@ -117,15 +115,32 @@ pub fn execute_schedule(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    target_address: String,
-    data: Binary,
+    msgs: Vec<CosmosMsg>,
     title: String,
     description: String,
     execution_time: Scheduled,
     executor_list: Option<Vec<String>>,
 ) -> Result<Response, ContractError> {
-    let sender = deps.api.addr_validate(&info.sender.to_string())?;
-    let target = deps.api.addr_validate(&target_address)?;
+    let sender = info.sender;
+    if msgs.is_empty() {
+        return Err(ContractError::NoMessages {});
+    }
+    for msg in &msgs {
+        validate_msg(deps.api, msg)?;
+    }
+
+    // Each operation only spends its own escrow, never funds held for other operations
+    let funds = sum_coins(&info.funds)?;
+    for (denom, required) in required_funds(&msgs)? {
+        let escrowed = funds.get(&denom).copied().unwrap_or_default();
+        if required > escrowed {
+            return Err(ContractError::InsufficientFunds {
+                denom,
+                required,
+                escrowed,
+            });
+        }
+    }
 
     let timelock = CONFIG.load(deps.storage)?;
     if !(timelock.proposers.contains(&sender)) {
@@ -156,8 +171,8 @@ pub fn execute_schedule(
         proposer: sender,
         executors,
         execution_time,
-        target,
-        data,
+        msgs,
+        funds: to_coins(funds),
         title,
         description,
     };
@@ -167,10 +182,78 @@ pub fn execute_schedule(
         .add_attribute("Schedule ", "success")
         .add_attribute("Operation ID: ", id)
         .add_attribute("Proposer: ", new_operation.proposer)
-        .add_attribute("Target Address: ", new_operation.target.to_string())
+        .add_attribute("Messages: ", new_operation.msgs.len().to_string())
+        .add_attribute("Funds: ", coins_to_string(&new_operation.funds))
         .add_attribute("Execution Time: ", new_operation.execution_time.to_string()))
 }
 
+// Addresses are checked when scheduling, so a typo doesn't surface only after the delay.
+// Only messages whose funds are accounted to the operation are accepted, staking and
+// distribution messages could move stake or rewards funded by another operation's escrow.
+fn validate_msg(api: &dyn Api, msg: &CosmosMsg) -> Result<(), ContractError> {
+    match msg {
+        CosmosMsg::Bank(BankMsg::Send { to_address, .. }) => {
+            api.addr_validate(to_address)?;
+        }
+        CosmosMsg::Bank(BankMsg::Burn { .. }) | CosmosMsg::Wasm(WasmMsg::Instantiate { .. }) => {}
+        CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. })
+        | CosmosMsg::Wasm(WasmMsg::Migrate { contract_addr, .. })
+        | CosmosMsg::Wasm(WasmMsg::ClearAdmin { contract_addr }) => {
+            api.addr_validate(contract_addr)?;
+        }
+        CosmosMsg::Wasm(WasmMsg::UpdateAdmin {
+            contract_addr,
+            admin,
+        }) => {
+            api.addr_validate(contract_addr)?;
+            api.addr_validate(admin)?;
+        }
+        _ => return Err(ContractError::UnsupportedMsg {}),
+    }
+    Ok(())
+}
+
+// Native funds the messages spend, per denom
+fn required_funds(msgs: &[CosmosMsg]) -> StdResult<BTreeMap<String, Uint128>> {
+    let coins = msgs.iter().flat_map(|msg| match msg {
+        CosmosMsg::Bank(BankMsg::Send { amount, .. })
+        | CosmosMsg::Bank(BankMsg::Burn { amount }) => amount.as_slice(),
+        CosmosMsg::Wasm(WasmMsg::Execute { funds, .. })
+        | CosmosMsg::Wasm(WasmMsg::Instantiate { funds, .. }) => funds.as_slice(),
+        _ => &[],
+    });
+    sum_coins(coins)
+}
+
+fn sum_coins<'a>(
+    coins: impl IntoIterator<Item = &'a Coin>,
+) -> StdResult<BTreeMap<String, Uint128>> {
+    let mut sums = BTreeMap::<String, Uint128>::new();
+    for coin in coins {
+        let sum = sums.entry(coin.denom.clone()).or_default();
+        *sum = sum.checked_add(coin.amount).map_err(StdError::overflow)?;
+    }
+    Ok(sums)
+}
+
+fn to_coins(sums: BTreeMap<String, Uint128>) -> Vec<Coin> {
+    sums.into_iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(denom, amount)| Coin { denom, amount })
+        .collect()
+}
+
+fn coins_to_string(coins: &[Coin]) -> String {
+    if coins.is_empty() {
+        return "none".to_string();
+    }
+    coins
+        .iter()
+        .map(|coin| coin.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 pub fn execute_execute(
     deps: DepsMut,
     env: Env,
@@ -202,13 +285,28 @@ pub fn execute_execute(
     operation.status = OperationStatus::Done;
     OPERATION_LIST.save(deps.storage, operation_id.u64(), &operation)?;
 
+    // The escrow the messages don't spend goes back to the proposer
+    let mut remainder = sum_coins(&operation.funds)?;
+    for (denom, required) in required_funds(&operation.msgs)? {
+        if let Some(escrowed) = remainder.get_mut(&denom) {
+            *escrowed = escrowed.checked_sub(required).map_err(StdError::overflow)?;
+        }
+    }
+    let remainder = to_coins(remainder);
+    let mut msgs = operation.msgs;
+    if !remainder.is_empty() {
+        msgs.push(
+            BankMsg::Send {
+                to_address: operation.proposer.to_string(),
+                amount: remainder,
+            }
+            .into(),
+        );
+    }
+
     Ok(Response::new()
-        .add_message(CosmosMsg::Wasm(Execute {
-            contract_addr: operation.target.to_string(),
-            msg: operation.data,
-            funds: vec![],
-        }))
-        .add_attribute("executor", &info.sender.to_string()))
+        .add_messages(msgs)
+        .add_attribute("executor", info.sender))
 }
 
 pub fn execute_cancel(
@@ -229,9 +327,19 @@ pub fn execute_cancel(
 
     OPERATION_LIST.remove(deps.storage, operation_id.u64());
 
+    // Escrowed funds go back to the proposer
+    let mut msgs = vec![];
+    if !operation.funds.is_empty() {
+        msgs.push(BankMsg::Send {
+            to_address: operation.proposer.to_string(),
+            amount: operation.funds,
+        });
+    }
+
     Ok(Response::new()
+        .add_messages(msgs)
         .add_attribute("Method", "cancel")
-        .add_attribute("sender", &info.sender.to_string())
+        .add_attribute("sender", info.sender.to_string())
         .add_attribute("operation_id", operation_id.to_string())
         .add_attribute("Result", "Success"))
 }
@@ -355,7 +463,7 @@ pub fn execute_update_min_delay(
     CONFIG.save(deps.storage, &timelock)?;
     Ok(Response::new()
         .add_attribute("Method", "Update Min Delay")
-        .add_attribute("Sender", &info.sender.to_string())
+        .add_attribute("Sender", info.sender.to_string())
         .add_attribute("New Min Delay", timelock.min_time_delay.to_string())
         .add_attribute("Result", "Success"))
 }
@@ -461,7 +569,7 @@ pub fn query_get_executors(deps: Deps, operation_id: Uint64) -> StdResult<Vec<Ad
 mod tests {
     use super::*;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::Timestamp;
+    use cosmwasm_std::{coins, SubMsg, Timestamp};
     use cw_utils::Scheduled;
 
     #[test]
@@ -481,14 +589,17 @@ mod tests {
         let res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
         println!("{:?}", res);
 
-        let data = to_binary(&"data").unwrap();
+        let msgs = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "target".to_string(),
+            msg: to_binary(&"data").unwrap(),
+            funds: vec![],
+        })];
         // try Schedule() with sender "creator"
         let res = execute_schedule(
             deps.as_mut(),
             env.clone(),
             info.clone(),
-            "target".to_string(),
-            data.clone(),
+            msgs.clone(),
             title.clone(),
             description.clone(),
             Scheduled::AtTime(Timestamp::from_seconds(10)),
@@ -504,8 +615,7 @@ mod tests {
             deps.as_mut(),
             env.clone(),
             info.clone(),
-            "target".to_string(),
-            data.clone(),
+            msgs.clone(),
             title.clone(),
             description.clone(),
             Scheduled::AtTime(Timestamp::from_seconds(1)),
@@ -519,8 +629,7 @@ mod tests {
             deps.as_mut(),
             env.clone(),
             info.clone(),
-            "target".to_string(),
-            data.clone(),
+            msgs.clone(),
             title.clone(),
             description.clone(),
             Scheduled::AtTime(Timestamp::from_seconds(120)),
@@ -562,7 +671,11 @@ mod tests {
         let res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
         println!("{:?}", res);
 
-        let data = to_binary(&"data").unwrap();
+        let msgs = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "target".to_string(),
+            msg: to_binary(&"data").unwrap(),
+            funds: vec![],
+        })];
         let description = "test desc".to_string();
         //change sender to prop1
         let info = mock_info("prop1", &[]);
@@ -572,8 +685,7 @@ mod tests {
             deps.as_mut(),
             env.clone(),
             info.clone(),
-            "target".to_string(),
-            data.clone(),
+            msgs.clone(),
             title.clone(),
             description.clone(),
             Scheduled::AtTime(Timestamp::from_seconds(120)),
@@ -617,7 +729,11 @@ mod tests {
         let res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
         println!("{:?}", res);
 
-        let data = to_binary(&"data").unwrap();
+        let msgs = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "target".to_string(),
+            msg: to_binary(&"data").unwrap(),
+            funds: vec![],
+        })];
         let description = "test desc".to_string();
 
         //change sender to prop1
@@ -628,8 +744,7 @@ mod tests {
             deps.as_mut(),
             env.clone(),
             info.clone(),
-            "target".to_string(),
-            data.clone(),
+            msgs.clone(),
             title.clone(),
             description.clone(),
             Scheduled::AtTime(Timestamp::from_seconds(120)),
@@ -656,8 +771,7 @@ mod tests {
             deps.as_mut(),
             env.clone(),
             info.clone(),
-            "target".to_string(),
-            data.clone(),
+            msgs.clone(),
             title.clone(),
             description.clone(),
             Scheduled::AtTime(Timestamp::from_seconds(140)),
@@ -680,8 +794,7 @@ mod tests {
             deps.as_mut(),
             env.clone(),
             info.clone(),
-            "target".to_string(),
-            data.clone(),
+            msgs.clone(),
             title.clone(),
             description.clone(),
             Scheduled::AtTime(Timestamp::from_seconds(140)),
@@ -697,6 +810,123 @@ mod tests {
         assert_eq!(res, ContractError::Unauthorized {});
     }
 
+    #[test]
+    fn test_escrowed_funds() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(100);
+        let msg = InstantiateMsg {
+            admins: Option::None,
+            proposers: vec!["prop1".to_string()],
+            min_delay: Duration::Time(10),
+        };
+        let info = mock_info("creator", &[]);
+
+        // instantiate
+        instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let title = "Title Example ".to_string();
+        let description = "test desc".to_string();
+        let msgs = vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "recipient".to_string(),
+                amount: coins(60, "ujuno"),
+            }),
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "target".to_string(),
+                msg: to_binary(&"data").unwrap(),
+                funds: coins(40, "ujuno"),
+            }),
+            CosmosMsg::Wasm(WasmMsg::UpdateAdmin {
+                contract_addr: "target".to_string(),
+                admin: "new_admin".to_string(),
+            }),
+        ];
+        let info = mock_info("prop1", &coins(100, "ujuno"));
+
+        //try Schedule() without messages
+        let res = execute_schedule(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            vec![],
+            title.clone(),
+            description.clone(),
+            Scheduled::AtTime(Timestamp::from_seconds(120)),
+            Option::None,
+        )
+        .unwrap_err();
+        assert_eq!(res, ContractError::NoMessages {});
+
+        //try Schedule() with less than the messages spend
+        let res = execute_schedule(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("prop1", &coins(90, "ujuno")),
+            msgs.clone(),
+            title.clone(),
+            description.clone(),
+            Scheduled::AtTime(Timestamp::from_seconds(120)),
+            Option::None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            res,
+            ContractError::InsufficientFunds {
+                denom: "ujuno".to_string(),
+                required: Uint128::new(100),
+                escrowed: Uint128::new(90),
+            }
+        );
+
+        //Schedule() sender "prop1" with 100ujuno escrowed
+        execute_schedule(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            msgs.clone(),
+            title.clone(),
+            description.clone(),
+            Scheduled::AtTime(Timestamp::from_seconds(120)),
+            Option::None,
+        )
+        .unwrap();
+        let res = query_get_operations(deps.as_ref(), Option::None, Option::None).unwrap();
+        assert_eq!(res.operationList[0].msgs, msgs);
+        assert_eq!(res.operationList[0].funds, coins(100, "ujuno"));
+
+        //Schedule() and Cancel() refunds the escrow to the proposer
+        execute_schedule(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            msgs.clone(),
+            title.clone(),
+            description.clone(),
+            Scheduled::AtTime(Timestamp::from_seconds(120)),
+            Option::None,
+        )
+        .unwrap();
+        let res = execute_cancel(deps.as_mut(), env.clone(), info.clone(), Uint64::new(2)).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "prop1".to_string(),
+                amount: coins(100, "ujuno"),
+            })]
+        );
+
+        //time pass
+        env.block.time = Timestamp::from_seconds(120);
+
+        //Execute() forwards the messages with their funds
+        let res = execute_execute(deps.as_mut(), env, info, Uint64::new(1)).unwrap();
+        assert_eq!(
+            res.messages,
+            msgs.into_iter().map(SubMsg::new).collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn test_add_remove_proposer() {
         let mut deps = mock_dependencies();
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -29,6 +29,19 @@ pub enum ContractError {
 
     #[error("Changes can not be made on a frozen Timelock contract.")]
     TimelockFrozen {},
+
+    #[error("Operations must contain at least one message.")]
+    NoMessages {},
+
+    #[error("The messages need {required}{denom} but only {escrowed}{denom} were sent.")]
+    InsufficientFunds {
+        denom: String,
+        required: Uint128,
+        escrowed: Uint128,
+    },
+
+    #[error("Operations can only contain bank send, bank burn and wasm messages.")]
+    UnsupportedMsg {},
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
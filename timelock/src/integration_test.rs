@@ -0,0 +1,199 @@
+#![cfg(test)]
+
+use cosmwasm_std::{
+    coin, coins, Addr, BankMsg, Coin, CosmosMsg, DistributionMsg, Empty, StakingMsg, Uint128,
+    Uint64,
+};
+use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+use cw_utils::{Duration, Scheduled};
+
+use crate::msg::{ExecuteMsg, InstantiateMsg};
+use crate::ContractError;
+
+const DENOM: &str = "ujuno";
+
+fn contract_timelock() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        crate::contract::execute,
+        crate::contract::instantiate,
+        crate::contract::query,
+    );
+    Box::new(contract)
+}
+
+fn bank_balance(router: &App, addr: &Addr) -> Uint128 {
+    router.wrap().query_balance(addr, DENOM).unwrap().amount
+}
+
+fn schedule_send(recipient: &str, amount: u128, execution_time: Scheduled) -> ExecuteMsg {
+    ExecuteMsg::Schedule {
+        msgs: vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins(amount, DENOM),
+        })],
+        title: "Send".to_string(),
+        description: "Send from the escrow".to_string(),
+        execution_time,
+        executors: None,
+    }
+}
+
+#[test]
+fn test_operations_only_spend_their_escrow() {
+    let prop1 = Addr::unchecked("prop1");
+    let prop2 = Addr::unchecked("prop2");
+    let mut router = App::new(|router, _, storage| {
+        for proposer in [&prop1, &prop2] {
+            router
+                .bank
+                .init_balance(storage, proposer, coins(1000, DENOM))
+                .unwrap();
+        }
+    });
+
+    let timelock_id = router.store_code(contract_timelock());
+    let msg = InstantiateMsg {
+        admins: None,
+        proposers: vec![prop1.to_string(), prop2.to_string()],
+        min_delay: Duration::Time(10),
+    };
+    let timelock = router
+        .instantiate_contract(timelock_id, prop1.clone(), &msg, &[], "timelock", None)
+        .unwrap();
+    let execution_time = Scheduled::AtTime(router.block_info().time.plus_seconds(20));
+
+    // Operation 1 escrows exactly what it sends
+    router
+        .execute_contract(
+            prop1.clone(),
+            timelock.clone(),
+            &schedule_send("alice", 100, execution_time),
+            &coins(100, DENOM),
+        )
+        .unwrap();
+    assert_eq!(bank_balance(&router, &timelock), Uint128::new(100));
+
+    // Operation 2 can't be scheduled to send the escrow of operation 1
+    for funds in [vec![], coins(50, DENOM)] {
+        let escrowed = funds.first().map_or(Uint128::zero(), |coin| coin.amount);
+        let err = router
+            .execute_contract(
+                prop2.clone(),
+                timelock.clone(),
+                &schedule_send("mallory", 100, execution_time),
+                &funds,
+            )
+            .unwrap_err();
+        assert_eq!(
+            ContractError::InsufficientFunds {
+                denom: DENOM.to_string(),
+                required: Uint128::new(100),
+                escrowed,
+            },
+            err.downcast().unwrap()
+        );
+    }
+
+    // Operation 2 escrows more than it sends, the rest goes back to its proposer
+    router
+        .execute_contract(
+            prop2.clone(),
+            timelock.clone(),
+            &schedule_send("mallory", 100, execution_time),
+            &[Coin::new(100, DENOM), Coin::new(50, DENOM)],
+        )
+        .unwrap();
+    assert_eq!(bank_balance(&router, &timelock), Uint128::new(250));
+
+    router.update_block(|block| block.time = block.time.plus_seconds(20));
+    let execute_msg = ExecuteMsg::Execute {
+        operation_id: Uint64::new(2),
+    };
+    router
+        .execute_contract(prop2.clone(), timelock.clone(), &execute_msg, &[])
+        .unwrap();
+    assert_eq!(
+        bank_balance(&router, &Addr::unchecked("mallory")),
+        Uint128::new(100)
+    );
+    assert_eq!(bank_balance(&router, &prop2), Uint128::new(900));
+
+    // Operation 1 still has its whole escrow
+    assert_eq!(bank_balance(&router, &timelock), Uint128::new(100));
+    let execute_msg = ExecuteMsg::Execute {
+        operation_id: Uint64::new(1),
+    };
+    router
+        .execute_contract(prop1.clone(), timelock.clone(), &execute_msg, &[])
+        .unwrap();
+    assert_eq!(
+        bank_balance(&router, &Addr::unchecked("alice")),
+        Uint128::new(100)
+    );
+    assert_eq!(bank_balance(&router, &prop1), Uint128::new(900));
+    assert_eq!(bank_balance(&router, &timelock), Uint128::zero());
+}
+
+#[test]
+fn test_staking_and_distribution_msgs_are_rejected() {
+    let proposer = Addr::unchecked("proposer");
+    let mut router = App::new(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &proposer, coins(1000, DENOM))
+            .unwrap();
+    });
+
+    let timelock_id = router.store_code(contract_timelock());
+    let msg = InstantiateMsg {
+        admins: None,
+        proposers: vec![proposer.to_string()],
+        min_delay: Duration::Time(10),
+    };
+    let timelock = router
+        .instantiate_contract(timelock_id, proposer.clone(), &msg, &[], "timelock", None)
+        .unwrap();
+    let execution_time = Scheduled::AtTime(router.block_info().time.plus_seconds(20));
+
+    // The stake and rewards of the Timelock aren't tracked per operation, so an operation
+    // can't undelegate or redirect what another operation's escrow funded
+    let msgs = [
+        CosmosMsg::Staking(StakingMsg::Undelegate {
+            validator: "validator".to_string(),
+            amount: coin(100, DENOM),
+        }),
+        CosmosMsg::Staking(StakingMsg::Redelegate {
+            src_validator: "validator".to_string(),
+            dst_validator: "other_validator".to_string(),
+            amount: coin(100, DENOM),
+        }),
+        CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: "validator".to_string(),
+            amount: coin(100, DENOM),
+        }),
+        CosmosMsg::Distribution(DistributionMsg::SetWithdrawAddress {
+            address: "mallory".to_string(),
+        }),
+        CosmosMsg::Distribution(DistributionMsg::WithdrawDelegatorReward {
+            validator: "validator".to_string(),
+        }),
+    ];
+    for msg in msgs {
+        let schedule_msg = ExecuteMsg::Schedule {
+            msgs: vec![msg],
+            title: "Stake".to_string(),
+            description: "Move the stake".to_string(),
+            execution_time,
+            executors: None,
+        };
+        let err = router
+            .execute_contract(
+                proposer.clone(),
+                timelock.clone(),
+                &schedule_msg,
+                &coins(100, DENOM),
+            )
+            .unwrap_err();
+        assert_eq!(ContractError::UnsupportedMsg {}, err.downcast().unwrap());
+    }
+}
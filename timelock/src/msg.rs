@@ -1,5 +1,5 @@
 use crate::state::{Operation, OperationStatus};
-use cosmwasm_std::{Addr, Binary, Uint64};
+use cosmwasm_std::{Addr, Coin, CosmosMsg, Uint64};
 use cw_utils::{Duration, Scheduled};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -14,9 +14,9 @@ pub struct InstantiateMsg {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    // Native funds sent along are escrowed for the messages
     Schedule {
-        target_address: String,
-        data: Binary,
+        msgs: Vec<CosmosMsg>,
         title: String,
         description: String,
         execution_time: Scheduled,
@@ -83,8 +83,8 @@ pub struct OperationResponse {
     pub proposer: Addr,
     pub executors: Option<Vec<Addr>>,
     pub execution_time: Scheduled,
-    pub target: Addr,
-    pub data: Binary,
+    pub msgs: Vec<CosmosMsg>,
+    pub funds: Vec<Coin>,
     pub title: String,
     pub description: String,
 }
@@ -98,8 +98,8 @@ impl From<Operation> for OperationResponse {
             proposer: operation.proposer,
             executors: operation.executors,
             execution_time: operation.execution_time,
-            target: operation.target,
-            data: operation.data,
+            msgs: operation.msgs,
+            funds: operation.funds,
             title: operation.title,
             description: operation.description,
         }
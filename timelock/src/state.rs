@@ -1,7 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Binary, Uint64};
+use cosmwasm_std::{Addr, Coin, CosmosMsg, Uint64};
 use cw_storage_plus::{Item, Map};
 use cw_utils::{Duration, Scheduled};
 
@@ -20,8 +20,9 @@ pub struct Operation {
     pub proposer: Addr,
     pub executors: Option<Vec<Addr>>,
     pub execution_time: Scheduled,
-    pub target: Addr,
-    pub data: Binary,
+    pub msgs: Vec<CosmosMsg>,
+    // Native funds sent with the schedule, held by the timelock until execution
+    pub funds: Vec<Coin>,
     pub title: String,
     pub description: String,
 }